@@ -0,0 +1,36 @@
+// Renders a small scene and reads the framebuffer back, rather than writing an image, to print the
+// average colour of the render.
+//
+//     cargo run --release --example average_color
+
+use std::sync::Arc;
+
+use raytracing::{CameraBuilder, Color, HittableList, Lambertian, Point3, Sphere, Vec3};
+
+fn main() {
+    let mut world = HittableList::default();
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let red = Arc::new(Lambertian::new(Color::new(0.7, 0.1, 0.1)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, ground)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, red)));
+
+    let cam = CameraBuilder::new()
+        .width(160)
+        .samples_per_pixel(16)
+        .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build();
+
+    let fb = cam.render(&world);
+
+    let mut sum = Color::default();
+    for y in 0..fb.height() {
+        for x in 0..fb.width() {
+            sum += fb.pixel(x, y);
+        }
+    }
+    let average = sum / (fb.width() * fb.height()) as f64;
+
+    println!("{}x{} at {} spp", fb.width(), fb.height(), fb.samples());
+    println!("average colour: {:.3} {:.3} {:.3}", average.x, average.y, average.z);
+}
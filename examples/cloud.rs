@@ -0,0 +1,34 @@
+// A procedural cloud lit by the afternoon sun, above a grey ground.
+//
+//     cargo run --release --example cloud > cloud.ppm
+
+use std::io;
+use std::sync::Arc;
+
+use raytracing::{CameraBuilder, Color, HenyeyGreenstein, HittableList, Lambertian, Point3, PreethamSky, Sphere, Vec3, Volume, VoxelGrid};
+
+fn main() {
+    let mut world = HittableList::default();
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.4, 0.4, 0.4)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -1001.0, 0.0), 1000.0, ground)));
+
+    // water droplets scatter almost all the light that hits them, mostly onwards
+    let grid = Arc::new(VoxelGrid::cloud(64, 3));
+    let droplets = Arc::new(HenyeyGreenstein::new(Color::new(0.99, 0.99, 0.99), 0.6));
+    world.add(Arc::new(Volume::new(grid, Point3::new(-1.5, -0.5, -1.5), Point3::new(1.5, 2.5, 1.5), 8.0, droplets)));
+
+    let sky = Arc::new(PreethamSky::new(PreethamSky::sun_direction(30.0, 60.0), 3.0, 1.0));
+    let cam = CameraBuilder::new()
+        .aspect_ratio(16.0 / 9.0)
+        .width(480)
+        .samples_per_pixel(64)
+        .max_depth(100)
+        .field_of_view(40.0)
+        .environment(sky)
+        .set_view(Point3::new(0.0, 1.0, 7.0), Point3::new(0.0, 0.8, 0.0), Vec3::new(0,1,0))
+        .build();
+
+    let fb = cam.render(&world);
+    fb.write_ppm(&mut io::stdout().lock()).expect("failed to write image");
+}
@@ -0,0 +1,62 @@
+// A row of spheres made with the principled material on a checkered floor: plastic, brushed gold,
+// clearcoated car paint, velvet and frosted glass.
+//
+//     cargo run --release --example principled > principled.ppm
+
+use std::io;
+use std::sync::Arc;
+
+use raytracing::{CameraBuilder, CheckerTexture, Color, HittableList, Point3, PrincipledBuilder, Sphere, Vec3};
+
+fn main() {
+    let mut world = HittableList::default();
+
+    let checker = Arc::new(CheckerTexture::new(0.5, Arc::new(Color::new(0.2, 0.3, 0.1)), Arc::new(Color::new(0.9, 0.9, 0.9))));
+    let floor = PrincipledBuilder::new().base_color(checker).roughness(Arc::new(0.8)).build();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.5, -1.0), 1000.0, Arc::new(floor))));
+
+    let materials = [
+        PrincipledBuilder::new()
+            .base_color(Arc::new(Color::new(0.7, 0.1, 0.1)))
+            .roughness(Arc::new(0.2))
+            .build(),
+        PrincipledBuilder::new()
+            .base_color(Arc::new(Color::new(1.0, 0.78, 0.34)))
+            .metallic(Arc::new(1.0))
+            .roughness(Arc::new(0.35))
+            .build(),
+        PrincipledBuilder::new()
+            .base_color(Arc::new(Color::new(0.05, 0.1, 0.4)))
+            .metallic(Arc::new(0.5))
+            .roughness(Arc::new(0.4))
+            .clearcoat(Arc::new(1.0))
+            .build(),
+        PrincipledBuilder::new()
+            .base_color(Arc::new(Color::new(0.4, 0.05, 0.3)))
+            .roughness(Arc::new(1.0))
+            .specular(Arc::new(0.0))
+            .sheen(Arc::new(1.0))
+            .build(),
+        PrincipledBuilder::new()
+            .base_color(Arc::new(Color::new(0.9, 1.0, 0.95)))
+            .roughness(Arc::new(0.25))
+            .transmission(Arc::new(1.0))
+            .build(),
+    ];
+    for (i, material) in materials.into_iter().enumerate() {
+        let x = (i as f64 - 2.0) * 1.1;
+        world.add(Arc::new(Sphere::new(Point3::new(x, 0.0, -1.0), 0.5, Arc::new(material))));
+    }
+
+    let cam = CameraBuilder::new()
+        .aspect_ratio(2.0)
+        .width(600)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .field_of_view(30.0)
+        .set_view(Point3::new(0.0, 1.5, 8.0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build();
+
+    let fb = cam.render(&world);
+    fb.write_ppm(&mut io::stdout().lock()).expect("failed to write image");
+}
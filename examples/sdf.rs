@@ -0,0 +1,58 @@
+// Shapes made from signed distance functions: a rounded box blended into a sphere, a twisted box, a
+// torus, a row of capsules and a box with a sphere carved out of it.
+//
+//     cargo run --release --example sdf > sdf.ppm
+
+use std::io;
+use std::sync::Arc;
+
+use raytracing::{CameraBuilder, Color, HittableList, Lambertian, Metal, Point3, Repetition, Sdf, SdfBox, SdfCapsule,
+                 SdfHittable, SdfRoundedBox, SdfSphere, SdfTorus, SmoothUnion, Sphere, Subtraction, Twist, Vec3};
+
+fn main() {
+    let mut world = HittableList::default();
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground)));
+
+    let blob: Arc<dyn Sdf> = Arc::new(SmoothUnion::new(
+        Arc::new(SdfRoundedBox::new(Point3::new(-2.2, 0.4, 0.0), Vec3::new(0.4, 0.4, 0.4), 0.1)),
+        Arc::new(SdfSphere::new(Point3::new(-2.2, 0.9, 0.0), 0.35)),
+        0.3,
+    ));
+    let twisted: Arc<dyn Sdf> = Arc::new(Twist::new(Arc::new(SdfBox::new(Point3::new(0.0, 0.6, 0.0), Vec3::new(0.25, 0.6, 0.25))), 1.5));
+    let torus: Arc<dyn Sdf> = Arc::new(SdfTorus::new(Point3::new(1.1, 0.15, 0.0), 0.4, 0.15));
+    let capsules: Arc<dyn Sdf> = Arc::new(Repetition::new(
+        Arc::new(SdfCapsule::new(Point3::new(-2.0, 0.1, 1.4), Point3::new(-2.0, 0.5, 1.4), 0.1)),
+        Vec3::new(0.5, 0.0, 0.0),
+        [9, 1, 1],
+    ));
+    let carved: Arc<dyn Sdf> = Arc::new(Subtraction::new(
+        Arc::new(SdfBox::new(Point3::new(2.4, 0.4, 0.0), Vec3::new(0.4, 0.4, 0.4))),
+        Arc::new(SdfSphere::new(Point3::new(2.4, 0.8, 0.4), 0.45)),
+        0.05,
+    ));
+
+    let materials: [Arc<dyn raytracing::Material>; 5] = [
+        Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.2))),
+        Arc::new(Metal::new(Color::new(0.8, 0.8, 0.9), 0.1)),
+        Arc::new(Lambertian::new(Color::new(0.2, 0.5, 0.8))),
+        Arc::new(Lambertian::new(Color::new(0.9, 0.8, 0.3))),
+        Arc::new(Metal::new(Color::new(0.9, 0.6, 0.3), 0.3)),
+    ];
+    for (sdf, mat) in [blob, twisted, torus, capsules, carved].into_iter().zip(materials) {
+        world.add(Arc::new(SdfHittable::new(sdf, mat)));
+    }
+
+    let cam = CameraBuilder::new()
+        .aspect_ratio(2.0)
+        .width(600)
+        .samples_per_pixel(64)
+        .max_depth(20)
+        .field_of_view(35.0)
+        .set_view(Point3::new(0.0, 3.0, 7.0), Point3::new(0.0, 0.4, 0.3), Vec3::new(0,1,0))
+        .build();
+
+    let fb = cam.render(&world);
+    fb.write_ppm(&mut io::stdout().lock()).expect("failed to write image");
+}
@@ -0,0 +1,36 @@
+// The three spheres from the first half of the book: a diffuse sphere between a hollow glass sphere and
+// a metal one.
+//
+//     cargo run --release --example three_spheres > three_spheres.ppm
+
+use std::io;
+use std::sync::Arc;
+
+use raytracing::{CameraBuilder, Color, Dielectric, HittableList, Lambertian, Metal, Point3, Sphere, Vec3};
+
+fn main() {
+    let mut world = HittableList::default();
+
+    let material_ground = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
+    let material_center = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
+    let material_left   = Arc::new(Dielectric::new(1.5));
+    let material_right  = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.0));
+
+    world.add(Arc::new(Sphere::new(Point3::new( 0.0, -100.5, -1.0), 100.0, material_ground)));
+    world.add(Arc::new(Sphere::new(Point3::new( 0.0,    0.0, -1.0),   0.5, material_center)));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0,    0.0, -1.0),   0.5, material_left.clone())));
+    world.add(Arc::new(Sphere::new(Point3::new(-1.0,    0.0, -1.0),  -0.4, material_left)));
+    world.add(Arc::new(Sphere::new(Point3::new( 1.0,    0.0, -1.0),   0.5, material_right)));
+
+    let cam = CameraBuilder::new()
+        .width(400)
+        .samples_per_pixel(100)
+        .max_depth(50)
+        .field_of_view(20.0)
+        .focus(10.0, 3.4)
+        .set_view(Point3::new(-2,2,1), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build();
+
+    let fb = cam.render(&world);
+    fb.write_ppm(&mut io::stdout().lock()).expect("failed to write image");
+}
@@ -0,0 +1,56 @@
+use crate::interval::Interval;
+use crate::ray::Ray;
+use crate::vec3::Point3;
+
+/// An axis-aligned bounding box, from corner `min` to corner `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Point3,
+    pub max: Point3,
+}
+
+impl Aabb {
+    pub fn new(min: Point3, max: Point3) -> Self {
+        Self {
+            min,
+            max,
+        }
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Point3::new(self.min.x.min(other.min.x), self.min.y.min(other.min.y), self.min.z.min(other.min.z)),
+            Point3::new(self.max.x.max(other.max.x), self.max.y.max(other.max.y), self.max.z.max(other.max.z)),
+        )
+    }
+
+    /// The box both boxes overlap in, which is empty, with `min` past `max`, if they don't.
+    pub fn intersection(&self, other: &Aabb) -> Aabb {
+        Aabb::new(
+            Point3::new(self.min.x.max(other.min.x), self.min.y.max(other.min.y), self.min.z.max(other.min.z)),
+            Point3::new(self.max.x.min(other.max.x), self.max.y.min(other.max.y), self.max.z.min(other.max.z)),
+        )
+    }
+
+    /// The part of `ray_t` for which `r` is inside the box, if any.
+    pub fn hit(&self, r: &Ray, ray_t: Interval) -> Option<(f64, f64)> {
+        let (mut t0, mut t1) = (ray_t.min, ray_t.max);
+        for (o, d, min, max) in [
+            (r.orig.x, r.dir.x, self.min.x, self.max.x),
+            (r.orig.y, r.dir.y, self.min.y, self.max.y),
+            (r.orig.z, r.dir.z, self.min.z, self.max.z),
+        ] {
+            let inv = 1.0 / d;
+            let (a, b) = ((min - o) * inv, (max - o) * inv);
+            let (a, b) = if a < b {(a, b)} else {(b, a)};
+            t0 = t0.max(a);
+            t1 = t1.min(b);
+            if t0 >= t1 {
+                return None;
+            }
+        }
+
+        Some((t0, t1))
+    }
+}
@@ -0,0 +1,74 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::util::random;
+
+/// Cuts holes in another hittable where an opacity texture, such as the alpha channel of a leaf or fence
+/// image loaded with [`ImageTexture::load_alpha`](crate::ImageTexture::load_alpha), is low. Rays go
+/// straight through the holes to whatever is behind, without scattering.
+pub struct AlphaMask {
+    object: Arc<dyn Hittable>,
+    alpha: Arc<dyn Texture>,
+    cutoff: Cutoff,
+}
+
+enum Cutoff {
+    Threshold(f64),
+    Stochastic,
+}
+
+impl AlphaMask {
+    /// Hits are skipped where the opacity is below `threshold`, giving hard edged cutouts.
+    pub fn new(object: Arc<dyn Hittable>, alpha: Arc<dyn Texture>, threshold: f64) -> Self {
+        Self {
+            object,
+            alpha,
+            cutoff: Cutoff::Threshold(threshold),
+        }
+    }
+
+    /// Rays pass through with a probability of one minus the opacity, so partly opaque areas such as
+    /// soft edges average out to partly covered over many samples.
+    pub fn stochastic(object: Arc<dyn Hittable>, alpha: Arc<dyn Texture>) -> Self {
+        Self {
+            object,
+            alpha,
+            cutoff: Cutoff::Stochastic,
+        }
+    }
+
+    fn opaque(&self, rec: &HitRecord) -> bool {
+        let alpha = self.alpha.scalar(rec.u, rec.v, rec.p);
+        match self.cutoff {
+            Cutoff::Threshold(threshold) => alpha >= threshold,
+            Cutoff::Stochastic => alpha >= 1.0 || random::<f64>() < alpha,
+        }
+    }
+}
+
+impl Hittable for AlphaMask {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        // keep looking further along the ray past hits in the holes
+        let mut ray_t = ray_t;
+        loop {
+            let rec = self.object.hit(r, ray_t)?;
+            if self.opaque(&rec) {
+                return Some(rec);
+            }
+            ray_t = Interval::new(rec.t, ray_t.max);
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.object.bounding_box()
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        self.object.materials()
+    }
+}
@@ -1,72 +1,257 @@
+use std::hash::Hasher;
+use std::io;
 use std::sync::Arc;
+use std::time::Instant;
 
-use rayon;
 use rayon::prelude::*;
 
-use rand::random;
-
-use crate::{hittable::{HitRecord, Hittable}, interval::Interval, ray::{self, Ray}, util::random_f64, write_color, Color, Point3, Vec3};
+use crate::checkpoint::{Checkpoint, Fingerprint};
+use crate::denoise::Denoiser;
+use crate::environment::{Environment, GradientSky};
+use crate::filter::{BoxFilter, Filter};
+use crate::spectrum::Wavelengths;
+use crate::framebuffer::{FirstHit, Framebuffer, Tile};
+use crate::util::{self, random, Rng};
+use crate::{hittable::{HitRecord, Hittable}, interval::Interval, ray::Ray, Color, Point3, Vec3};
+
+const SAMPLES_PER_PASS: u32 = 8;
+const FINGERPRINT_PROBES: u32 = 8;
+
+fn hash_vec3(hasher: &mut impl Hasher, v: Vec3) {
+    hasher.write_u64(v.x.to_bits());
+    hasher.write_u64(v.y.to_bits());
+    hasher.write_u64(v.z.to_bits());
+}
 
+/// A pinhole or thin-lens camera, along with the settings used to render through it.
+///
+/// Cameras are made with a [`CameraBuilder`].
 pub struct Camera {
-    pub aspect_ratio: f64,
-    pub image_width: u32,
-    pub samples_per_pixel: u32,
-    pub max_depth: u32,
-    pub vfov: f64,
-    pub lookfrom: Point3,
-    pub lookat: Point3,
-    pub vup: Vec3,
-    pub defocus_angle: f64,
-    pub focus_dist: f64,
-
+    image_width: u32,
     image_height: u32,
+    samples_per_pixel: u32,
+    max_depth: u32,
+    spectral: bool,
+    denoiser: Option<Denoiser>,
+    filter: Arc<dyn Filter>,
+    environment: Arc<dyn Environment>,
+    vfov: f64,
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vec3,
+    defocus_angle: f64,
+    focus_dist: f64,
+
     center: Point3,
     pixel00_loc: Point3,
     pixel_delta_u: Vec3,
     pixel_delta_v: Vec3,
-    u: Vec3, 
-    v: Vec3, 
     w: Vec3,
     defocus_disk_u: Vec3,
     defocus_disk_v: Vec3,
 }
 
 impl Camera {
-    pub fn render(&self, world: Arc<dyn Hittable>) {
-        print!("P3\n{} {}\n255\n", self.image_width, self.image_height);
-
-        for j in 0..self.image_height {
-            eprintln!("scanlines remaining: {}", self.image_height - j);
-            for i in 0..self.image_width {
-                let pixel_color = (0..self.samples_per_pixel).into_par_iter().map(|_| {
-                    let r = self.get_ray(i, j);
-                    let new_world = world.clone();
-                    self.ray_color(&r, self.max_depth, new_world)
-                }).sum();
-
-                write_color(pixel_color, self.samples_per_pixel);
+    pub fn image_width(&self) -> u32 {
+        self.image_width
+    }
+
+    pub fn image_height(&self) -> u32 {
+        self.image_height
+    }
+
+    pub fn samples_per_pixel(&self) -> u32 {
+        self.samples_per_pixel
+    }
+
+    /// Renders `world`, returning the framebuffer once every pixel has `samples_per_pixel` samples.
+    pub fn render(&self, world: &dyn Hittable) -> Framebuffer {
+        let mut fb = self.new_framebuffer();
+        self.accumulate(world, &mut fb, |_| Ok(())).expect("rendering without a checkpoint can't fail");
+        self.finish(&mut fb);
+        fb
+    }
+
+    /// Like [`Camera::render`], but periodically saves the framebuffer to `checkpoint` so that a
+    /// render that is killed part way through can carry on from where it got to by passing `resume`.
+    ///
+    /// Resuming fails with [`io::ErrorKind::NotFound`] if there's no checkpoint to resume, rather than
+    /// quietly starting again, and with [`io::ErrorKind::InvalidData`] if the checkpoint was saved from
+    /// a different scene or camera.
+    pub fn render_checkpointed(&self, world: &dyn Hittable, checkpoint: &Checkpoint, resume: bool) -> io::Result<Framebuffer> {
+        let fingerprint = self.fingerprint(world);
+
+        let mut fb = if resume {
+            let fb = checkpoint.load(fingerprint)?;
+            eprintln!("resuming from {} samples per pixel", fb.samples());
+            fb
+        } else {
+            self.new_framebuffer()
+        };
+
+        let mut last_save = Instant::now();
+        self.accumulate(world, &mut fb, |fb| {
+            if last_save.elapsed() >= checkpoint.interval {
+                checkpoint.save(fingerprint, fb)?;
+                last_save = Instant::now();
             }
+            Ok(())
+        })?;
+        checkpoint.save(fingerprint, &fb)?;
+        self.finish(&mut fb);
+
+        Ok(fb)
+    }
+
+    fn new_framebuffer(&self) -> Framebuffer {
+        // how many pixels beyond the one it was taken in a sample can reach
+        let pad = (self.filter.radius() - 0.5).ceil().max(0.0) as u32;
+        Framebuffer::new(self.image_width, self.image_height, pad, random())
+    }
+
+    // post-processing, run on the framebuffer once all its samples have been taken
+    fn finish(&self, fb: &mut Framebuffer) {
+        if let Some(denoiser) = &self.denoiser {
+            eprintln!("Denoising");
+            fb.denoise(denoiser);
+        }
+    }
+
+    // adds samples to every tile of `fb` in passes until each pixel has `samples_per_pixel`, calling
+    // `after_pass` between passes
+    fn accumulate(&self, world: &dyn Hittable, fb: &mut Framebuffer, mut after_pass: impl FnMut(&Framebuffer) -> io::Result<()>) -> io::Result<()> {
+        while fb.samples() < self.samples_per_pixel {
+            eprintln!("samples remaining: {}", self.samples_per_pixel - fb.samples());
+
+            fb.tiles.par_iter_mut().for_each(|tile| {
+                let samples = SAMPLES_PER_PASS.min(self.samples_per_pixel.saturating_sub(tile.samples));
+                self.render_tile(world, tile, samples);
+            });
+
+            after_pass(fb)?;
         }
         eprintln!("Done rendering");
+
+        Ok(())
+    }
+
+    fn render_tile(&self, world: &dyn Hittable, tile: &mut Tile, samples: u32) {
+        let mut rng = tile.rng;
+        let first_pass = tile.samples == 0;
+
+        util::with_rng(&mut rng, || {
+            for j in 0..tile.height {
+                for i in 0..tile.width {
+                    let index = (j * tile.width + i) as usize;
+
+                    for sample in 0..samples {
+                        let (x, y) = (tile.x0 + i, tile.y0 + j);
+                        let (dx, dy) = self.pixel_sample_square();
+                        let mut wavelengths = if self.spectral {Wavelengths::sample()} else {Wavelengths::rgb()};
+                        let mut r = self.get_ray(x, y, dx, dy);
+                        r.wavelength = wavelengths.hero();
+                        let hit = world.hit(&r, Interval::new(0.001, f64::INFINITY));
+
+                        let color = match &hit {
+                            Some(rec) => self.hit_color(&r, rec, self.max_depth, world, &mut wavelengths),
+                            None => wavelengths.upsample(self.environment.radiance(r.dir)),
+                        };
+                        let color = wavelengths.to_rgb(color);
+                        tile.splat(x as f64 + dx, y as f64 + dy, color, self.filter.as_ref());
+                        tile.box_sums[index] += color;
+
+                        let Some(rec) = hit else { continue };
+                        tile.normal_sums[index] += rec.normal;
+                        tile.albedo_sums[index] += rec.mat.albedo(&rec);
+
+                        if first_pass && sample == 0 {
+                            tile.first_hits[index] = FirstHit {
+                                depth: (rec.p - self.center).dot(&-self.w),
+                                position: rec.p,
+                                object_id: rec.object_id,
+                                material_id: rec.material_id,
+                            };
+                        }
+                    }
+                }
+            }
+        });
+
+        tile.rng = rng;
+        tile.samples += samples;
+    }
+
+    // a hash of the camera and of what a grid of rays through the image hits, used to check that a
+    // checkpoint belongs to the scene being rendered
+    fn fingerprint(&self, world: &dyn Hittable) -> u64 {
+        let mut hasher = Fingerprint::default();
+
+        hasher.write_u32(self.image_width);
+        hasher.write_u32(self.image_height);
+        hasher.write_u32(self.max_depth);
+        hasher.write_u8(self.spectral as u8);
+        hasher.write_u64(self.filter.radius().to_bits());
+        for i in 0..8 {
+            hasher.write_u64(self.filter.evaluate(i as f64 * 0.25).to_bits());
+        }
+        for value in [self.vfov, self.defocus_angle, self.focus_dist] {
+            hasher.write_u64(value.to_bits());
+        }
+        for v in [self.lookfrom, self.lookat, self.vup] {
+            hash_vec3(&mut hasher, v);
+        }
+
+        // the probe rays go through pixel centres without jitter or defocus, and materials scatter with
+        // a fixed seed, so the same scene always hashes the same
+        let mut rng = Rng::new(0, 0);
+        util::with_rng(&mut rng, || {
+            for j in 0..FINGERPRINT_PROBES {
+                for i in 0..FINGERPRINT_PROBES {
+                    let x = (i * self.image_width) / FINGERPRINT_PROBES;
+                    let y = (j * self.image_height) / FINGERPRINT_PROBES;
+                    let pixel_center = self.pixel00_loc + (x as f64 * self.pixel_delta_u) + (y as f64 * self.pixel_delta_v);
+                    let r = Ray::new(self.center, pixel_center - self.center);
+
+                    let Some(rec) = world.hit(&r, Interval::new(0.001, f64::INFINITY)) else {
+                        hasher.write_u8(0);
+                        hash_vec3(&mut hasher, self.environment.radiance(r.dir));
+                        continue;
+                    };
+
+                    hasher.write_u8(1);
+                    hasher.write_u64(rec.t.to_bits());
+                    hash_vec3(&mut hasher, rec.normal);
+                    if let Some((atten, scatter)) = rec.mat.scatter(&r, &rec) {
+                        hash_vec3(&mut hasher, atten);
+                        hash_vec3(&mut hasher, scatter.dir);
+                    }
+                }
+            }
+        });
+
+        hasher.finish()
     }
 
-    fn get_ray(&self, i: u32, j: u32) -> Ray {
-        // get a randomly-sampled camera ray for the pixel at locationi,j originating from the camera defocus disk.
+    #[allow(clippy::needless_return)]
+    fn get_ray(&self, i: u32, j: u32, dx: f64, dy: f64) -> Ray {
+        // get a camera ray through the point dx,dy pixels from the centre of pixel i,j, originating from the camera defocus disk.
 
         let pixel_center = self.pixel00_loc + (i as f64 * self.pixel_delta_u) + (j as f64 * self.pixel_delta_v);
-        let pixel_sample = pixel_center + self.pixel_sample_square();
+        let pixel_sample = pixel_center + (dx * self.pixel_delta_u) + (dy * self.pixel_delta_v);
 
         let ray_origin = if self.defocus_angle <= 0.0 {self.center} else {self.defocus_disk_sample()};
         let ray_direction = pixel_sample - ray_origin;
 
-        return Ray {orig: ray_origin, dir: ray_direction}
+        return Ray {orig: ray_origin, dir: ray_direction, wavelength: None}
     }
 
-    fn pixel_sample_square(&self) -> Vec3 {
+    // a random offset within the square around a pixel centre, in pixels
+    #[allow(clippy::needless_return)]
+    fn pixel_sample_square(&self) -> (f64, f64) {
         let px = -0.5 + random::<f64>();
         let py = -0.5 + random::<f64>();
 
-        return (px * self.pixel_delta_u) + (py * self.pixel_delta_v);
+        return (px, py);
     }
 
     fn defocus_disk_sample(&self) -> Point3 {
@@ -76,28 +261,85 @@ impl Camera {
         self.center + (p.x * self.defocus_disk_u) + (p.y * self.defocus_disk_v)
     }
 
-    fn ray_color(&self, r: &Ray, depth: u32, world: Arc<dyn Hittable>) -> Color {
-        if depth <= 0 {return Color::new(0,0,0)}
-    
-        if let Some(rec) = world.hit(r, Interval::new(0.001, f64::INFINITY)) {
-            if let Some((atten, scatter)) = rec.mat.scatter(r, &rec) {
-                return atten * self.ray_color(&scatter, depth - 1, world);
+    // the light arriving back along `r` from `rec`, with `depth` bounces left, at each of `wavelengths`
+    fn hit_color(&self, r: &Ray, rec: &HitRecord, depth: u32, world: &dyn Hittable, wavelengths: &mut Wavelengths) -> Color {
+        if depth <= 1 {return Color::new(0,0,0)}
+
+        // sampled light still counts when the material's own sample is absorbed
+        let mut color = self.environment_light(r, rec, world, wavelengths);
+        let Some((atten, mut scatter)) = rec.mat.scatter(r, rec) else {return color};
+        scatter.wavelength = r.wavelength;
+
+        let mut atten = wavelengths.upsample(atten);
+        if rec.mat.disperses() {
+            atten = atten * wavelengths.terminate_secondary();
+        }
+
+        match world.hit(&scatter, Interval::new(0.001, f64::INFINITY)) {
+            Some(next) => color += atten * self.hit_color(&scatter, &next, depth - 1, world, wavelengths),
+            None => {
+                // the environment might have been found by sampling it directly as well, in which case
+                // the two ways of finding it share the credit
+                let weight = match rec.mat.evaluate(r, rec, scatter.dir) {
+                    Some((_, scatter_pdf)) => power_heuristic(scatter_pdf, self.environment.pdf(scatter.dir)),
+                    None => 1.0,
+                };
+                color += weight * atten * wavelengths.upsample(self.environment.radiance(scatter.dir));
             }
-            
+        }
+
+        color
+    }
+
+    // light reaching `rec` straight from the environment, found by sampling a direction from the
+    // environment rather than from the material
+    fn environment_light(&self, r: &Ray, rec: &HitRecord, world: &dyn Hittable, wavelengths: &Wavelengths) -> Color {
+        let (direction, radiance, light_pdf) = self.environment.sample();
+        if light_pdf <= 0.0 {return Color::new(0,0,0)}
+
+        let Some((f_cos, scatter_pdf)) = rec.mat.evaluate(r, rec, direction) else {return Color::new(0,0,0)};
+        if f_cos.near_zero() {
+            return Color::new(0,0,0);
+        }
+        let transmittance = world.transmittance(&Ray::new(rec.p, direction), Interval::new(0.001, f64::INFINITY));
+        if transmittance <= 0.0 {
             return Color::new(0,0,0);
         }
-    
-        let unit_direction = r.dir.unit_vector();
-        let a = 0.5 * (unit_direction.y + 1.0);
-        return (1.0 - a) * Color::new(1,1,1) + a * Color::new(0.5, 0.7, 1.0);
+
+        let f_cos = wavelengths.upsample(f_cos);
+        power_heuristic(light_pdf, scatter_pdf) * transmittance * f_cos * wavelengths.upsample(radiance) / light_pdf
     }
 }
 
+// multiple importance sampling weight for a direction found with probability density `pdf`, that the
+// other strategy would have found with `other_pdf`
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    if a + b == 0.0 {return 0.0}
+    a / (a + b)
+}
+
+/// Collects camera settings; anything left unset gets a default when the camera is built.
+///
+/// ```no_run
+/// use raytracing::{CameraBuilder, Point3, Vec3};
+///
+/// let cam = CameraBuilder::new()
+///     .width(400)
+///     .samples_per_pixel(50)
+///     .set_view(Point3::new(13,2,3), Point3::new(0,0,0), Vec3::new(0,1,0))
+///     .build();
+/// ```
+#[derive(Default)]
 pub struct CameraBuilder {
     aspect_ratio: Option<f64>,
     image_width: Option<u32>,
     samples_per_pixel: Option<u32>,
     max_depth: Option<u32>,
+    spectral: Option<bool>,
+    denoiser: Option<Denoiser>,
+    filter: Option<Arc<dyn Filter>>,
+    environment: Option<Arc<dyn Environment>>,
     vfov: Option<f64>,
     lookfrom: Option<Point3>,
     lookat: Option<Point3>,
@@ -108,21 +350,10 @@ pub struct CameraBuilder {
 
 impl CameraBuilder {
     pub fn new() -> Self {
-        Self {
-            aspect_ratio: None,
-            image_width: None,
-            samples_per_pixel: None,
-            max_depth: None,
-            vfov: None,
-            lookfrom: None,
-            lookat: None,
-            vup: None,
-            defocus_angle: None,
-            focus_dist: None,
-        }
+        Self::default()
     }
 
-    pub fn build(self) -> Camera {
+    pub fn build(&self) -> Camera {
         let aspect_ratio = self.aspect_ratio.unwrap_or(16.0 / 9.0);
         let image_width = self.image_width.unwrap_or(400);
         let samples_per_pixel = self.samples_per_pixel.unwrap_or(10);
@@ -133,7 +364,7 @@ impl CameraBuilder {
         let lookat = self.lookat.unwrap_or(Point3::new(0,0,0));
         let vup = self.vup.unwrap_or(Vec3::new(0,1,0));
         let defocus_angle = self.defocus_angle.unwrap_or(0.0);
-        let focus_dist = self.focus_dist.unwrap_or(0.0);
+        let focus_dist = self.focus_dist.unwrap_or(10.0);
 
         let image_height = (image_width as f64 / aspect_ratio) as u32;
         let image_height = if image_height > 0 {image_height} else {1};
@@ -169,10 +400,13 @@ impl CameraBuilder {
         let defocus_disk_v = v * defocus_radius;
 
         Camera {
-            aspect_ratio,
             image_width,
             samples_per_pixel,
             max_depth,
+            spectral: self.spectral.unwrap_or(false),
+            denoiser: self.denoiser,
+            filter: self.filter.clone().unwrap_or_else(|| Arc::new(BoxFilter::default())),
+            environment: self.environment.clone().unwrap_or_else(|| Arc::new(GradientSky)),
             vfov,
             image_height,
             center,
@@ -182,8 +416,6 @@ impl CameraBuilder {
             lookfrom,
             lookat,
             vup,
-            u,
-            v,
             w,
             defocus_angle,
             focus_dist,
@@ -192,31 +424,63 @@ impl CameraBuilder {
         }
     }
 
+    /// Width over height, 16:9 by default.
     pub fn aspect_ratio(&mut self, aspect_ratio: f64) -> &mut Self {
         self.aspect_ratio = Some(aspect_ratio);
         self
     }
 
+    /// Image width in pixels, 400 by default. The height follows from the aspect ratio.
     pub fn width(&mut self, width: u32) -> &mut Self {
         self.image_width = Some(width);
         self
     }
 
+    /// Samples averaged for each pixel, 10 by default.
     pub fn samples_per_pixel(&mut self, samples: u32) -> &mut Self {
         self.samples_per_pixel = Some(samples);
         self
     }
 
+    /// Maximum number of bounces along a path, 10 by default.
     pub fn max_depth(&mut self, depth: u32) -> &mut Self {
         self.max_depth = Some(depth);
         self
     }
 
+    /// Traces light at sampled wavelengths rather than in RGB, so that materials whose index of
+    /// refraction depends on wavelength split light into colours. Off by default.
+    pub fn spectral(&mut self, spectral: bool) -> &mut Self {
+        self.spectral = Some(spectral);
+        self
+    }
+
+    /// Denoises the image once it has been rendered. Off by default.
+    pub fn denoise(&mut self, denoiser: Denoiser) -> &mut Self {
+        self.denoiser = Some(denoiser);
+        self
+    }
+
+    /// The filter samples are weighted by when they're combined into pixels. By default each pixel is
+    /// the plain average of the samples taken within it, i.e. a [`BoxFilter`] of radius 0.5.
+    pub fn filter(&mut self, filter: Arc<dyn Filter>) -> &mut Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    /// The light seen by rays that escape the scene. By default this is a [`GradientSky`].
+    pub fn environment(&mut self, environment: Arc<dyn Environment>) -> &mut Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Vertical field of view in degrees, 90 by default.
     pub fn field_of_view(&mut self, vfov: f64) -> &mut Self {
         self.vfov = Some(vfov);
         self
     }
 
+    /// Places the camera at `lookfrom`, pointed at `lookat`, with `vup` upwards.
     pub fn set_view(&mut self, lookfrom: Point3, lookat: Point3, vup:Vec3) -> &mut Self {
         self.lookfrom = Some(lookfrom);
         self.lookat = Some(lookat);
@@ -224,6 +488,8 @@ impl CameraBuilder {
         self
     }
 
+    /// The angle in degrees of the cone of rays through each pixel, and the distance to the plane in
+    /// perfect focus. An angle of zero (the default) gives a pinhole camera.
     pub fn focus(&mut self, defocus_angle: f64, focus_dist: f64) -> &mut Self {
         self.defocus_angle = Some(defocus_angle);
         self.focus_dist = Some(focus_dist);
@@ -0,0 +1,194 @@
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::framebuffer::{FirstHit, Framebuffer};
+use crate::util::Rng;
+use crate::vec3::Vec3;
+
+const MAGIC: &[u8; 8] = b"RTCKPT04";
+
+/// Where, and how often, [`Camera::render_checkpointed`](crate::Camera::render_checkpointed) saves
+/// its progress.
+pub struct Checkpoint {
+    pub path: PathBuf,
+    pub interval: Duration,
+}
+
+impl Checkpoint {
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        Self {
+            path: path.into(),
+            interval,
+        }
+    }
+
+    // writes to a temporary file first so a crash mid-save can't destroy the previous checkpoint
+    pub(crate) fn save(&self, fingerprint: u64, fb: &Framebuffer) -> io::Result<()> {
+        let tmp_path = self.path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&tmp_path)?);
+
+        out.write_all(MAGIC)?;
+        write_u64(&mut out, fingerprint)?;
+        write_u32(&mut out, fb.width)?;
+        write_u32(&mut out, fb.height)?;
+        write_u32(&mut out, fb.pad)?;
+        write_u32(&mut out, fb.tiles.len() as u32)?;
+
+        for tile in &fb.tiles {
+            write_u32(&mut out, tile.x0)?;
+            write_u32(&mut out, tile.y0)?;
+            write_u32(&mut out, tile.width)?;
+            write_u32(&mut out, tile.height)?;
+            write_u32(&mut out, tile.samples)?;
+            write_u64(&mut out, tile.rng.state)?;
+            write_u64(&mut out, tile.rng.inc)?;
+
+            for v in tile.sums.iter().chain(&tile.box_sums).chain(&tile.normal_sums).chain(&tile.albedo_sums) {
+                write_vec3(&mut out, *v)?;
+            }
+            for weight in &tile.weights {
+                write_f64(&mut out, *weight)?;
+            }
+            for first_hit in &tile.first_hits {
+                write_f64(&mut out, first_hit.depth)?;
+                write_vec3(&mut out, first_hit.position)?;
+                write_u32(&mut out, first_hit.object_id)?;
+                write_u32(&mut out, first_hit.material_id)?;
+            }
+        }
+
+        out.into_inner()?.sync_all()?;
+        fs::rename(tmp_path, &self.path)
+    }
+
+    pub(crate) fn load(&self, fingerprint: u64) -> io::Result<Framebuffer> {
+        let mut input = BufReader::new(File::open(&self.path)?);
+
+        let mut magic = [0u8; 8];
+        input.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a render checkpoint"));
+        }
+
+        if read_u64(&mut input)? != fingerprint {
+            return Err(invalid_data("saved from a different scene or camera"));
+        }
+
+        let width = read_u32(&mut input)?;
+        let height = read_u32(&mut input)?;
+        let pad = read_u32(&mut input)?;
+        let tile_count = read_u32(&mut input)?;
+
+        let mut fb = Framebuffer::new(width, height, pad, 0);
+        if fb.tiles.len() != tile_count as usize {
+            return Err(invalid_data("unexpected tile layout"));
+        }
+
+        for tile in &mut fb.tiles {
+            let x0 = read_u32(&mut input)?;
+            let y0 = read_u32(&mut input)?;
+            let tile_width = read_u32(&mut input)?;
+            let tile_height = read_u32(&mut input)?;
+            if (x0, y0, tile_width, tile_height) != (tile.x0, tile.y0, tile.width, tile.height) {
+                return Err(invalid_data("unexpected tile layout"));
+            }
+
+            tile.samples = read_u32(&mut input)?;
+            tile.rng = Rng {
+                state: read_u64(&mut input)?,
+                inc: read_u64(&mut input)?,
+            };
+
+            for v in tile.sums.iter_mut().chain(&mut tile.box_sums).chain(&mut tile.normal_sums).chain(&mut tile.albedo_sums) {
+                *v = read_vec3(&mut input)?;
+            }
+            for weight in &mut tile.weights {
+                *weight = read_f64(&mut input)?;
+            }
+            for first_hit in &mut tile.first_hits {
+                *first_hit = FirstHit {
+                    depth: read_f64(&mut input)?,
+                    position: read_vec3(&mut input)?,
+                    object_id: read_u32(&mut input)?,
+                    material_id: read_u32(&mut input)?,
+                };
+            }
+        }
+
+        Ok(fb)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn write_u32(out: &mut impl Write, value: u32) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_u64(out: &mut impl Write, value: u64) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_f64(out: &mut impl Write, value: f64) -> io::Result<()> {
+    out.write_all(&value.to_le_bytes())
+}
+
+fn write_vec3(out: &mut impl Write, v: Vec3) -> io::Result<()> {
+    write_f64(out, v.x)?;
+    write_f64(out, v.y)?;
+    write_f64(out, v.z)
+}
+
+fn read_u32(input: &mut impl Read) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    input.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64(input: &mut impl Read) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn read_f64(input: &mut impl Read) -> io::Result<f64> {
+    let mut bytes = [0u8; 8];
+    input.read_exact(&mut bytes)?;
+    Ok(f64::from_le_bytes(bytes))
+}
+
+fn read_vec3(input: &mut impl Read) -> io::Result<Vec3> {
+    Ok(Vec3 {
+        x: read_f64(input)?,
+        y: read_f64(input)?,
+        z: read_f64(input)?,
+    })
+}
+
+// FNV-1a, used for scene fingerprints because std's hashers aren't guaranteed to be stable between
+// compiler versions
+pub(crate) struct Fingerprint(u64);
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for Fingerprint {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= *byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+}
@@ -1,14 +1,21 @@
+use std::io::{self, Write};
+
 use crate::{interval::Interval, vec3::Vec3};
 
+/// A linear RGB colour.
 pub type Color = Vec3;
 
+// how bright a colour looks, with the Rec. 709 weights for each channel
+pub(crate) fn luminance(c: Color) -> f64 {
+    0.2126 * c.x + 0.7152 * c.y + 0.0722 * c.z
+}
+
 pub fn linear_to_gamma(linear_component: f64) -> f64 {
     linear_component.sqrt()
 }
 
-pub fn write_color(pixel_color: Color, samples_per_pixel: u32) {
-    let scale = 1.0 / samples_per_pixel as f64;
-    let pixel_color = pixel_color * scale;
+/// Writes `pixel_color` as a gamma-corrected line of a plain PPM.
+pub fn write_color(out: &mut impl Write, pixel_color: Color) -> io::Result<()> {
     let intensity = Interval::new(0.000, 0.999);
 
     let r = linear_to_gamma(pixel_color.x);
@@ -19,5 +26,5 @@ pub fn write_color(pixel_color: Color, samples_per_pixel: u32) {
     let g = (256.0 * intensity.clamp(g)) as u32;
     let b = (256.0 * intensity.clamp(b)) as u32;
 
-    print!("{r} {g} {b}\n");
-}
\ No newline at end of file
+    writeln!(out, "{r} {g} {b}")
+}
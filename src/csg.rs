@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+
+/// A closed hittable with an inside, which can be combined with others by [`Csg`].
+pub trait Solid: Hittable {
+    /// Every stretch of the line along `r` that's inside the solid, in order along the ray and not
+    /// overlapping, including any behind its origin. Entries are front faces and exits back faces,
+    /// with normals facing against the ray as always.
+    fn spans(&self, r: &Ray) -> Vec<Span>;
+}
+
+/// Where a ray enters a solid and where it next leaves it.
+pub struct Span {
+    pub enter: HitRecord,
+    pub exit: HitRecord,
+}
+
+// the spans of a closed hittable found by following the ray from one of its surfaces to the next
+// within `ray_t`, pairing each front face with the back face after it
+pub(crate) fn spans_between(object: &dyn Hittable, r: &Ray, ray_t: Interval) -> Vec<Span> {
+    let mut spans = Vec::new();
+    let mut enter = None;
+    let mut t = ray_t.min;
+    while let Some(rec) = object.hit(r, Interval::new(t, ray_t.max)) {
+        t = rec.t;
+        if rec.front_face {
+            enter = Some(rec);
+        } else if let Some(enter) = enter.take() {
+            spans.push(Span {
+                enter,
+                exit: rec,
+            });
+        }
+    }
+    spans
+}
+
+#[derive(Clone, Copy)]
+enum Operation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl Operation {
+    fn inside(self, a: bool, b: bool) -> bool {
+        match self {
+            Operation::Union => a || b,
+            Operation::Intersection => a && b,
+            Operation::Difference => a && !b,
+        }
+    }
+}
+
+/// Constructive solid geometry: a solid made by combining two others, such as a lens from the
+/// intersection of two spheres or a hollow shell from the difference of two. Each part of the surface
+/// keeps the material of the solid it came from, so a cut shows the material of the solid that cut it.
+///
+/// Spheres, tori, shapes made from signed distance functions, capped cylinders, cones and paraboloids,
+/// and other combinations are solids. Without their caps the open shapes have no inside, and so add
+/// nothing to a combination.
+pub struct Csg {
+    a: Arc<dyn Solid>,
+    b: Arc<dyn Solid>,
+    operation: Operation,
+}
+
+impl Csg {
+    /// Everything inside either `a` or `b`.
+    pub fn union(a: Arc<dyn Solid>, b: Arc<dyn Solid>) -> Self {
+        Self::new(a, b, Operation::Union)
+    }
+
+    /// Only what's inside both `a` and `b`.
+    pub fn intersection(a: Arc<dyn Solid>, b: Arc<dyn Solid>) -> Self {
+        Self::new(a, b, Operation::Intersection)
+    }
+
+    /// What's inside `a` with `b` cut away.
+    pub fn difference(a: Arc<dyn Solid>, b: Arc<dyn Solid>) -> Self {
+        Self::new(a, b, Operation::Difference)
+    }
+
+    fn new(a: Arc<dyn Solid>, b: Arc<dyn Solid>, operation: Operation) -> Self {
+        Self {
+            a,
+            b,
+            operation,
+        }
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        self.spans(r)
+            .into_iter()
+            .flat_map(|span| [span.enter, span.exit])
+            .find(|rec| ray_t.surrounds(rec.t))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        match self.operation {
+            Operation::Union => Some(self.a.bounding_box()?.union(&self.b.bounding_box()?)),
+            Operation::Intersection => match (self.a.bounding_box(), self.b.bounding_box()) {
+                (Some(a), Some(b)) => Some(a.intersection(&b)),
+                (a, b) => a.or(b),
+            },
+            Operation::Difference => self.a.bounding_box(),
+        }
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        let mut materials = self.a.materials();
+        materials.extend(self.b.materials());
+        materials
+    }
+}
+
+impl Solid for Csg {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        // walk along the ray through every surface of either solid in order, keeping track of which
+        // solids we're inside, and keep the surfaces where that takes us in or out of the combination
+        let mut events: Vec<(HitRecord, bool, bool)> = Vec::new();
+        for (spans, from_a) in [(self.a.spans(r), true), (self.b.spans(r), false)] {
+            for span in spans {
+                events.push((span.enter, from_a, true));
+                events.push((span.exit, from_a, false));
+            }
+        }
+        events.sort_by(|x, y| x.0.t.total_cmp(&y.0.t));
+
+        let (mut in_a, mut in_b) = (false, false);
+        let mut enter = None;
+        let mut spans = Vec::new();
+        for (mut rec, from_a, entering) in events {
+            let was_inside = self.operation.inside(in_a, in_b);
+            if from_a {in_a = entering} else {in_b = entering}
+            let inside = self.operation.inside(in_a, in_b);
+            if inside == was_inside {
+                continue;
+            }
+
+            // the surface's normal already faces against the ray, which is outwards from the
+            // combination wherever the ray goes in, and inwards wherever it comes out
+            rec.front_face = inside;
+            if inside {
+                enter = Some(rec);
+            } else if let Some(enter) = enter.take() {
+                spans.push(Span {
+                    enter,
+                    exit: rec,
+                });
+            }
+        }
+
+        spans
+    }
+}
@@ -0,0 +1,119 @@
+use rayon::prelude::*;
+
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::vec3::Vec3;
+
+// B3 spline, the 1D kernel the 5x5 à-trous filter is built from
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// An edge-avoiding à-trous wavelet filter (Dammertz et al. 2010), guided by the normal, albedo and
+/// depth passes so that it blurs away noise without blurring across geometric or texture edges.
+///
+/// Colour is divided by albedo before filtering and multiplied back afterwards, so texture detail
+/// survives even where the filter is wide.
+#[derive(Clone, Copy, Debug)]
+pub struct Denoiser {
+    /// Number of filter passes; each doubles the spacing of the taps, so 3 passes cover a 29 pixel wide
+    /// footprint and 5 passes a 125 pixel one.
+    pub iterations: u32,
+    /// How different two pixels' colours can be before they stop being averaged. Halved each pass.
+    pub sigma_color: f64,
+    pub sigma_normal: f64,
+    /// Relative difference in depth allowed between averaged pixels.
+    pub sigma_depth: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 3,
+            sigma_color: 1.0,
+            sigma_normal: 0.3,
+            sigma_depth: 0.05,
+        }
+    }
+}
+
+struct Guide {
+    normal: Vec3,
+    depth: f64,
+}
+
+impl Denoiser {
+    /// The denoised colour of every pixel of `fb`, in scanline order.
+    pub fn denoise(&self, fb: &Framebuffer) -> Vec<Color> {
+        let (width, height) = (fb.width() as usize, fb.height() as usize);
+        let pixels = || (0..fb.height()).flat_map(|y| (0..fb.width()).map(move |x| (x, y)));
+
+        let albedo: Vec<Color> = pixels().map(|(x, y)| {
+            let a = fb.albedo(x, y);
+            Color::new(demodulation_factor(a.x), demodulation_factor(a.y), demodulation_factor(a.z))
+        }).collect();
+        let guides: Vec<Guide> = pixels().map(|(x, y)| Guide {
+            normal: fb.normal(x, y),
+            depth: fb.depth(x, y),
+        }).collect();
+
+        let mut irradiance: Vec<Color> = pixels().zip(&albedo).map(|((x, y), a)| {
+            let c = fb.noisy_pixel(x, y);
+            Color::new(c.x / a.x, c.y / a.y, c.z / a.z)
+        }).collect();
+
+        let mut sigma_color = self.sigma_color;
+        for iteration in 0..self.iterations {
+            let step = 1 << iteration;
+
+            irradiance = (0..width * height).into_par_iter().map(|p| {
+                let (px, py) = ((p % width) as i64, (p / width) as i64);
+                let mut sum = Color::default();
+                let mut weight_sum = 0.0;
+
+                for (j, kj) in KERNEL.iter().enumerate() {
+                    for (i, ki) in KERNEL.iter().enumerate() {
+                        let qx = px + (i as i64 - 2) * step;
+                        let qy = py + (j as i64 - 2) * step;
+                        if qx < 0 || qy < 0 || qx >= width as i64 || qy >= height as i64 {
+                            continue;
+                        }
+                        let q = qy as usize * width + qx as usize;
+
+                        let weight = ki * kj * self.edge_weight(irradiance[p], irradiance[q], &guides[p], &guides[q], sigma_color);
+                        sum += weight * irradiance[q];
+                        weight_sum += weight;
+                    }
+                }
+
+                // the centre tap always has weight, so this never divides by zero
+                sum / weight_sum
+            }).collect();
+
+            sigma_color /= 2.0;
+        }
+
+        irradiance.iter().zip(&albedo).map(|(c, a)| *c * *a).collect()
+    }
+
+    fn edge_weight(&self, cp: Color, cq: Color, gp: &Guide, gq: &Guide, sigma_color: f64) -> f64 {
+        let color = (-(cp - cq).length_squared() / (sigma_color * sigma_color)).exp();
+        let normal = (-(gp.normal - gq.normal).length_squared() / (self.sigma_normal * self.sigma_normal)).exp();
+
+        // misses have infinite depth, and only blend with other misses
+        let depth = match (gp.depth.is_finite(), gq.depth.is_finite()) {
+            (true, true) => {
+                let relative = (gp.depth - gq.depth).abs() / gp.depth.max(gq.depth);
+                (-relative / self.sigma_depth).exp()
+            }
+            (false, false) => 1.0,
+            _ => 0.0,
+        };
+
+        color * normal * depth
+    }
+}
+
+// what to divide a channel of colour by to remove the surface colour. Where there's no albedo, such as
+// the background, the colour is filtered as is
+fn demodulation_factor(albedo: f64) -> f64 {
+    if albedo > 0.01 {albedo} else {1.0}
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::mem;
+
+use crate::mesh::{Mesh, Weighting};
+use crate::texture::Texture;
+use crate::vec3::{Point3, Vec3};
+
+impl Mesh {
+    /// A copy of the mesh with its triangles split until no edge is longer than `max_edge`, so that
+    /// [`Mesh::displace`] has enough vertices to show a texture's detail. Long edges are split at their
+    /// middles, shared by the triangles either side so they stay joined up, with the vertex attributes
+    /// blended from the edge's ends.
+    ///
+    /// Panics if `max_edge` isn't positive, or if any position isn't finite, as edges reaching to
+    /// infinity can't be split short enough.
+    pub fn tessellate(&self, max_edge: f64) -> Mesh {
+        assert!(max_edge > 0.0, "tessellating to a maximum edge length of {max_edge}");
+        if let Some(p) = self.positions.iter().find(|p| !(p.x.is_finite() && p.y.is_finite() && p.z.is_finite())) {
+            panic!("tessellating a mesh with a vertex at {p:?}");
+        }
+        let mut mesh = self.clone();
+        mesh.triangulate();
+
+        loop {
+            let mut middles = HashMap::new();
+            let mut triangles = Vec::with_capacity(mesh.triangles.len());
+            for corners in mem::take(&mut mesh.triangles) {
+                // the middle of each edge that's too long, for the edge from each corner to the next
+                let middle = [0, 1, 2].map(|i| {
+                    let (a, b) = (corners[i], corners[(i + 1) % 3]);
+                    if (mesh.positions[a] - mesh.positions[b]).length() <= max_edge {
+                        return None;
+                    }
+                    Some(*middles.entry((a.min(b), a.max(b))).or_insert_with(|| mesh.add_middle(a, b)))
+                });
+                split(&mesh.positions, corners, middle, &mut triangles);
+            }
+
+            mesh.triangles = triangles;
+            if middles.is_empty() {
+                return mesh;
+            }
+        }
+    }
+
+    /// A copy of the mesh with every vertex moved along the surface normal by `scale` times the scalar
+    /// value of `height` there, giving it relief that changes its outline, unlike a
+    /// [`BumpMap`](crate::BumpMap). The normals are worked out afresh from the displaced surface by
+    /// [`Mesh::compute_normals`], keeping edges sharper than `crease_angle` degrees.
+    ///
+    /// Vertices only move, so detail finer than the triangles is lost; [`Mesh::tessellate`] first to
+    /// add more. The mesh's vertex normals give the direction to move in where it has them, and the
+    /// normals of its triangles otherwise. Vertices sharing a place, such as along texture seams, move
+    /// together by their average so the surface doesn't tear. Without texture coordinates, `height` is
+    /// looked up at `u` and `v` of zero and so had better depend only on the point.
+    pub fn displace(&self, height: &dyn Texture, scale: f64, crease_angle: f64) -> Mesh {
+        let mut mesh = self.clone();
+        mesh.triangulate();
+
+        let mut shared = HashMap::new();
+        let place: Vec<usize> = mesh.positions.iter().map(|&p| {
+            let key = [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()];
+            let next = shared.len();
+            *shared.entry(key).or_insert(next)
+        }).collect();
+
+        let mut directions = vec![Vec3::new(0, 0, 0); shared.len()];
+        if mesh.normals.is_empty() {
+            // weighted by area, as the cross product is twice the area long
+            for &[a, b, c] in &mesh.triangles {
+                let (pa, pb, pc) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+                let n = (pb - pa).cross(&(pc - pa));
+                for v in [a, b, c] {
+                    directions[place[v]] += n;
+                }
+            }
+        } else {
+            for (v, &n) in mesh.normals.iter().enumerate() {
+                directions[place[v]] += n;
+            }
+        }
+
+        let mut heights = vec![(0.0, 0); shared.len()];
+        for (v, &p) in mesh.positions.iter().enumerate() {
+            let (u, w) = mesh.uvs.get(v).copied().unwrap_or((0.0, 0.0));
+            let (sum, count) = &mut heights[place[v]];
+            *sum += height.scalar(u, w, p);
+            *count += 1;
+        }
+
+        for (v, p) in mesh.positions.iter_mut().enumerate() {
+            let direction = directions[place[v]];
+            if direction.length() > 0.0 {
+                let (sum, count) = heights[place[v]];
+                *p += scale * sum / count as f64 * direction.unit_vector();
+            }
+        }
+
+        mesh.compute_normals(Weighting::Angle, crease_angle);
+        mesh
+    }
+
+    // adds a vertex halfway between vertices `a` and `b`, returning its index
+    fn add_middle(&mut self, a: usize, b: usize) -> usize {
+        self.positions.push(0.5 * (self.positions[a] + self.positions[b]));
+        if !self.normals.is_empty() {
+            let n = self.normals[a] + self.normals[b];
+            self.normals.push(if n.length() > 0.0 {n.unit_vector()} else {n});
+        }
+        if !self.colors.is_empty() {
+            self.colors.push(0.5 * (self.colors[a] + self.colors[b]));
+        }
+        if !self.uvs.is_empty() {
+            let (ta, tb) = (self.uvs[a], self.uvs[b]);
+            self.uvs.push((0.5 * (ta.0 + tb.0), 0.5 * (ta.1 + tb.1)));
+        }
+        self.positions.len() - 1
+    }
+}
+
+// adds the triangles `corners` splits into given the middles of the edges being split, where
+// `middle[i]` is on the edge from corner `i` to the next
+fn split(positions: &[Point3], corners: [usize; 3], middle: [Option<usize>; 3], triangles: &mut Vec<[usize; 3]>) {
+    match middle.iter().filter(|m| m.is_some()).count() {
+        0 => triangles.push(corners),
+        3 => {
+            let [a, b, c] = corners;
+            let [ab, bc, ca] = middle.map(Option::unwrap);
+            triangles.extend([[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]);
+        }
+        count => {
+            // turned so that the first edge is split, and for two, the second too
+            let r = (0..3).find(|&r| middle[r].is_some() && (count == 1 || middle[(r + 1) % 3].is_some())).unwrap();
+            let [a, b, c] = [0, 1, 2].map(|i| corners[(r + i) % 3]);
+            let ab = middle[r].unwrap();
+            match middle[(r + 1) % 3] {
+                None => triangles.extend([[a, ab, c], [ab, b, c]]),
+                Some(bc) => {
+                    triangles.push([ab, b, bc]);
+                    // the rest is a quad, cut across its shorter diagonal
+                    if (positions[a] - positions[bc]).length() < (positions[ab] - positions[c]).length() {
+                        triangles.extend([[a, ab, bc], [a, bc, c]]);
+                    } else {
+                        triangles.extend([[a, ab, c], [ab, bc, c]]);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use std::f64::consts::PI;
+use std::io;
+use std::path::Path;
+
+use crate::color::{luminance, Color};
+use crate::util::random;
+use crate::vec3::Vec3;
+
+/// The light arriving from infinitely far away, seen by rays that don't hit anything.
+///
+/// Environments can also be sampled, so that surfaces can look for light from them directly instead
+/// of waiting for a bounce to happen to escape towards something bright.
+pub trait Environment: Send + Sync {
+    /// The radiance arriving from `direction`, which need not be unit length.
+    fn radiance(&self, direction: Vec3) -> Color;
+
+    /// A unit direction to look for light in, the radiance from it, and the probability density (per
+    /// unit solid angle) of having chosen it. The default picks uniformly over the sphere.
+    fn sample(&self) -> (Vec3, Color, f64) {
+        let direction = Vec3::random_unit_vector();
+        (direction, self.radiance(direction), self.pdf(direction))
+    }
+
+    /// The probability density of `sample` choosing `direction`.
+    fn pdf(&self, _direction: Vec3) -> f64 {
+        1.0 / (4.0 * PI)
+    }
+}
+
+/// The white to blue gradient from the book.
+pub struct GradientSky;
+
+impl Environment for GradientSky {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let unit_direction = direction.unit_vector();
+        let a = 0.5 * (unit_direction.y + 1.0);
+        (1.0 - a) * Color::new(1,1,1) + a * Color::new(0.5, 0.7, 1.0)
+    }
+}
+
+/// The same light from every direction.
+pub struct ConstantEnvironment {
+    radiance: Color,
+}
+
+impl ConstantEnvironment {
+    pub fn new(radiance: Color) -> Self {
+        Self {
+            radiance,
+        }
+    }
+}
+
+impl Environment for ConstantEnvironment {
+    fn radiance(&self, _direction: Vec3) -> Color {
+        self.radiance
+    }
+}
+
+/// A high dynamic range panorama in equirectangular (latitude-longitude) layout, with +y up.
+///
+/// Directions are sampled in proportion to the luminance of the image, so small bright features like
+/// the sun in a photographed sky light the scene without much noise.
+pub struct ImageEnvironment {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    rotation: f64,
+    intensity: f64,
+    rows: Distribution,
+    columns: Vec<Distribution>,
+}
+
+impl ImageEnvironment {
+    /// Loads a `.hdr` or `.exr` panorama. `rotation` turns it about the vertical axis, in degrees,
+    /// and `intensity` scales its brightness.
+    pub fn load(path: impl AsRef<Path>, rotation: f64, intensity: f64) -> io::Result<Self> {
+        let image = image::open(path).map_err(io::Error::other)?.into_rgb32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = image.pixels().map(|p| Color::new(p[0], p[1], p[2])).collect();
+
+        Ok(Self::new(width, height, pixels, rotation, intensity))
+    }
+
+    /// An environment from `width * height` pixels in scanline order, top row first.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>, rotation: f64, intensity: f64) -> Self {
+        assert_eq!(pixels.len(), width * height);
+
+        // rows near the poles are squashed into less solid angle, so they're chosen less often
+        let columns: Vec<Distribution> = (0..height).map(|j| {
+            let sin_theta = (PI * (j as f64 + 0.5) / height as f64).sin();
+            Distribution::new(pixels[j * width..(j + 1) * width].iter().map(|p| luminance(*p) * sin_theta).collect())
+        }).collect();
+        let rows = Distribution::new(columns.iter().map(|c| c.total).collect());
+
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: rotation.to_radians(),
+            intensity,
+            rows,
+            columns,
+        }
+    }
+
+    // texture coordinates in [0, 1) of a unit direction, u going around from -x and v down from +y
+    fn direction_to_uv(&self, d: Vec3) -> (f64, f64) {
+        let phi = (-d.z).atan2(d.x) + PI - self.rotation;
+        let u = phi.rem_euclid(2.0 * PI) / (2.0 * PI);
+        let v = d.y.clamp(-1.0, 1.0).acos() / PI;
+        (u, v)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = 2.0 * PI * u + self.rotation - PI;
+        let theta = PI * v;
+        Vec3::new(theta.sin() * phi.cos(), theta.cos(), -theta.sin() * phi.sin())
+    }
+
+    fn texel(&self, u: f64, v: f64) -> (usize, usize) {
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        (i, j)
+    }
+}
+
+impl Environment for ImageEnvironment {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let (u, v) = self.direction_to_uv(direction.unit_vector());
+        let (i, j) = self.texel(u, v);
+        self.intensity * self.pixels[j * self.width + i]
+    }
+
+    fn sample(&self) -> (Vec3, Color, f64) {
+        if self.rows.total <= 0.0 {
+            let direction = Vec3::random_unit_vector();
+            return (direction, self.radiance(direction), 1.0 / (4.0 * PI));
+        }
+
+        let j = self.rows.sample();
+        let i = self.columns[j].sample();
+        let u = (i as f64 + random::<f64>()) / self.width as f64;
+        let v = (j as f64 + random::<f64>()) / self.height as f64;
+
+        let direction = self.uv_to_direction(u, v);
+        (direction, self.intensity * self.pixels[j * self.width + i], self.pdf(direction))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        if self.rows.total <= 0.0 {
+            return 1.0 / (4.0 * PI);
+        }
+
+        let (u, v) = self.direction_to_uv(direction.unit_vector());
+        let (i, j) = self.texel(u, v);
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            return 0.0;
+        }
+
+        // the density over the image, converted to solid angle
+        let pdf_uv = self.rows.probability(j) * self.columns[j].probability(i) * (self.width * self.height) as f64;
+        pdf_uv / (2.0 * PI * PI * sin_theta)
+    }
+}
+
+// picks an index with probability proportional to its weight
+struct Distribution {
+    weights: Vec<f64>,
+    cdf: Vec<f64>,
+    total: f64,
+}
+
+impl Distribution {
+    fn new(weights: Vec<f64>) -> Self {
+        let mut cdf = Vec::with_capacity(weights.len());
+        let mut total = 0.0;
+        for weight in &weights {
+            total += weight;
+            cdf.push(total);
+        }
+
+        Self {
+            weights,
+            cdf,
+            total,
+        }
+    }
+
+    fn sample(&self) -> usize {
+        let target = random::<f64>() * self.total;
+        // skip zero-weight entries, which can never be chosen
+        self.cdf.partition_point(|c| *c <= target).min(self.cdf.len() - 1)
+    }
+
+    fn probability(&self, i: usize) -> f64 {
+        if self.total <= 0.0 {
+            return 0.0;
+        }
+        self.weights[i] / self.total
+    }
+}
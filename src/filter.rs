@@ -0,0 +1,193 @@
+use std::f64::consts::PI;
+
+/// A pixel reconstruction filter. Every sample is splatted into each pixel whose centre lies within
+/// `radius` of it, weighted by the filter, and a pixel is the weighted average of the samples it
+/// receives.
+///
+/// Filters are separable, so `evaluate` only needs to handle one axis.
+pub trait Filter: Send + Sync {
+    /// How far from a pixel centre, in pixels, a sample can contribute to it.
+    fn radius(&self) -> f64;
+
+    /// The weight of a sample `x` pixels from a pixel centre along one axis, zero beyond `radius`.
+    /// Weights integrate to 1 over the filter's width.
+    fn evaluate(&self, x: f64) -> f64;
+}
+
+/// Weights every sample within `radius` equally. With a radius of 0.5 each sample only contributes to
+/// the pixel it was taken in.
+pub struct BoxFilter {
+    radius: f64,
+}
+
+impl BoxFilter {
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+        }
+    }
+}
+
+impl Default for BoxFilter {
+    fn default() -> Self {
+        Self::new(0.5)
+    }
+}
+
+impl Filter for BoxFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        if x.abs() < self.radius {0.5 / self.radius} else {0.0}
+    }
+}
+
+/// Weight falls off linearly to zero at `radius`.
+pub struct TentFilter {
+    radius: f64,
+}
+
+impl TentFilter {
+    pub fn new(radius: f64) -> Self {
+        Self {
+            radius,
+        }
+    }
+}
+
+impl Filter for TentFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        (self.radius - x.abs()).max(0.0) / (self.radius * self.radius)
+    }
+}
+
+/// A Gaussian with standard deviation `sigma`, shifted down so that it reaches zero at `radius`.
+pub struct GaussianFilter {
+    radius: f64,
+    sigma: f64,
+    scale: f64,
+}
+
+impl GaussianFilter {
+    pub fn new(radius: f64, sigma: f64) -> Self {
+        let mut filter = Self {
+            radius,
+            sigma,
+            scale: 1.0,
+        };
+        filter.scale = 1.0 / integral(&filter);
+        filter
+    }
+
+    fn gaussian(&self, x: f64) -> f64 {
+        (-x * x / (2.0 * self.sigma * self.sigma)).exp()
+    }
+}
+
+impl Filter for GaussianFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        self.scale * (self.gaussian(x) - self.gaussian(self.radius)).max(0.0)
+    }
+}
+
+/// The Mitchell–Netravali family of cubics. `b = c = 1/3` is the usual compromise between blurring and
+/// ringing. Its negative lobes sharpen edges.
+pub struct MitchellFilter {
+    radius: f64,
+    b: f64,
+    c: f64,
+}
+
+impl MitchellFilter {
+    pub fn new(radius: f64, b: f64, c: f64) -> Self {
+        Self {
+            radius,
+            b,
+            c,
+        }
+    }
+}
+
+impl Default for MitchellFilter {
+    fn default() -> Self {
+        Self::new(2.0, 1.0 / 3.0, 1.0 / 3.0)
+    }
+}
+
+impl Filter for MitchellFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        // the cubic is defined over [-2, 2], so stretch it to cover the radius
+        let x = (2.0 * x / self.radius).abs();
+        let (b, c) = (self.b, self.c);
+
+        let weight = if x < 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)
+        } else if x < 2.0 {
+            (-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c)
+        } else {
+            0.0
+        };
+        // the cubic integrates to 1 over [-2, 2], and stretching it to the radius scales that too
+        weight / 6.0 * 2.0 / self.radius
+    }
+}
+
+/// A sinc windowed by a wider sinc, with `radius` lobes on each side. Sharp, but rings around bright
+/// edges.
+pub struct LanczosFilter {
+    radius: f64,
+    scale: f64,
+}
+
+impl LanczosFilter {
+    pub fn new(radius: f64) -> Self {
+        let mut filter = Self {
+            radius,
+            scale: 1.0,
+        };
+        filter.scale = 1.0 / integral(&filter);
+        filter
+    }
+}
+
+impl Filter for LanczosFilter {
+    fn radius(&self) -> f64 {
+        self.radius
+    }
+
+    fn evaluate(&self, x: f64) -> f64 {
+        if x.abs() >= self.radius {
+            return 0.0;
+        }
+        self.scale * sinc(x) * sinc(x / self.radius)
+    }
+}
+
+// the integral of `filter` across its width, by the midpoint rule, for filters to scale themselves by
+fn integral(filter: &dyn Filter) -> f64 {
+    const STEPS: usize = 4096;
+    let radius = filter.radius();
+    let step = 2.0 * radius / STEPS as f64;
+    (0..STEPS).map(|i| filter.evaluate(-radius + (i as f64 + 0.5) * step)).sum::<f64>() * step
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
@@ -0,0 +1,452 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use exr::prelude::{AnyChannel, AnyChannels, Encoding, FlatSamples, Image, ImageAttributes, IntegerBounds, Layer, LayerAttributes, SmallVec, WritableImage};
+
+use crate::color::{write_color, Color};
+use crate::denoise::Denoiser;
+use crate::filter::Filter;
+use crate::util::Rng;
+use crate::vec3::{Point3, Vec3};
+
+pub(crate) const TILE_SIZE: u32 = 16;
+
+// the least total filter weight per sample a pixel can have and still be divided by
+const MIN_WEIGHT: f64 = 1e-3;
+
+/// The images a render produces: the beauty colour and the arbitrary output variables rendered
+/// alongside it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pass {
+    Beauty,
+    /// Distance along the camera's view direction to the first hit.
+    Depth,
+    /// World-space normal at the first hit, facing the camera.
+    Normal,
+    /// Surface colour at the first hit.
+    Albedo,
+    /// World-space position of the first hit.
+    Position,
+    /// Which object of the world was hit first, as numbered by its [`HittableList`](crate::HittableList),
+    /// or 0 where nothing was.
+    ObjectId,
+    /// Which material was hit first, as numbered by the world's [`HittableList`](crate::HittableList),
+    /// or 0 where nothing was.
+    MaterialId,
+}
+
+impl Pass {
+    pub const ALL: [Pass; 7] = [Pass::Beauty, Pass::Depth, Pass::Normal, Pass::Albedo, Pass::Position, Pass::ObjectId, Pass::MaterialId];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Pass::Beauty => "beauty",
+            Pass::Depth => "depth",
+            Pass::Normal => "normal",
+            Pass::Albedo => "albedo",
+            Pass::Position => "position",
+            Pass::ObjectId => "objectId",
+            Pass::MaterialId => "materialId",
+        }
+    }
+
+    fn channels(self) -> &'static [&'static str] {
+        match self {
+            Pass::Beauty | Pass::Albedo => &["R", "G", "B"],
+            Pass::Depth => &["Z"],
+            Pass::Normal | Pass::Position => &["X", "Y", "Z"],
+            Pass::ObjectId | Pass::MaterialId => &["id"],
+        }
+    }
+}
+
+// what the first sample of a pixel hit. Unlike colour, normals and albedo these can't be meaningfully
+// averaged across samples, so they're taken from one sample rather than smeared across edges
+#[derive(Clone, Copy)]
+pub(crate) struct FirstHit {
+    pub depth: f64,
+    pub position: Point3,
+    pub object_id: u32,
+    pub material_id: u32,
+}
+
+impl Default for FirstHit {
+    fn default() -> Self {
+        Self {
+            depth: f64::INFINITY,
+            position: Point3::default(),
+            object_id: 0,
+            material_id: 0,
+        }
+    }
+}
+
+// a tile's samples are splatted into the pixels around it as well as its own, so it keeps filtered
+// sums for a padded rectangle overlapping its neighbours, which are combined when the image is read
+pub(crate) struct Tile {
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+    pub samples: u32,
+    pub padded: Rect,
+    pub sums: Vec<Color>,
+    pub weights: Vec<f64>,
+    // the tile's own pixels' samples summed without the filter, to fall back on
+    pub box_sums: Vec<Color>,
+    pub normal_sums: Vec<Vec3>,
+    pub albedo_sums: Vec<Color>,
+    pub first_hits: Vec<FirstHit>,
+    pub rng: Rng,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Rect {
+    pub x0: u32,
+    pub y0: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        self.x0 <= x && x < self.x0 + self.width && self.y0 <= y && y < self.y0 + self.height
+    }
+
+    pub fn index(&self, x: u32, y: u32) -> usize {
+        ((y - self.y0) * self.width + (x - self.x0)) as usize
+    }
+}
+
+impl Tile {
+    pub fn new(x0: u32, y0: u32, width: u32, height: u32, padded: Rect, rng: Rng) -> Self {
+        let pixels = (width * height) as usize;
+        let padded_pixels = (padded.width * padded.height) as usize;
+
+        Self {
+            x0,
+            y0,
+            width,
+            height,
+            samples: 0,
+            padded,
+            sums: vec![Color::default(); padded_pixels],
+            weights: vec![0.0; padded_pixels],
+            box_sums: vec![Color::default(); pixels],
+            normal_sums: vec![Vec3::default(); pixels],
+            albedo_sums: vec![Color::default(); pixels],
+            first_hits: vec![FirstHit::default(); pixels],
+            rng,
+        }
+    }
+
+    // adds `color`, sampled at `x`,`y` in pixel coordinates (where pixel i,j has its centre at i,j), to
+    // every pixel in reach of `filter`
+    pub fn splat(&mut self, x: f64, y: f64, color: Color, filter: &dyn Filter) {
+        let radius = filter.radius();
+        let first = |c: f64| (c - radius).ceil().max(0.0) as u32;
+        let last = |c: f64| (c + radius).floor().max(0.0) as u32;
+
+        for py in first(y)..=last(y) {
+            for px in first(x)..=last(x) {
+                if !self.padded.contains(px, py) {
+                    continue;
+                }
+
+                let weight = filter.evaluate(x - px as f64) * filter.evaluate(y - py as f64);
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let i = self.padded.index(px, py);
+                self.sums[i] += weight * color;
+                self.weights[i] += weight;
+            }
+        }
+    }
+}
+
+/// The result of a render: the sum of every sample taken for each pixel, split into tiles that render
+/// independently, along with the other [`Pass`]es.
+pub struct Framebuffer {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) pad: u32,
+    pub(crate) tiles: Vec<Tile>,
+    denoised: Option<Vec<Color>>,
+}
+
+impl Framebuffer {
+    // `pad` is how many pixels beyond its edges a tile's samples can reach
+    pub(crate) fn new(width: u32, height: u32, pad: u32, seed: u64) -> Self {
+        let mut tiles = Vec::new();
+
+        for y0 in (0..height).step_by(TILE_SIZE as usize) {
+            for x0 in (0..width).step_by(TILE_SIZE as usize) {
+                let tile_width = TILE_SIZE.min(width - x0);
+                let tile_height = TILE_SIZE.min(height - y0);
+                // each tile gets its own stream so the result doesn't depend on which thread renders it
+                let rng = Rng::new(seed, tiles.len() as u64);
+
+                let padded_x0 = x0.saturating_sub(pad);
+                let padded_y0 = y0.saturating_sub(pad);
+                let padded = Rect {
+                    x0: padded_x0,
+                    y0: padded_y0,
+                    width: (x0 + tile_width + pad).min(width) - padded_x0,
+                    height: (y0 + tile_height + pad).min(height) - padded_y0,
+                };
+
+                tiles.push(Tile::new(x0, y0, tile_width, tile_height, padded, rng));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            pad,
+            tiles,
+            denoised: None,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The number of samples every pixel has received.
+    pub fn samples(&self) -> u32 {
+        self.tiles.iter().map(|tile| tile.samples).min().unwrap_or(0)
+    }
+
+    // the tile containing x,y and the index of the pixel within it
+    fn locate(&self, x: u32, y: u32) -> (&Tile, usize) {
+        let tiles_across = self.width.div_ceil(TILE_SIZE);
+        let tile = &self.tiles[((y / TILE_SIZE) * tiles_across + x / TILE_SIZE) as usize];
+        (tile, ((y - tile.y0) * tile.width + (x - tile.x0)) as usize)
+    }
+
+    // every tile whose padding reaches x,y
+    fn tiles_reaching(&self, x: u32, y: u32) -> impl Iterator<Item = &Tile> {
+        let tiles_across = self.width.div_ceil(TILE_SIZE);
+        let tiles_down = self.height.div_ceil(TILE_SIZE);
+        let columns = x.saturating_sub(self.pad) / TILE_SIZE..=((x + self.pad) / TILE_SIZE).min(tiles_across - 1);
+        let rows = y.saturating_sub(self.pad) / TILE_SIZE..=((y + self.pad) / TILE_SIZE).min(tiles_down - 1);
+
+        rows.flat_map(move |row| columns.clone().map(move |column| &self.tiles[(row * tiles_across + column) as usize]))
+    }
+
+    fn average(tile: &Tile, sum: Vec3) -> Vec3 {
+        if tile.samples == 0 {
+            return Vec3::default();
+        }
+        sum / tile.samples as f64
+    }
+
+    /// The colour of the pixel at `x`,`y`, in linear colour. This is the denoised colour once
+    /// [`Framebuffer::denoise`] has been called, and the average of the samples taken before then.
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        match &self.denoised {
+            Some(denoised) => denoised[(y * self.width + x) as usize],
+            None => self.noisy_pixel(x, y),
+        }
+    }
+
+    /// The filtered average of the samples around the pixel at `x`,`y`, whether or not it's been
+    /// denoised.
+    pub fn noisy_pixel(&self, x: u32, y: u32) -> Color {
+        let mut sum = Color::default();
+        let mut weight = 0.0;
+
+        for tile in self.tiles_reaching(x, y) {
+            if tile.padded.contains(x, y) {
+                let i = tile.padded.index(x, y);
+                sum += tile.sums[i];
+                weight += tile.weights[i];
+            }
+        }
+
+        // filters with negative lobes can all but cancel their weights out, and dividing by what's left
+        // would blow the pixel up, so it falls back to the plain average of its own samples
+        let (tile, i) = self.locate(x, y);
+        if weight <= MIN_WEIGHT * tile.samples as f64 {
+            return Self::average(tile, tile.box_sums[i]);
+        }
+        sum / weight
+    }
+
+    /// Replaces the beauty pass with a denoised version of it.
+    pub fn denoise(&mut self, denoiser: &Denoiser) {
+        self.denoised = Some(denoiser.denoise(self));
+    }
+
+    /// Camera-space depth of the pixel at `x`,`y`; infinite where nothing was hit.
+    pub fn depth(&self, x: u32, y: u32) -> f64 {
+        let (tile, i) = self.locate(x, y);
+        tile.first_hits[i].depth
+    }
+
+    /// The average world-space normal of the pixel at `x`,`y`.
+    pub fn normal(&self, x: u32, y: u32) -> Vec3 {
+        let (tile, i) = self.locate(x, y);
+        Self::average(tile, tile.normal_sums[i])
+    }
+
+    /// The average albedo of the pixel at `x`,`y`.
+    pub fn albedo(&self, x: u32, y: u32) -> Color {
+        let (tile, i) = self.locate(x, y);
+        Self::average(tile, tile.albedo_sums[i])
+    }
+
+    pub fn position(&self, x: u32, y: u32) -> Point3 {
+        let (tile, i) = self.locate(x, y);
+        tile.first_hits[i].position
+    }
+
+    pub fn object_id(&self, x: u32, y: u32) -> u32 {
+        let (tile, i) = self.locate(x, y);
+        tile.first_hits[i].object_id
+    }
+
+    pub fn material_id(&self, x: u32, y: u32) -> u32 {
+        let (tile, i) = self.locate(x, y);
+        tile.first_hits[i].material_id
+    }
+
+    /// Writes the image as a gamma-corrected plain PPM.
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "P3\n{} {}\n255", self.width, self.height)?;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                write_color(out, self.pixel(x, y))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes every pass to `path` as a multi-layer OpenEXR file, with one layer per pass named by
+    /// [`Pass::name`].
+    pub fn write_exr(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let layers: Vec<_> = Pass::ALL.iter().map(|pass| self.exr_layer(*pass)).collect();
+        let bounds = IntegerBounds::from_dimensions((self.width as usize, self.height as usize));
+
+        Image::from_layers(ImageAttributes::new(bounds), layers)
+            .write()
+            .to_file(path)
+            .map_err(io::Error::other)
+    }
+
+    /// Writes a single pass to `path` as an OpenEXR file.
+    pub fn write_pass_exr(&self, pass: Pass, path: impl AsRef<Path>) -> io::Result<()> {
+        Image::from_layer(self.exr_layer(pass))
+            .write()
+            .to_file(path)
+            .map_err(io::Error::other)
+    }
+
+    fn exr_layer(&self, pass: Pass) -> Layer<AnyChannels<FlatSamples>> {
+        let channels = pass.channels().iter().enumerate().map(|(c, name)| {
+            AnyChannel::new(*name, self.exr_samples(pass, c))
+        });
+
+        Layer::new(
+            (self.width as usize, self.height as usize),
+            LayerAttributes::named(pass.name()),
+            Encoding::FAST_LOSSLESS,
+            AnyChannels::sort(SmallVec::from_iter(channels)),
+        )
+    }
+
+    // channel `c` of `pass`, in scanline order
+    fn exr_samples(&self, pass: Pass, c: usize) -> FlatSamples {
+        let pixels = (0..self.height).flat_map(|y| (0..self.width).map(move |x| (x, y)));
+        let component = |v: Vec3| [v.x, v.y, v.z][c] as f32;
+
+        match pass {
+            Pass::Beauty => FlatSamples::F32(pixels.map(|(x, y)| component(self.pixel(x, y))).collect()),
+            Pass::Depth => FlatSamples::F32(pixels.map(|(x, y)| self.depth(x, y) as f32).collect()),
+            Pass::Normal => FlatSamples::F32(pixels.map(|(x, y)| component(self.normal(x, y))).collect()),
+            Pass::Albedo => FlatSamples::F32(pixels.map(|(x, y)| component(self.albedo(x, y))).collect()),
+            Pass::Position => FlatSamples::F32(pixels.map(|(x, y)| component(self.position(x, y))).collect()),
+            Pass::ObjectId => FlatSamples::U32(pixels.map(|(x, y)| self.object_id(x, y)).collect()),
+            Pass::MaterialId => FlatSamples::U32(pixels.map(|(x, y)| self.material_id(x, y)).collect()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{LanczosFilter, MitchellFilter};
+
+    // a colour that changes sharply across the image, so that the filters have edges to ring around
+    fn color_at(x: f64, y: f64) -> Color {
+        Color::new((x * 0.7).sin().abs(), if x + y > 20.0 {1.0} else {0.0}, (y * 0.3).cos().abs())
+    }
+
+    // the offsets within a pixel its samples are taken at
+    fn offsets() -> Vec<(f64, f64)> {
+        (0..4).flat_map(|j| (0..4).map(move |i| (-0.375 + 0.25 * i as f64, -0.375 + 0.25 * j as f64))).collect()
+    }
+
+    #[test]
+    fn tiles_filter_like_one_image() {
+        let (width, height) = (40, 24);
+        let filters: [Box<dyn Filter>; 2] = [Box::new(MitchellFilter::default()), Box::new(LanczosFilter::new(3.0))];
+        for filter in filters {
+            let pad = (filter.radius() - 0.5).ceil() as u32;
+            let mut fb = Framebuffer::new(width, height, pad, 0);
+            for tile in &mut fb.tiles {
+                for y in tile.y0..tile.y0 + tile.height {
+                    for x in tile.x0..tile.x0 + tile.width {
+                        for (dx, dy) in offsets() {
+                            let (sx, sy) = (x as f64 + dx, y as f64 + dy);
+                            tile.splat(sx, sy, color_at(sx, sy), filter.as_ref());
+                        }
+                    }
+                }
+                tile.samples = offsets().len() as u32;
+            }
+
+            // every sample filtered straight into every pixel, with no tiles
+            for py in 0..height {
+                for px in 0..width {
+                    let (mut sum, mut weight) = (Color::default(), 0.0);
+                    for y in 0..height {
+                        for x in 0..width {
+                            for (dx, dy) in offsets() {
+                                let (sx, sy) = (x as f64 + dx, y as f64 + dy);
+                                let w = filter.evaluate(sx - px as f64) * filter.evaluate(sy - py as f64);
+                                sum += w * color_at(sx, sy);
+                                weight += w;
+                            }
+                        }
+                    }
+
+                    let error = (fb.noisy_pixel(px, py) - sum / weight).length();
+                    assert!(error < 1e-9, "off by {error} at {px},{py}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cancelled_weights_fall_back_to_the_box_average() {
+        let mut fb = Framebuffer::new(4, 4, 1, 0);
+        let tile = &mut fb.tiles[0];
+        tile.samples = 2;
+        let i = tile.padded.index(1, 1);
+        tile.sums[i] = Color::new(1, 1, 1);
+        tile.weights[i] = 1e-12;
+        tile.box_sums[(tile.width + 1) as usize] = Color::new(0.2, 0.4, 0.6);
+
+        let error = (fb.noisy_pixel(1, 1) - Color::new(0.1, 0.2, 0.3)).length();
+        assert!(error < 1e-12, "off by {error}");
+    }
+}
@@ -1,25 +1,71 @@
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::color::Color;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::ray::*;
 use crate::vec3::*;
 
+/// Where a ray hit a surface. `normal` always faces against the ray, and `front_face` records whether
+/// that is the outward side of the surface. `u` and `v` are the surface's texture coordinates there,
+/// and `dpdu` and `dpdv` how the point moves as they change.
+///
+/// `normal` is the shading normal materials scatter around, which normal and bump maps can tilt away
+/// from `geometric_normal`, the true normal of the surface, facing the same way.
+///
+/// `object_id` and `material_id` number the object and material hit for the ID passes. They're 0 until
+/// the hit passes through a [`HittableList`](crate::HittableList), which numbers what's in it.
+///
+/// Hits on a mesh's triangles also give how much of each of the triangle's corners the point is, in
+/// `barycentric`, and the vertex colour blended from the corners in `color`, if the mesh has them.
+#[derive(Clone)]
 pub struct HitRecord {
     pub p: Point3,
     pub normal: Vec3,
+    pub geometric_normal: Vec3,
+    pub dpdu: Vec3,
+    pub dpdv: Vec3,
     pub mat: Arc<dyn Material>,
     pub t: f64,
+    pub u: f64,
+    pub v: f64,
+    pub barycentric: Option<[f64; 3]>,
+    pub color: Option<Color>,
     pub front_face: bool,
+    pub object_id: u32,
+    pub material_id: u32,
 }
 
 impl HitRecord {
+    /// Sets `normal`, `geometric_normal` and `front_face` from the surface's outward normal, which must
+    /// be unit length.
     pub fn set_face_normal(&mut self, r: &Ray, outward_normal: &Vec3) {
         self.front_face = r.dir.dot(outward_normal) < 0.0;
         self.normal = if self.front_face {*outward_normal} else {-*outward_normal};
+        self.geometric_normal = self.normal;
     }
 }
 
+/// Anything a ray can hit.
 pub trait Hittable: Send + Sync {
-    fn hit(&self, r: &Ray, ray: Interval) -> Option<HitRecord>;
-}
\ No newline at end of file
+    /// The closest hit along `r` with `t` strictly inside `ray_t`, if any.
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord>;
+
+    /// The fraction of light getting along `r` over `ray_t` without being stopped, for shadow rays.
+    /// Solid objects let none through if they're hit at all; volumes can let some through.
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        if self.hit(r, ray_t).is_some() {0.0} else {1.0}
+    }
+
+    /// A box the object fits inside, or `None` if it's unbounded or can't tell.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// The materials of the object's surfaces, always in the same order, so that a
+    /// [`HittableList`](crate::HittableList) can number them for the material ID pass.
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        Vec::new()
+    }
+}
@@ -1,34 +1,84 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
-use crate::{hittable::*, interval::Interval, ray::Ray};
+use crate::{aabb::Aabb, hittable::*, interval::Interval, material::Material, ray::Ray};
 
+/// A scene, or part of one, made up of other hittables.
+///
+/// The list numbers what's in it for the ID passes: its objects from 1 in the order they're added, and
+/// their materials from 1 in the order they're first used, so objects sharing a material share its ID.
+/// A scene built the same way gets the same IDs, however many others were built before it.
 #[derive(Default)]
 pub struct HittableList {
     objects: Vec<Arc<dyn Hittable>>,
+    // every material of the objects, in the order they're numbered. Holding them keeps their addresses
+    // from being reused by other materials, so the addresses can key their IDs
+    materials: Vec<Arc<dyn Material>>,
+    material_ids: HashMap<usize, u32>,
 }
 
 impl HittableList {
     pub fn add(&mut self, object: Arc<dyn Hittable>) {
+        for mat in object.materials() {
+            let next = self.materials.len() as u32 + 1;
+            self.material_ids.entry(key(&mat)).or_insert_with(|| {
+                self.materials.push(Arc::clone(&mat));
+                next
+            });
+        }
         self.objects.push(object);
     }
 
     pub fn clear(&mut self) {
         self.objects.clear();
+        self.materials.clear();
+        self.material_ids.clear();
     }
 }
 
+fn key(mat: &Arc<dyn Material>) -> usize {
+    Arc::as_ptr(mat) as *const () as usize
+}
+
 impl Hittable for HittableList {
+    #[allow(clippy::needless_return)]
     fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
         let mut temp_rec = None;
         let mut closest_so_far = ray_t.max;
 
-        for object in &self.objects {
-            if let Some(hit_record) = object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
+        for (i, object) in self.objects.iter().enumerate() {
+            if let Some(mut hit_record) = object.hit(r, Interval::new(ray_t.min, closest_so_far)) {
                 closest_so_far = hit_record.t;
+                hit_record.object_id = i as u32 + 1;
+                if let Some(&id) = self.material_ids.get(&key(&hit_record.mat)) {
+                    hit_record.material_id = id;
+                }
                 temp_rec = Some(hit_record);
             }
         }
 
         return temp_rec;
     }
-}
\ No newline at end of file
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let mut transmittance = 1.0;
+        for object in &self.objects {
+            transmittance *= object.transmittance(r, ray_t);
+            if transmittance <= 0.0 {
+                break;
+            }
+        }
+
+        transmittance
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut objects = self.objects.iter();
+        let first = objects.next()?.bounding_box()?;
+        objects.try_fold(first, |aabb, object| Some(aabb.union(&object.bounding_box()?)))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        self.materials.clone()
+    }
+}
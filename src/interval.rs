@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Interval {
     pub min: f64,
     pub max: f64,
@@ -19,6 +20,7 @@ impl Interval {
         self.min < item && item < self.max
     }
 
+    #[allow(clippy::needless_return)]
     pub fn clamp(&self, item: f64) -> f64 {
         if item < self.min {return self.min}
         if item > self.max {return self.max}
@@ -0,0 +1,140 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::microfacet::{fresnel_dielectric, Ggx};
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::util::random;
+use crate::vec3::Vec3;
+
+/// A blend of two materials, such as dirt over metal, with `weight` the fraction of `b` at each point:
+/// 0 for `a` alone and 1 for `b` alone. Each scattering picks one of them at random in that proportion.
+pub struct MixMaterial {
+    a: Arc<dyn Material>,
+    b: Arc<dyn Material>,
+    weight: Arc<dyn Texture>,
+}
+
+impl MixMaterial {
+    pub fn new(a: Arc<dyn Material>, b: Arc<dyn Material>, weight: Arc<dyn Texture>) -> Self {
+        Self {
+            a,
+            b,
+            weight,
+        }
+    }
+
+    fn weight(&self, rec: &HitRecord) -> f64 {
+        self.weight.scalar(rec.u, rec.v, rec.p).clamp(0.0, 1.0)
+    }
+}
+
+impl Material for MixMaterial {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        if random::<f64>() < self.weight(rec) {self.b.scatter(r_in, rec)} else {self.a.scatter(r_in, rec)}
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        // light can only be sampled directly if neither material scatters into a single direction, as
+        // that direction would be picked with a probability density `evaluate` can't report
+        let (f_a, pdf_a) = self.a.evaluate(r_in, rec, direction)?;
+        let (f_b, pdf_b) = self.b.evaluate(r_in, rec, direction)?;
+
+        let w = self.weight(rec);
+        Some((f_a * (1.0 - w) + f_b * w, pdf_a * (1.0 - w) + pdf_b * w))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        let w = self.weight(rec);
+        self.a.albedo(rec) * (1.0 - w) + self.b.albedo(rec) * w
+    }
+
+    fn disperses(&self) -> bool {
+        self.a.disperses() || self.b.disperses()
+    }
+}
+
+/// A clear varnish with index of refraction `ior` over another material, such as the clearcoat on car
+/// paint. The varnish reflects some light off its surface, more at grazing angles, and what gets through
+/// to `base` and back out is dimmed by the same amount on the way in and on the way out.
+///
+/// `roughness` between 0 (smooth) and 1 blurs the varnish's reflection. Rays hitting the back of the
+/// surface see `base` alone.
+pub struct Coated {
+    base: Arc<dyn Material>,
+    ior: f64,
+    ggx: Ggx,
+}
+
+impl Coated {
+    pub fn new(base: Arc<dyn Material>, ior: f64, roughness: f64) -> Self {
+        Self {
+            base,
+            ior,
+            ggx: Ggx::new(roughness),
+        }
+    }
+
+    // the fraction of light let through the varnish into and back out of the base
+    fn transmittance(&self, n: Vec3, wo: Vec3, wi: Vec3) -> f64 {
+        let cos_i = n.dot(&wi.unit_vector()).abs();
+        (1.0 - fresnel_dielectric(n.dot(&wo), self.ior)) * (1.0 - fresnel_dielectric(cos_i, self.ior))
+    }
+}
+
+impl Material for Coated {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        if !rec.front_face {
+            return self.base.scatter(r_in, rec);
+        }
+
+        // reflect off the varnish in proportion to how much of the light it reflects, otherwise scatter
+        // from the base
+        let (n, wo) = (rec.normal, -r_in.dir.unit_vector());
+        let p_coat = fresnel_dielectric(n.dot(&wo), self.ior);
+        if random::<f64>() < p_coat {
+            let m = self.ggx.sample_visible_normal(n, wo);
+            let wi = Vec3::reflect(&-wo, &m);
+            if n.dot(&wi) <= 0.0 {
+                return None;
+            }
+
+            let fresnel = fresnel_dielectric(wo.dot(&m), self.ior);
+            let atten = fresnel * self.ggx.g2(n, wo, wi) / self.ggx.g1(n, wo) / p_coat;
+            Some((Color::new(atten, atten, atten), Ray::new(rec.p, wi)))
+        } else {
+            let (atten, scatter) = self.base.scatter(r_in, rec)?;
+            let atten = atten * (self.transmittance(n, wo, scatter.dir) / (1.0 - p_coat));
+            Some((atten, scatter))
+        }
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let (f_base, pdf_base) = self.base.evaluate(r_in, rec, direction)?;
+        if !rec.front_face {
+            return Some((f_base, pdf_base));
+        }
+
+        let (n, wo, wi) = (rec.normal, -r_in.dir.unit_vector(), direction.unit_vector());
+        let p_coat = fresnel_dielectric(n.dot(&wo), self.ior);
+        let mut f_cos = f_base * self.transmittance(n, wo, wi);
+        let mut pdf = pdf_base * (1.0 - p_coat);
+
+        if let Some((g, g_pdf, m)) = self.ggx.reflection(n, wo, wi) {
+            let coat = fresnel_dielectric(wo.dot(&m), self.ior) * g;
+            f_cos += Color::new(coat, coat, coat);
+            pdf += p_coat * g_pdf;
+        }
+        Some((f_cos, pdf))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn disperses(&self) -> bool {
+        self.base.disperses()
+    }
+}
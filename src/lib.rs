@@ -0,0 +1,88 @@
+//! A path tracer following [Ray Tracing in One Weekend](https://raytracing.github.io/books/RayTracingInOneWeekend.html).
+//!
+//! Build a scene by adding [`Hittable`]s such as [`Sphere`]s, each with a [`Material`], to a
+//! [`HittableList`], then render it through a [`Camera`] made with a [`CameraBuilder`]:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! use raytracing::{CameraBuilder, Color, HittableList, Lambertian, Point3, Sphere};
+//!
+//! let mut world = HittableList::default();
+//! let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+//! world.add(Arc::new(Sphere::new(Point3::new(0, 0, -1), 0.5, material)));
+//!
+//! let cam = CameraBuilder::new().width(400).build();
+//! let fb = cam.render(&world);
+//! fb.write_ppm(&mut std::io::stdout().lock()).unwrap();
+//! ```
+
+pub mod aabb;
+pub mod alpha_mask;
+pub mod camera;
+pub mod checkpoint;
+pub mod color;
+pub mod csg;
+pub mod denoise;
+mod displacement;
+pub mod environment;
+pub mod filter;
+pub mod framebuffer;
+pub mod hittable;
+pub mod hittable_list;
+pub mod interval;
+pub mod layered;
+pub mod material;
+pub mod mesh;
+mod microfacet;
+pub mod normal_map;
+mod ply;
+pub mod principled;
+pub mod quadric;
+pub mod ray;
+pub mod scene;
+pub mod sdf;
+pub mod sky;
+pub mod sphere;
+mod spectrum;
+mod stl;
+pub mod subdivision;
+pub mod subsurface;
+pub mod texture;
+pub mod thin_film;
+pub mod util;
+pub mod vec3;
+pub mod vertex_color;
+pub mod volume;
+
+pub use aabb::Aabb;
+pub use alpha_mask::AlphaMask;
+pub use camera::{Camera, CameraBuilder};
+pub use checkpoint::Checkpoint;
+pub use color::Color;
+pub use csg::{Csg, Solid, Span};
+pub use denoise::Denoiser;
+pub use environment::{ConstantEnvironment, Environment, GradientSky, ImageEnvironment};
+pub use filter::{BoxFilter, Filter, GaussianFilter, LanczosFilter, MitchellFilter, TentFilter};
+pub use framebuffer::{Framebuffer, Pass};
+pub use hittable::{HitRecord, Hittable};
+pub use hittable_list::HittableList;
+pub use interval::Interval;
+pub use layered::{Coated, MixMaterial};
+pub use material::{Conductor, Dielectric, Lambertian, Material, Metal, RoughDielectric};
+pub use mesh::{Mesh, TriangleMesh, Weighting};
+pub use normal_map::{BumpMap, NormalMap};
+pub use principled::{Principled, PrincipledBuilder};
+pub use quadric::{Cone, Cylinder, Disk, Paraboloid, Torus};
+pub use ray::Ray;
+pub use scene::Scene;
+pub use sdf::{Repetition, Sdf, SdfBox, SdfCapsule, SdfHittable, SdfRoundedBox, SdfSphere, SdfTorus, SmoothUnion, Subtraction, Twist};
+pub use sky::PreethamSky;
+pub use sphere::Sphere;
+pub use subdivision::Subdivision;
+pub use subsurface::Subsurface;
+pub use texture::{CheckerTexture, ImageTexture, Texture};
+pub use thin_film::ThinFilm;
+pub use vec3::{Point3, Vec3};
+pub use vertex_color::VertexColor;
+pub use volume::{HenyeyGreenstein, Volume, VoxelGrid};
@@ -1,31 +1,217 @@
-mod vec3;
-mod color;
-mod ray;
-mod hittable;
-mod sphere;
-mod hittable_list;
-mod interval;
-mod camera;
-mod util;
-mod material;
-
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::Arc;
+use std::time::Duration;
+
+use raytracing::util::{random, random_f64, with_rng, Rng};
+use raytracing::{BoxFilter, CameraBuilder, Checkpoint, Color, Denoiser, Dielectric, Filter, GaussianFilter, Hittable, HittableList, ImageEnvironment, Interval, Lambertian, LanczosFilter, Material, Metal, MitchellFilter, Pass, Point3, PreethamSky, Scene, Sphere, TentFilter, Vec3};
+
+// the scene is generated from a fixed seed so that a resumed render sees the same spheres
+const SCENE_SEED: u64 = 42;
+
+struct Args {
+    samples: Option<u32>,
+    checkpoint: Option<PathBuf>,
+    checkpoint_interval: Duration,
+    resume: bool,
+    denoise: bool,
+    spectral: bool,
+    filter: Option<String>,
+    filter_radius: Option<f64>,
+    environment: Option<PathBuf>,
+    environment_rotation: f64,
+    environment_intensity: f64,
+    sky: bool,
+    sun_elevation: f64,
+    sun_azimuth: f64,
+    turbidity: f64,
+    exr: Option<PathBuf>,
+    aov_dir: Option<PathBuf>,
+    gltf: Option<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        samples: None,
+        checkpoint: None,
+        checkpoint_interval: Duration::from_secs(300),
+        resume: false,
+        denoise: false,
+        spectral: false,
+        filter: None,
+        filter_radius: None,
+        environment: None,
+        environment_rotation: 0.0,
+        environment_intensity: 1.0,
+        sky: false,
+        sun_elevation: 45.0,
+        sun_azimuth: 0.0,
+        turbidity: 3.0,
+        exr: None,
+        aov_dir: None,
+        gltf: None,
+    };
+
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("{arg} needs a value"));
+
+        match arg.as_str() {
+            "--samples" => args.samples = Some(value()?.parse().map_err(|e| format!("--samples: {e}"))?),
+            "--checkpoint" => args.checkpoint = Some(PathBuf::from(value()?)),
+            "--checkpoint-interval" => {
+                let secs: u64 = value()?.parse().map_err(|e| format!("--checkpoint-interval: {e}"))?;
+                args.checkpoint_interval = Duration::from_secs(secs);
+            }
+            "--resume" => args.resume = true,
+            "--denoise" => args.denoise = true,
+            "--spectral" => args.spectral = true,
+            "--filter" => args.filter = Some(value()?),
+            "--environment" => args.environment = Some(PathBuf::from(value()?)),
+            "--environment-rotation" => args.environment_rotation = value()?.parse().map_err(|e| format!("--environment-rotation: {e}"))?,
+            "--environment-intensity" => args.environment_intensity = value()?.parse().map_err(|e| format!("--environment-intensity: {e}"))?,
+            "--sky" => args.sky = true,
+            "--sun-elevation" => args.sun_elevation = value()?.parse().map_err(|e| format!("--sun-elevation: {e}"))?,
+            "--sun-azimuth" => args.sun_azimuth = value()?.parse().map_err(|e| format!("--sun-azimuth: {e}"))?,
+            "--turbidity" => args.turbidity = value()?.parse().map_err(|e| format!("--turbidity: {e}"))?,
+            "--filter-radius" => args.filter_radius = Some(value()?.parse().map_err(|e| format!("--filter-radius: {e}"))?),
+            "--exr" => args.exr = Some(PathBuf::from(value()?)),
+            "--aov-dir" => args.aov_dir = Some(PathBuf::from(value()?)),
+            "--gltf" => args.gltf = Some(PathBuf::from(value()?)),
+            _ => return Err(format!("unknown argument {arg}")),
+        }
+    }
+
+    if let Some(name) = &args.filter {
+        if !["box", "tent", "gaussian", "mitchell", "lanczos"].contains(&name.as_str()) {
+            return Err(format!("unknown filter {name}"));
+        }
+    }
+
+    if args.sky && args.environment.is_some() {
+        return Err("--sky and --environment can't be used together".to_string());
+    }
 
-use camera::Camera;
-use camera::CameraBuilder;
-use interval::Interval;
-use material::Dielectric;
-use material::Lambertian;
-use material::Material;
-use material::Metal;
-use rand::random;
-use crate::color::*;
-use crate::hittable_list::HittableList;
-use crate::sphere::Sphere;
-use crate::util::random_f64;
-use crate::vec3::*;
+    if args.resume && args.checkpoint.is_none() {
+        return Err("--resume needs --checkpoint".to_string());
+    }
+
+    Ok(args)
+}
 
 fn main() {
+    let args = parse_args().unwrap_or_else(|e| {
+        eprintln!("{e}");
+        eprintln!("usage: raytracing [--samples N] [--checkpoint FILE [--checkpoint-interval SECS] [--resume]] [--denoise] [--spectral] [--filter NAME [--filter-radius R]] [--environment FILE [--environment-rotation DEG] [--environment-intensity X]] [--sky [--sun-elevation DEG] [--sun-azimuth DEG] [--turbidity T]] [--exr FILE] [--aov-dir DIR] [--gltf FILE]");
+        process::exit(2);
+    });
+
+    let (world, mut builder) = match &args.gltf {
+        Some(path) => gltf_scene(path),
+        None => {
+            let mut builder = CameraBuilder::new();
+            builder.field_of_view(20.0)
+                .focus(0.6, 10.0)
+                .set_view(Point3::new(13,2,3), Point3::new(0,0,0), Vec3::new(0,1,0));
+            (with_rng(&mut Rng::new(SCENE_SEED, 0), random_scene), builder)
+        }
+    };
+    builder.width(1200);
+    if let Some(samples) = args.samples {
+        builder.samples_per_pixel(samples);
+    }
+    if args.denoise {
+        builder.denoise(Denoiser::default());
+    }
+    if args.spectral {
+        builder.spectral(true);
+    }
+    if let Some(path) = &args.environment {
+        let environment = ImageEnvironment::load(path, args.environment_rotation, args.environment_intensity).unwrap_or_else(|e| {
+            eprintln!("{}: {e}", path.display());
+            process::exit(1);
+        });
+        builder.environment(Arc::new(environment));
+    }
+    if args.sky {
+        let sun_direction = PreethamSky::sun_direction(args.sun_elevation, args.sun_azimuth);
+        builder.environment(Arc::new(PreethamSky::new(sun_direction, args.turbidity, args.environment_intensity)));
+    }
+    if let Some(filter) = make_filter(args.filter.as_deref(), args.filter_radius) {
+        builder.filter(filter);
+    }
+
+    let cam = builder.build();
+
+    let fb = match &args.checkpoint {
+        Some(path) => {
+            let checkpoint = Checkpoint::new(path, args.checkpoint_interval);
+            cam.render_checkpointed(&world, &checkpoint, args.resume).unwrap_or_else(|e| {
+                eprintln!("{}: {e}", path.display());
+                process::exit(1);
+            })
+        }
+        None => cam.render(&world),
+    };
+
+    fb.write_ppm(&mut io::stdout().lock()).expect("failed to write image");
+
+    if let Some(path) = &args.exr {
+        if let Err(e) = fb.write_exr(path) {
+            eprintln!("{}: {e}", path.display());
+            process::exit(1);
+        }
+    }
+
+    if let Some(dir) = &args.aov_dir {
+        for pass in Pass::ALL {
+            let path = dir.join(format!("{}.exr", pass.name()));
+            if let Err(e) = fb.write_pass_exr(pass, &path) {
+                eprintln!("{}: {e}", path.display());
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn make_filter(name: Option<&str>, radius: Option<f64>) -> Option<Arc<dyn Filter>> {
+    let filter: Arc<dyn Filter> = match name? {
+        "box" => Arc::new(BoxFilter::new(radius.unwrap_or(0.5))),
+        "tent" => Arc::new(TentFilter::new(radius.unwrap_or(1.0))),
+        "gaussian" => Arc::new(GaussianFilter::new(radius.unwrap_or(1.5), 0.5)),
+        "mitchell" => Arc::new(MitchellFilter::new(radius.unwrap_or(2.0), 1.0 / 3.0, 1.0 / 3.0)),
+        "lanczos" => Arc::new(LanczosFilter::new(radius.unwrap_or(3.0))),
+        _ => unreachable!("filter names are checked when parsing arguments"),
+    };
+    Some(filter)
+}
+
+// the scene in a glTF file, seen through its first camera, or from in front if it has none
+fn gltf_scene(path: &Path) -> (HittableList, CameraBuilder) {
+    let mut scene = Scene::load_gltf(path).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", path.display());
+        process::exit(1);
+    });
+    let world = scene.world();
+    if !scene.cameras.is_empty() {
+        return (world, scene.cameras.swap_remove(0));
+    }
+
+    let mut builder = CameraBuilder::new();
+    if let Some(bbox) = world.bounding_box() {
+        // far enough back for the whole of the scene's bounding sphere to fit in the view
+        let center = 0.5 * (bbox.min + bbox.max);
+        let radius = 0.5 * (bbox.max - bbox.min).length();
+        let distance = radius / 20.0_f64.to_radians().sin();
+        builder.field_of_view(40.0)
+            .set_view(center + distance * Vec3::new(0.0, 0.3, 1.0).unit_vector(), center, Vec3::new(0,1,0));
+    }
+    (world, builder)
+}
+
+fn random_scene() -> HittableList {
     let mut world = HittableList::default();
 
     let ground_material = Arc::new(Lambertian::new(Color::new(0.5,0.5,0.5)));
@@ -68,55 +254,5 @@ fn main() {
     let material3 = Arc::new(Metal::new(Color::new(0.7, 0.6, 0.5), 0.0));
     world.add(Arc::new(Sphere::new(Point3::new(4,1,0), 1.0, material3)));
 
-    let mut builder = CameraBuilder::new();
-    builder.width(1200)
-        .field_of_view(20.0)
-        .focus(0.6, 10.0)
-        .set_view(Point3::new(13,2,3), Point3::new(0,0,0), Vec3::new(0,1,0));
-
-    let cam = builder.build();
-        
-    /*let cam = Camera::new(
-        16.0 / 9.0,
-        1200,
-        500,
-        50,
-        20.0,
-        Point3::new(13,2,3),
-        Point3::new(0,0,0),
-        Vec3::new(0,1,0),
-        0.6,
-        10.0,
-    );*/
-
-    cam.render(Arc::new(world));
-
-    /* 
-    let ground_material = Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.0)));
-    let material_center = Arc::new(Lambertian::new(Color::new(0.1, 0.2, 0.5)));
-    let material_left   = Arc::new(Dielectric::new(1.5));
-    let material_right  = Arc::new(Metal::new(Color::new(0.8, 0.6, 0.2), 0.0));
-
-    world.add(Arc::new(Sphere::new(Point3::new( 0.0, -100.5, -1.0), 100.0, material_ground)));
-    world.add(Arc::new(Sphere::new(Point3::new( 0.0,    0.0, -1.0),   0.5, material_center)));
-    world.add(Arc::new(Sphere::new(Point3::new(-1.0,    0.0, -1.0),   0.5, material_left.clone())));
-    world.add(Arc::new(Sphere::new(Point3::new(-1.0,    0.0, -1.0),  -0.4, material_left)));
-    world.add(Arc::new(Sphere::new(Point3::new( 1.0,    0.0, -1.0),   0.5, material_right)));
-
-    let mut cam = Camera::new(
-        16.0 / 9.0,
-        400,
-        100,
-        50,
-        20.0,
-        Point3::new(-2,2,1),
-        Point3::new(0,0,-1),
-        Vec3::new(0,1,0),
-        10.0,
-        3.4
-    );
-
-    cam.render(world);
-
-    */
-}
\ No newline at end of file
+    world
+}
@@ -1,11 +1,41 @@
-use rand::random;
+use std::f64::consts::PI;
 
-use crate::{hittable::HitRecord, ray::Ray, util::random_f64, Color, Vec3};
+use crate::{hittable::HitRecord, microfacet::{fresnel_conductor, fresnel_dielectric, Ggx}, ray::Ray, spectrum::RGB_WAVELENGTH, util::random, Color, Vec3};
 
+/// How light scatters from a surface.
 pub trait Material: Send + Sync {
+    /// The attenuation and scattered ray for `r_in` arriving at `rec`, or `None` if it's absorbed.
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)>;
+
+    /// For materials that can scatter light in any direction, the BSDF times the cosine term for light
+    /// scattering from `direction` into `r_in`, and the probability density of `scatter` choosing
+    /// `direction`. This lets light be sampled directly. Materials that scatter into a single direction
+    /// return `None`.
+    fn evaluate(&self, _r_in: &Ray, _rec: &HitRecord, _direction: Vec3) -> Option<(Color, f64)> {
+        None
+    }
+
+    /// The surface colour at `rec`, for the albedo pass. Materials without one are white.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1,1,1)
+    }
+
+    /// Whether `scatter` depends on the wavelength of `r_in` in a way its RGB attenuation can't
+    /// describe, such as picking a direction by wavelength. When rendering spectrally, a path only
+    /// carries the ray's own wavelength on from such a surface.
+    fn disperses(&self) -> bool {
+        false
+    }
+
+    /// The complex index of refraction `eta + ik` of what's beneath the surface, per channel, for
+    /// layers such as [`ThinFilm`](crate::ThinFilm) that change how it reflects. `None` if it doesn't
+    /// have a single one.
+    fn refractive_index(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Color)> {
+        None
+    }
 }
 
+/// An ideal diffuse surface.
 pub struct Lambertian {
     albedo: Color,
 }
@@ -19,7 +49,7 @@ impl Lambertian {
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
         let mut scatter_direction = rec.normal + Vec3::random_unit_vector();
 
         if scatter_direction.near_zero() {
@@ -31,8 +61,18 @@ impl Material for Lambertian {
 
         Some((atten, scatter))
     }
+
+    fn evaluate(&self, _r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let cosine = rec.normal.dot(&direction.unit_vector()).max(0.0);
+        Some((self.albedo * cosine / PI, cosine / PI))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
 }
 
+/// A reflective surface, blurred by `fuzz` between 0 (a perfect mirror) and 1.
 pub struct Metal {
     albedo: Color,
     fuzz: f64,
@@ -57,19 +97,226 @@ impl Material for Metal {
 
         Some((atten, scatter))
     }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+
+    fn refractive_index(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Color)> {
+        // the metal whose reflectance at normal incidence is the albedo, from "Artist Friendly Metallic
+        // Fresnel" (Gulbrandsen 2014) with the edge tint left white
+        let channel = |r: f64| {
+            let r = r.clamp(0.0, 0.999);
+            let eta = (1.0 - r) / (1.0 + r);
+            let k = ((r * (eta + 1.0).powi(2) - (eta - 1.0).powi(2)) / (1.0 - r)).max(0.0).sqrt();
+            (eta, k)
+        };
+
+        let [(er, kr), (eg, kg), (eb, kb)] = [self.albedo.x, self.albedo.y, self.albedo.z].map(channel);
+        Some((Color::new(er, eg, eb), Color::new(kr, kg, kb)))
+    }
 }
 
-pub struct Dielectric {
+/// A rough metal, with microfacets following the GGX distribution and a complex index of refraction
+/// `eta + ik` per channel giving its colour. `roughness` runs from 0, a mirror, to 1.
+///
+/// Unlike [`Metal`], light never scatters into the surface, and only the light that would bounce
+/// between microfacets more than once is lost.
+pub struct Conductor {
+    eta: Color,
+    k: Color,
+    ggx: Ggx,
+}
+
+impl Conductor {
+    pub fn new(eta: Color, k: Color, roughness: f64) -> Self {
+        Self {
+            eta,
+            k,
+            ggx: Ggx::new(roughness),
+        }
+    }
+
+    // the optical constants below are for red, green and blue wavelengths of about 650, 550 and 450 nm
+
+    pub fn gold(roughness: f64) -> Self {
+        Self::new(Color::new(0.143, 0.374, 1.442), Color::new(3.983, 2.385, 1.603), roughness)
+    }
+
+    pub fn copper(roughness: f64) -> Self {
+        Self::new(Color::new(0.200, 0.924, 1.102), Color::new(3.912, 2.452, 2.142), roughness)
+    }
+
+    pub fn aluminium(roughness: f64) -> Self {
+        Self::new(Color::new(1.657, 0.880, 0.521), Color::new(9.224, 6.270, 4.837), roughness)
+    }
+
+    pub fn silver(roughness: f64) -> Self {
+        Self::new(Color::new(0.155, 0.117, 0.138), Color::new(4.828, 3.122, 2.147), roughness)
+    }
+}
+
+impl Material for Conductor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let (n, wo) = (rec.normal, -r_in.dir.unit_vector());
+        let m = self.ggx.sample_visible_normal(n, wo);
+        let wi = Vec3::reflect(&-wo, &m);
+        if n.dot(&wi) <= 0.0 {
+            return None;
+        }
+
+        let atten = fresnel_conductor(wo.dot(&m), self.eta, self.k) * self.ggx.g2(n, wo, wi) / self.ggx.g1(n, wo);
+        Some((atten, Ray::new(rec.p, wi)))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let (n, wo, wi) = (rec.normal, -r_in.dir.unit_vector(), direction.unit_vector());
+        let Some((f_cos, pdf, m)) = self.ggx.reflection(n, wo, wi) else {return Some((Color::new(0,0,0), 0.0))};
+
+        Some((fresnel_conductor(wo.dot(&m), self.eta, self.k) * f_cos, pdf))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        fresnel_conductor(1.0, self.eta, self.k)
+    }
+
+    fn refractive_index(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Color)> {
+        Some((self.eta, self.k))
+    }
+}
+
+/// A clear refractive material such as frosted glass, with index of refraction `ir` and microfacets
+/// following the GGX distribution. `roughness` runs from 0, which looks like [`Dielectric`], to 1.
+pub struct RoughDielectric {
     ir: f64,
+    ggx: Ggx,
+}
+
+impl RoughDielectric {
+    pub fn new(ir: f64, roughness: f64) -> Self {
+        Self {
+            ir,
+            ggx: Ggx::new(roughness),
+        }
+    }
+}
+
+impl Material for RoughDielectric {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let (n, wo) = (rec.normal, -r_in.dir.unit_vector());
+        let eta = if rec.front_face {self.ir} else {1.0 / self.ir};
+        let m = self.ggx.sample_visible_normal(n, wo);
+
+        // reflect or refract through the chosen microfacet in proportion to the Fresnel term, which
+        // then cancels out of the attenuation
+        let wi = if fresnel_dielectric(wo.dot(&m), eta) > random::<f64>() {
+            let wi = Vec3::reflect(&-wo, &m);
+            if n.dot(&wi) <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            let wi = Vec3::refract(&-wo, &m, 1.0 / eta);
+            if n.dot(&wi) >= 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        let atten = Color::new(1,1,1) * (self.ggx.g2(n, wo, wi) / self.ggx.g1(n, wo));
+        Some((atten, Ray::new(rec.p, wi)))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let (n, wo, wi) = (rec.normal, -r_in.dir.unit_vector(), direction.unit_vector());
+        let eta = if rec.front_face {self.ir} else {1.0 / self.ir};
+
+        if let Some((f_cos, pdf, m)) = self.ggx.reflection(n, wo, wi) {
+            let fresnel = fresnel_dielectric(wo.dot(&m), eta);
+            return Some((Color::new(1,1,1) * (fresnel * f_cos), fresnel * pdf));
+        }
+        if let Some((f_cos, pdf, m)) = self.ggx.transmission(n, wo, wi, eta) {
+            let transmitted = 1.0 - fresnel_dielectric(wo.dot(&m), eta);
+            return Some((Color::new(1,1,1) * (transmitted * f_cos), transmitted * pdf));
+        }
+        Some((Color::new(0,0,0), 0.0))
+    }
+
+    fn refractive_index(&self, _r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Color)> {
+        Some((Color::new(self.ir, self.ir, self.ir), Color::new(0,0,0)))
+    }
+}
+
+/// A clear refractive material such as glass or water, with index of refraction `ir`.
+///
+/// The index of refraction can also vary with wavelength, splitting white light into colours when
+/// rendering spectrally. In RGB the index at 587.6 nm is used.
+///
+/// Coloured glass absorbs light as it travels through it, so thick glass is darker than thin glass
+/// (the Beer–Lambert law). The glass needs to be a closed surface for this to work, since the distance
+/// travelled is measured from where a ray entered to where it leaves.
+pub struct Dielectric {
+    ior: Ior,
+    tint: Color,
+    absorption: Color,
+}
+
+// an index of refraction, possibly depending on wavelength
+enum Ior {
+    Constant(f64),
+    Cauchy {a: f64, b: f64},
+    Sellmeier {b: [f64; 3], c: [f64; 3]},
+}
+
+impl Ior {
+    fn at(&self, wavelength: Option<f64>) -> f64 {
+        // both dispersion formulas take the wavelength in micrometres
+        let l = wavelength.unwrap_or(RGB_WAVELENGTH) / 1000.0;
+        let l2 = l * l;
+
+        match self {
+            Ior::Constant(ir) => *ir,
+            Ior::Cauchy {a, b} => a + b / l2,
+            Ior::Sellmeier {b, c} => (1.0 + (0..3).map(|i| b[i] * l2 / (l2 - c[i])).sum::<f64>()).sqrt(),
+        }
+    }
 }
 
 impl Dielectric {
     pub fn new(ir: f64) -> Self {
+        Self::colored(ir, Color::new(1,1,1), Color::new(0,0,0))
+    }
+
+    /// Glass that lets through a `tint` of the light each time it passes through its surface, and
+    /// absorbs `absorption` of what's left per unit of distance travelled inside it, per channel.
+    pub fn colored(ir: f64, tint: Color, absorption: Color) -> Self {
         Self {
-            ir,
+            ior: Ior::Constant(ir),
+            tint,
+            absorption,
+        }
+    }
+
+    /// Glass whose index of refraction follows Cauchy's equation `a + b / λ²`, with λ in micrometres.
+    /// Borosilicate crown glass (BK7) has `a = 1.5046` and `b = 0.00420`.
+    pub fn cauchy(a: f64, b: f64) -> Self {
+        Self {
+            ior: Ior::Cauchy {a, b},
+            ..Self::new(1.0)
         }
     }
 
+    /// Glass whose index of refraction follows the Sellmeier equation
+    /// `n² = 1 + Σ b[i] λ² / (λ² - c[i])`, with λ in micrometres, as given in glass catalogues. BK7 has
+    /// `b = [1.03961212, 0.231792344, 1.01046945]` and `c = [0.00600069867, 0.0200179144, 103.560653]`.
+    pub fn sellmeier(b: [f64; 3], c: [f64; 3]) -> Self {
+        Self {
+            ior: Ior::Sellmeier {b, c},
+            ..Self::new(1.0)
+        }
+    }
+
+    #[allow(clippy::needless_return)]
     fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
         let mut r0 = (1.0-ref_idx) / (1.0+ref_idx);
         r0 = r0 * r0;
@@ -78,9 +325,18 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
+    #[allow(clippy::needless_return)]
     fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
-        let atten = Color::new(1,1,1);
-        let refraction_ratio = if rec.front_face {1.0 / self.ir} else {self.ir};
+        // leaving the glass, so the ray has just travelled through it
+        let mut atten = Color::new(1,1,1);
+        if !rec.front_face {
+            let distance = rec.t * r_in.dir.length();
+            let a = self.absorption;
+            atten = Color::new((-a.x * distance).exp(), (-a.y * distance).exp(), (-a.z * distance).exp());
+        }
+
+        let ir = self.ior.at(r_in.wavelength);
+        let refraction_ratio = if rec.front_face {1.0 / ir} else {ir};
 
         let unit_direction = Vec3::unit_vector(r_in.dir);
         let cos_theta = (-unit_direction).dot(&rec.normal).min(1.0);
@@ -91,11 +347,24 @@ impl Material for Dielectric {
         let direction = if cannot_refract || Self::reflectance(cos_theta, refraction_ratio) > random::<f64>() {
             Vec3::reflect(&unit_direction, &rec.normal)
         } else {
+            atten = atten * self.tint;
             Vec3::refract(&unit_direction, &rec.normal, refraction_ratio)
         };
 
         let scatter = Ray::new(rec.p, direction);
         return Some((atten, scatter));
     }
-}
 
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.tint
+    }
+
+    fn disperses(&self) -> bool {
+        !matches!(self.ior, Ior::Constant(_))
+    }
+
+    fn refractive_index(&self, r_in: &Ray, _rec: &HitRecord) -> Option<(Color, Color)> {
+        let ir = self.ior.at(r_in.wavelength);
+        Some((Color::new(ir, ir, ir), Color::new(0,0,0)))
+    }
+}
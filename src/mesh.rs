@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+// the most triangles left in one leaf of a mesh's hierarchy
+const LEAF_SIZE: usize = 4;
+
+/// The vertices and triangles of a mesh, as loaded from a file or built by hand, before it's made
+/// into a [`TriangleMesh`] to render.
+///
+/// `normals`, `colors` and `uvs` are per vertex, and either empty or as long as `positions`.
+/// Triangles and quads list their corners anticlockwise seen from outside. Quads are kept for
+/// [`Mesh::subdivide`], which smooths them differently, and are otherwise split into triangles.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub positions: Vec<Point3>,
+    pub normals: Vec<Vec3>,
+    pub colors: Vec<Color>,
+    pub uvs: Vec<(f64, f64)>,
+    pub triangles: Vec<[usize; 3]>,
+    pub quads: Vec<[usize; 4]>,
+}
+
+/// How much each triangle around a vertex counts towards its normal in [`Mesh::compute_normals`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weighting {
+    /// By the triangle's area, so large triangles count for more than slivers.
+    Area,
+    /// By the angle of the triangle's corner at the vertex, which doesn't change as triangles are split
+    /// up, so it's steadier on unevenly tessellated meshes.
+    Angle,
+}
+
+impl Mesh {
+    /// Splits the quads into triangles, across their first and third corners.
+    pub fn triangulate(&mut self) {
+        for [a, b, c, d] in self.quads.drain(..) {
+            self.triangles.extend([[a, b, c], [a, c, d]]);
+        }
+    }
+
+    /// Replaces the vertex normals with ones averaged from the normals of the triangles around each
+    /// vertex, weighted by `weighting`.
+    ///
+    /// Triangles meeting at more than `crease_angle` degrees are kept apart, leaving a sharp edge
+    /// between them, so vertices along creases are split into one per side. Triangles sharing a place
+    /// are smoothed together even where they have separate vertices, such as along texture seams.
+    /// Vertices no triangle uses are dropped, and quads are split into triangles first.
+    pub fn compute_normals(&mut self, weighting: Weighting, crease_angle: f64) {
+        self.triangulate();
+        let min_cos = crease_angle.to_radians().cos();
+        let key = |p: Point3| [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()];
+
+        // every triangle's unit normal, or `None` if it has no area, and each corner's weight
+        let faces: Vec<(Option<Vec3>, [f64; 3])> = self.triangles.iter().map(|&corners| {
+            let [a, b, c] = corners.map(|v| self.positions[v]);
+            let cross = (b - a).cross(&(c - a));
+            if cross.length() == 0.0 {
+                return (None, [0.0; 3]);
+            }
+
+            let weights = match weighting {
+                Weighting::Area => [cross.length(); 3],
+                Weighting::Angle => [(b - a, c - a), (c - b, a - b), (a - c, b - c)].map(|(e1, e2)| {
+                    e1.unit_vector().dot(&e2.unit_vector()).clamp(-1.0, 1.0).acos()
+                }),
+            };
+            (Some(cross.unit_vector()), weights)
+        }).collect();
+
+        // the corners of triangles at each place
+        let mut around: HashMap<_, Vec<(usize, usize)>> = HashMap::new();
+        for (t, corners) in self.triangles.iter().enumerate() {
+            for (k, &v) in corners.iter().enumerate() {
+                around.entry(key(self.positions[v])).or_default().push((t, k));
+            }
+        }
+
+        let mut split = Mesh::default();
+        let mut vertices = HashMap::new();
+        let mut triangles = Vec::with_capacity(self.triangles.len());
+        for (t, corners) in self.triangles.iter().enumerate() {
+            let own = faces[t].0;
+            let triangle = [0, 1, 2].map(|k| {
+                let v = corners[k];
+                let mut normal = Vec3::new(0, 0, 0);
+                for &(other, j) in &around[&key(self.positions[v])] {
+                    let (Some(n), weights) = faces[other] else { continue };
+                    // triangles with no area of their own take the normal of everything around them
+                    if own.is_none_or(|own| own.dot(&n) >= min_cos) {
+                        normal += weights[j] * n;
+                    }
+                }
+                if normal.length() > 0.0 {
+                    normal = normal.unit_vector();
+                }
+
+                let normal_key = [normal.x.to_bits(), normal.y.to_bits(), normal.z.to_bits()];
+                *vertices.entry((v, normal_key)).or_insert_with(|| {
+                    split.positions.push(self.positions[v]);
+                    split.normals.push(normal);
+                    if !self.colors.is_empty() {
+                        split.colors.push(self.colors[v]);
+                    }
+                    if !self.uvs.is_empty() {
+                        split.uvs.push(self.uvs[v]);
+                    }
+                    split.positions.len() - 1
+                })
+            });
+            triangles.push(triangle);
+        }
+
+        split.triangles = triangles;
+        *self = split;
+    }
+}
+
+/// A mesh of triangles with one material, kept in a bounding volume hierarchy so that rays only test
+/// the triangles near them.
+///
+/// Where the mesh has vertex normals they're interpolated into the shading normal, where it has
+/// texture coordinates they're interpolated into `u` and `v`, and where it has vertex colours they're
+/// interpolated into the hit's `color`, for [`VertexColor`](crate::VertexColor) to use. Otherwise `u`
+/// and `v` are how far across the triangle from its first corner towards its second and third. The
+/// hit's `barycentric` is how much of each corner the point is, whatever the mesh has.
+pub struct TriangleMesh {
+    mesh: Mesh,
+    nodes: Vec<Node>,
+    // the mesh's triangles in the order the hierarchy's leaves refer to them
+    order: Vec<usize>,
+    mat: Arc<dyn Material>,
+}
+
+// a box in the hierarchy, holding either triangles `order[start..start + count]`, or two boxes, the
+// first straight after it and the second at `second`
+struct Node {
+    bbox: Aabb,
+    start: usize,
+    count: usize,
+    second: usize,
+}
+
+impl TriangleMesh {
+    /// Panics if a triangle or quad refers to a vertex that doesn't exist, or `mesh` has some but not
+    /// all of a vertex attribute.
+    pub fn new(mut mesh: Mesh, mat: Arc<dyn Material>) -> Self {
+        mesh.triangulate();
+        let vertices = mesh.positions.len();
+        assert!(mesh.triangles.iter().flatten().all(|&i| i < vertices), "triangle refers to a missing vertex");
+        assert!(mesh.normals.is_empty() || mesh.normals.len() == vertices);
+        assert!(mesh.colors.is_empty() || mesh.colors.len() == vertices);
+        assert!(mesh.uvs.is_empty() || mesh.uvs.len() == vertices);
+
+        let mut mesh_box = None;
+        let mut triangles: Vec<(usize, Aabb, Point3)> = mesh.triangles.iter().enumerate().map(|(i, &[a, b, c])| {
+            let (a, b, c) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+            let bbox = Aabb::new(a, a).union(&Aabb::new(b, b)).union(&Aabb::new(c, c));
+            mesh_box = Some(mesh_box.map_or(bbox, |m: Aabb| m.union(&bbox)));
+            (i, bbox, (a + b + c) / 3.0)
+        }).collect();
+
+        // triangles lying flat across an axis have boxes with no thickness, which rays can't hit, so
+        // every box is padded a little for the size of the mesh
+        let pad = mesh_box.map_or(0.0, |m| 1e-9 * (m.max - m.min).length()).max(1e-12);
+        let pad = Vec3::new(pad, pad, pad);
+        for (_, bbox, _) in &mut triangles {
+            *bbox = Aabb::new(bbox.min - pad, bbox.max + pad);
+        }
+
+        let mut nodes = Vec::new();
+        if !triangles.is_empty() {
+            build(&mut nodes, &mut triangles, 0);
+        }
+
+        Self {
+            order: triangles.into_iter().map(|(i, _, _)| i).collect(),
+            mesh,
+            nodes,
+            mat,
+        }
+    }
+
+    /// The mesh being rendered.
+    pub fn mesh(&self) -> &Mesh {
+        &self.mesh
+    }
+
+    // where `r` crosses triangle `i` within `ray_t`, as `t` and how far towards its second and third
+    // corners, by the Möller-Trumbore method
+    fn intersect(&self, i: usize, r: &Ray, ray_t: Interval) -> Option<(f64, f64, f64)> {
+        let [a, b, c] = self.mesh.triangles[i].map(|v| self.mesh.positions[v]);
+        let (e1, e2) = (b - a, c - a);
+
+        let p = r.dir.cross(&e2);
+        let det = e1.dot(&p);
+        if det.abs() < 1e-300 {
+            return None;
+        }
+
+        let inv = 1.0 / det;
+        let s = r.orig - a;
+        let b1 = s.dot(&p) * inv;
+        if !(0.0..=1.0).contains(&b1) {
+            return None;
+        }
+
+        let q = s.cross(&e1);
+        let b2 = r.dir.dot(&q) * inv;
+        if b2 < 0.0 || b1 + b2 > 1.0 {
+            return None;
+        }
+
+        let t = e2.dot(&q) * inv;
+        if !ray_t.surrounds(t) {
+            return None;
+        }
+        Some((t, b1, b2))
+    }
+
+    fn record(&self, r: &Ray, i: usize, t: f64, b1: f64, b2: f64) -> HitRecord {
+        let mesh = &self.mesh;
+        let corners = mesh.triangles[i];
+        let [a, b, c] = corners.map(|v| mesh.positions[v]);
+        let (e1, e2) = (b - a, c - a);
+        let b0 = 1.0 - b1 - b2;
+        let interpolate = |x: Vec3, y: Vec3, z: Vec3| b0 * x + b1 * y + b2 * z;
+
+        let mut outward_normal = e1.cross(&e2).unit_vector();
+        let shading = if mesh.normals.is_empty() {
+            None
+        } else {
+            let [na, nb, nc] = corners.map(|v| mesh.normals[v]);
+            let n = interpolate(na, nb, nc);
+            if n.near_zero() {None} else {Some(n.unit_vector())}
+        };
+        // vertex normals are more often right about which side is outside than the order of the corners
+        if shading.is_some_and(|n| n.dot(&outward_normal) < 0.0) {
+            outward_normal = -outward_normal;
+        }
+
+        let (uv, (dpdu, dpdv)) = if mesh.uvs.is_empty() {
+            ((b1, b2), (e1, e2))
+        } else {
+            let [ta, tb, tc] = corners.map(|v| mesh.uvs[v]);
+            let uv = (b0 * ta.0 + b1 * tb.0 + b2 * tc.0, b0 * ta.1 + b1 * tb.1 + b2 * tc.1);
+            (uv, uv_tangents(e1, e2, (tb.0 - ta.0, tb.1 - ta.1), (tc.0 - ta.0, tc.1 - ta.1), outward_normal))
+        };
+
+        let mut rec = HitRecord {
+            p: r.at(t),
+            normal: outward_normal,
+            geometric_normal: outward_normal,
+            dpdu,
+            dpdv,
+            mat: Arc::clone(&self.mat),
+            t,
+            u: uv.0,
+            v: uv.1,
+            barycentric: Some([b0, b1, b2]),
+            color: (!mesh.colors.is_empty()).then(|| {
+                let [ca, cb, cc] = corners.map(|v| mesh.colors[v]);
+                interpolate(ca, cb, cc)
+            }),
+            front_face: false,
+            object_id: 0,
+            material_id: 0,
+        };
+
+        rec.set_face_normal(r, &outward_normal);
+        if let Some(n) = shading {
+            rec.normal = if rec.front_face {n} else {-n};
+        }
+        rec
+    }
+}
+
+impl Hittable for TriangleMesh {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut closest = None;
+        let mut max = ray_t.max;
+        let mut stack = vec![0];
+        while let Some(n) = stack.pop() {
+            let node = &self.nodes[n];
+            if node.bbox.hit(r, Interval::new(ray_t.min, max)).is_none() {
+                continue;
+            }
+
+            if node.count == 0 {
+                stack.extend([node.second, n + 1]);
+                continue;
+            }
+            for &i in &self.order[node.start..node.start + node.count] {
+                if let Some((t, b1, b2)) = self.intersect(i, r, Interval::new(ray_t.min, max)) {
+                    max = t;
+                    closest = Some((i, t, b1, b2));
+                }
+            }
+        }
+
+        let (i, t, b1, b2) = closest?;
+        Some(self.record(r, i, t, b1, b2))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| node.bbox)
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+// adds the hierarchy over `triangles` to `nodes`, splitting them in half across the widest spread of
+// their centres until few enough are left. `start` is where `triangles` begins in the whole mesh
+fn build(nodes: &mut Vec<Node>, triangles: &mut [(usize, Aabb, Point3)], start: usize) {
+    let bbox = triangles[1..].iter().fold(triangles[0].1, |b, t| b.union(&t.1));
+    let centres = triangles[1..].iter().fold(Aabb::new(triangles[0].2, triangles[0].2), |b, t| b.union(&Aabb::new(t.2, t.2)));
+    let spread = centres.max - centres.min;
+    let index = nodes.len();
+    nodes.push(Node {
+        bbox,
+        start,
+        count: triangles.len(),
+        second: 0,
+    });
+
+    if triangles.len() <= LEAF_SIZE || spread.near_zero() {
+        return;
+    }
+
+    let axis = if spread.x > spread.y && spread.x > spread.z {0} else if spread.y > spread.z {1} else {2};
+    let along = |c: &Point3| [c.x, c.y, c.z][axis];
+    let half = triangles.len() / 2;
+    triangles.select_nth_unstable_by(half, |a, b| along(&a.2).total_cmp(&along(&b.2)));
+
+    let (first, second) = triangles.split_at_mut(half);
+    build(nodes, first, start);
+    nodes[index].count = 0;
+    nodes[index].second = nodes.len();
+    build(nodes, second, start + half);
+}
+
+// how a point on a triangle with edges `e1` and `e2` moves with its texture coordinates, given how they
+// change along the edges, falling back to any tangents across `normal` if they don't change
+fn uv_tangents(e1: Vec3, e2: Vec3, duv1: (f64, f64), duv2: (f64, f64), normal: Vec3) -> (Vec3, Vec3) {
+    let det = duv1.0 * duv2.1 - duv1.1 * duv2.0;
+    if det.abs() < 1e-12 {
+        return normal.orthonormal_basis();
+    }
+
+    let inv = 1.0 / det;
+    ((duv2.1 * e1 - duv1.1 * e2) * inv, (duv1.0 * e2 - duv2.0 * e1) * inv)
+}
+
+pub(crate) fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
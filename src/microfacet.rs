@@ -0,0 +1,172 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::util::random;
+use crate::vec3::Vec3;
+
+/// The GGX (Trowbridge–Reitz) distribution of microfacet normals, shared by the rough materials.
+/// Directions are world space unit vectors, on the same side of the surface normal `n` as the
+/// outgoing direction.
+pub(crate) struct Ggx {
+    alpha: f64,
+}
+
+impl Ggx {
+    /// `roughness` between 0 (smooth) and 1, squared into the distribution's width so that it looks
+    /// roughly linear.
+    pub(crate) fn new(roughness: f64) -> Self {
+        // a perfectly smooth distribution is a delta, which the formulas below can't represent
+        Self {
+            alpha: (roughness * roughness).clamp(1e-4, 1.0),
+        }
+    }
+
+    /// The density of microfacets with normal `m`, per unit solid angle and projected area.
+    pub(crate) fn d(&self, n: Vec3, m: Vec3) -> f64 {
+        let cos = n.dot(&m);
+        if cos <= 0.0 {
+            return 0.0;
+        }
+
+        let a2 = self.alpha * self.alpha;
+        let t = cos * cos * (a2 - 1.0) + 1.0;
+        a2 / (PI * t * t)
+    }
+
+    fn lambda(&self, n: Vec3, v: Vec3) -> f64 {
+        let cos2 = n.dot(&v).powi(2);
+        if cos2 <= 0.0 {
+            return f64::INFINITY;
+        }
+
+        let tan2 = (1.0 - cos2).max(0.0) / cos2;
+        ((1.0 + self.alpha * self.alpha * tan2).sqrt() - 1.0) / 2.0
+    }
+
+    /// The fraction of microfacets facing `v` that aren't hidden from it by others.
+    pub(crate) fn g1(&self, n: Vec3, v: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(n, v))
+    }
+
+    /// The fraction of microfacets visible from both `wo` and `wi`.
+    pub(crate) fn g2(&self, n: Vec3, wo: Vec3, wi: Vec3) -> f64 {
+        1.0 / (1.0 + self.lambda(n, wo) + self.lambda(n, wi))
+    }
+
+    /// Samples a microfacet normal in proportion to how much of it `wo` sees ("Sampling the GGX
+    /// Distribution of Visible Normals", Heitz 2018).
+    pub(crate) fn sample_visible_normal(&self, n: Vec3, wo: Vec3) -> Vec3 {
+        let (t, b) = n.orthonormal_basis();
+        let alpha = self.alpha;
+
+        // stretch the view direction into the frame where the distribution is a hemisphere
+        let v = Vec3::new(alpha * wo.dot(&t), alpha * wo.dot(&b), wo.dot(&n)).unit_vector();
+        let len2 = v.x * v.x + v.y * v.y;
+        let t1 = if len2 > 0.0 {Vec3::new(-v.y, v.x, 0.0) / len2.sqrt()} else {Vec3::new(1,0,0)};
+        let t2 = v.cross(&t1);
+
+        // a point on the projected hemisphere, squashed where it's hidden behind the view direction
+        let r = random::<f64>().sqrt();
+        let phi = 2.0 * PI * random::<f64>();
+        let p1 = r * phi.cos();
+        let s = 0.5 * (1.0 + v.z);
+        let p2 = (1.0 - s) * (1.0 - p1 * p1).sqrt() + s * r * phi.sin();
+        let h = p1 * t1 + p2 * t2 + (1.0 - p1 * p1 - p2 * p2).max(0.0).sqrt() * v;
+
+        // and back out of the stretched frame
+        let m = Vec3::new(alpha * h.x, alpha * h.y, h.z.max(0.0)).unit_vector();
+        m.x * t + m.y * b + m.z * n
+    }
+
+    /// The probability density of `sample_visible_normal` choosing `m`.
+    pub(crate) fn visible_normal_pdf(&self, n: Vec3, wo: Vec3, m: Vec3) -> f64 {
+        let cos_o = n.dot(&wo);
+        if cos_o <= 0.0 {
+            return 0.0;
+        }
+        self.g1(n, wo) * wo.dot(&m).max(0.0) * self.d(n, m) / cos_o
+    }
+
+    /// For light reflecting off the microfacets from `wi` into `wo`, the BSDF times the cosine term
+    /// without the Fresnel factor, the probability density of finding `wi` by reflecting `wo` off
+    /// `sample_visible_normal`, and the microfacet normal. `None` if `wi` is below the surface.
+    pub(crate) fn reflection(&self, n: Vec3, wo: Vec3, wi: Vec3) -> Option<(f64, f64, Vec3)> {
+        let cos_o = n.dot(&wo);
+        if cos_o <= 0.0 || n.dot(&wi) <= 0.0 {
+            return None;
+        }
+
+        let m = (wo + wi).unit_vector();
+        let f_cos = self.d(n, m) * self.g2(n, wo, wi) / (4.0 * cos_o);
+        let pdf = self.visible_normal_pdf(n, wo, m) / (4.0 * wo.dot(&m));
+        Some((f_cos, pdf, m))
+    }
+
+    /// The same for light refracting through the microfacets into a medium `eta` times as dense, from
+    /// `wi` below the surface into `wo` above it. `None` if no microfacet refracts one into the other.
+    pub(crate) fn transmission(&self, n: Vec3, wo: Vec3, wi: Vec3, eta: f64) -> Option<(f64, f64, Vec3)> {
+        let cos_o = n.dot(&wo);
+        if cos_o <= 0.0 || n.dot(&wi) >= 0.0 {
+            return None;
+        }
+
+        // the microfacet that refracts `wo` into `wi`, facing out of the surface
+        let mut m = (wo + eta * wi).unit_vector();
+        if m.dot(&n) < 0.0 {
+            m = -m;
+        }
+        let (o_m, i_m) = (wo.dot(&m), wi.dot(&m));
+        if o_m <= 0.0 || i_m >= 0.0 {
+            return None;
+        }
+
+        // the change of variables from the microfacet normal to the refracted direction
+        let denom = o_m + eta * i_m;
+        let jacobian = eta * eta * -i_m / (denom * denom);
+
+        let f_cos = self.d(n, m) * self.g2(n, wo, wi) * o_m * jacobian / cos_o;
+        let pdf = self.visible_normal_pdf(n, wo, m) * jacobian;
+        Some((f_cos, pdf, m))
+    }
+}
+
+/// The fraction of light reflected by a conductor with complex index of refraction `eta + ik`, for
+/// light arriving at `cos_i` to the surface, per channel.
+pub(crate) fn fresnel_conductor(cos_i: f64, eta: Color, k: Color) -> Color {
+    let channel = |eta: f64, k: f64| {
+        let cos2 = cos_i * cos_i;
+        let sin2 = 1.0 - cos2;
+        let (eta2, k2) = (eta * eta, k * k);
+
+        let t0 = eta2 - k2 - sin2;
+        let a2_plus_b2 = (t0 * t0 + 4.0 * eta2 * k2).sqrt();
+        let t1 = a2_plus_b2 + cos2;
+        let a = (0.5 * (a2_plus_b2 + t0)).max(0.0).sqrt();
+        let t2 = 2.0 * cos_i * a;
+        let rs = (t1 - t2) / (t1 + t2);
+
+        let t3 = cos2 * a2_plus_b2 + sin2 * sin2;
+        let t4 = t2 * sin2;
+        let rp = rs * (t3 - t4) / (t3 + t4);
+
+        0.5 * (rs + rp)
+    };
+
+    Color::new(channel(eta.x, k.x), channel(eta.y, k.y), channel(eta.z, k.z))
+}
+
+/// The fraction of light reflected at the boundary into a dielectric `eta` times as dense, for light
+/// arriving at `cos_i` to the surface. Unlike Schlick's approximation this is exact, and is 1 past
+/// the critical angle.
+pub(crate) fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+    let cos_i = cos_i.clamp(0.0, 1.0);
+    let sin2_t = (1.0 - cos_i * cos_i) / (eta * eta);
+    if sin2_t >= 1.0 {
+        return 1.0;
+    }
+
+    let cos_t = (1.0 - sin2_t).sqrt();
+    let rs = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+    let rp = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+    0.5 * (rs * rs + rp * rp)
+}
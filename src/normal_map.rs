@@ -0,0 +1,187 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::vec3::Vec3;
+
+// how far a tilted normal may lean away from the viewer: just short of edge on
+const MIN_COS: f64 = 0.01;
+
+// the step in texture coordinates for finding the slope of a bump texture
+const BUMP_DELTA: f64 = 1e-3;
+
+/// Tilts the shading normal of `base` by a tangent space normal map, as made by baking or texturing
+/// tools: red and green run along increasing `u` and `v` and blue out of the surface, with 0.5 for
+/// straight along the surface normal. Load the image with
+/// [`ImageTexture::load_linear`](crate::ImageTexture::load_linear), as it holds directions rather
+/// than colour.
+pub struct NormalMap {
+    base: Arc<dyn Material>,
+    texture: Arc<dyn Texture>,
+}
+
+impl NormalMap {
+    pub fn new(base: Arc<dyn Material>, texture: Arc<dyn Texture>) -> Self {
+        Self {
+            base,
+            texture,
+        }
+    }
+
+    // the mapped normal, on the outward side of the surface
+    fn outward_normal(&self, rec: &HitRecord) -> Vec3 {
+        let n = outward(rec, rec.normal);
+        let (t, b) = tangent_frame(rec, n);
+
+        let c = 2.0 * self.texture.value(rec.u, rec.v, rec.p) - Color::new(1,1,1);
+        let mapped = c.x * t + c.y * b + c.z * n;
+        if mapped.near_zero() {n} else {mapped.unit_vector()}
+    }
+}
+
+/// Tilts the shading normal of `base` as if the surface were raised along it by `height`, scaled by
+/// `scale` into world units, without moving the surface itself.
+pub struct BumpMap {
+    base: Arc<dyn Material>,
+    height: Arc<dyn Texture>,
+    scale: f64,
+}
+
+impl BumpMap {
+    pub fn new(base: Arc<dyn Material>, height: Arc<dyn Texture>, scale: f64) -> Self {
+        Self {
+            base,
+            height,
+            scale,
+        }
+    }
+
+    fn outward_normal(&self, rec: &HitRecord) -> Vec3 {
+        let n = outward(rec, rec.normal);
+        if rec.dpdu.near_zero() || rec.dpdv.near_zero() {
+            return n;
+        }
+
+        // the slopes of the raised surface, from how far the height changes a small step along u and v
+        let h = |du: f64, dv: f64| {
+            let p = rec.p + du * rec.dpdu + dv * rec.dpdv;
+            self.scale * self.height.scalar(rec.u + du, rec.v + dv, p)
+        };
+        let h0 = h(0.0, 0.0);
+        let dpdu = rec.dpdu + (h(BUMP_DELTA, 0.0) - h0) / BUMP_DELTA * n;
+        let dpdv = rec.dpdv + (h(0.0, BUMP_DELTA) - h0) / BUMP_DELTA * n;
+
+        let bumped = dpdu.cross(&dpdv);
+        if bumped.near_zero() {
+            return n;
+        }
+        let bumped = bumped.unit_vector();
+        if bumped.dot(&n) < 0.0 {-bumped} else {bumped}
+    }
+}
+
+impl Material for NormalMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        scatter_shaded(&*self.base, r_in, rec, self.outward_normal(rec))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        evaluate_shaded(&*self.base, r_in, rec, self.outward_normal(rec), direction)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn disperses(&self) -> bool {
+        self.base.disperses()
+    }
+
+    fn refractive_index(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Color)> {
+        self.base.refractive_index(r_in, rec)
+    }
+}
+
+impl Material for BumpMap {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        scatter_shaded(&*self.base, r_in, rec, self.outward_normal(rec))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        evaluate_shaded(&*self.base, r_in, rec, self.outward_normal(rec), direction)
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn disperses(&self) -> bool {
+        self.base.disperses()
+    }
+
+    fn refractive_index(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Color)> {
+        self.base.refractive_index(r_in, rec)
+    }
+}
+
+// `base` scattering around the shading normal `normal`, given facing out of the surface. Directions
+// that would leak through the surface are absorbed
+fn scatter_shaded(base: &dyn Material, r_in: &Ray, rec: &HitRecord, normal: Vec3) -> Option<(Color, Ray)> {
+    let shaded = shade(rec, r_in, normal);
+    let (atten, scatter) = base.scatter(r_in, &shaded)?;
+    if leaks(&shaded, scatter.dir) {
+        return None;
+    }
+    Some((atten, scatter))
+}
+
+fn evaluate_shaded(base: &dyn Material, r_in: &Ray, rec: &HitRecord, normal: Vec3, direction: Vec3) -> Option<(Color, f64)> {
+    let shaded = shade(rec, r_in, normal);
+    let evaluated = base.evaluate(r_in, &shaded, direction)?;
+    if leaks(&shaded, direction) {
+        return Some((Color::new(0,0,0), 0.0));
+    }
+    Some(evaluated)
+}
+
+// `normal` facing out of the surface rather than against the ray
+fn outward(rec: &HitRecord, normal: Vec3) -> Vec3 {
+    if rec.front_face {normal} else {-normal}
+}
+
+// unit tangents along increasing u and v, at right angles to `n`
+fn tangent_frame(rec: &HitRecord, n: Vec3) -> (Vec3, Vec3) {
+    let t = rec.dpdu - n.dot(&rec.dpdu) * n;
+    if t.near_zero() {
+        return n.orthonormal_basis();
+    }
+
+    let t = t.unit_vector();
+    let b = n.cross(&t);
+    if b.dot(&rec.dpdv) < 0.0 {(t, -b)} else {(t, b)}
+}
+
+// `rec` with its shading normal replaced by `normal`, turned back towards the viewer if it would face
+// away, which would leave the surface black
+fn shade(rec: &HitRecord, r_in: &Ray, normal: Vec3) -> HitRecord {
+    // facing against the ray, like `rec.normal`
+    let wo = -r_in.dir.unit_vector();
+    let mut n = if rec.front_face {normal} else {-normal};
+    let cos = n.dot(&wo);
+    if cos < MIN_COS {
+        n = (n + (MIN_COS - cos) * wo).unit_vector();
+    }
+
+    let mut shaded = rec.clone();
+    shaded.normal = n;
+    shaded
+}
+
+// whether `direction` is on one side of the shading normal but the other of the true surface, which
+// would let light through an opaque surface or reflect it from inside a clear one
+fn leaks(rec: &HitRecord, direction: Vec3) -> bool {
+    direction.dot(&rec.normal) * direction.dot(&rec.geometric_normal) <= 0.0
+}
@@ -0,0 +1,310 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::color::Color;
+use crate::mesh::{invalid_data, Mesh};
+use crate::texture::srgb_to_linear;
+use crate::vec3::Vec3;
+
+impl Mesh {
+    /// Loads a PLY file, in either its text or binary form, as saved by scanning and modelling tools.
+    ///
+    /// Vertex normals (`nx`, `ny`, `nz`), colours (`red`, `green`, `blue`) and texture coordinates (`u`
+    /// and `v`, or `s` and `t`) are read where the file has them. Integer colours are taken to be sRGB
+    /// encoded and converted to linear colour. Faces with four corners are kept as quads, larger ones
+    /// are split into triangles, and any other elements are skipped.
+    pub fn load_ply(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if !bytes.starts_with(b"ply") {
+            return Err(invalid_data("not a PLY file"));
+        }
+
+        let end = bytes.windows(10)
+            .position(|w| w == b"end_header")
+            .ok_or_else(|| invalid_data("truncated PLY header"))?;
+        let header = String::from_utf8_lossy(&bytes[..end]);
+        // the data starts on the line after the header
+        let start = bytes[end..].iter()
+            .position(|&b| b == b'\n')
+            .map(|i| end + i + 1)
+            .ok_or_else(|| invalid_data("truncated PLY header"))?;
+
+        let (format, elements) = parse_header(&header)?;
+        let mut body = Body {
+            bytes: &bytes[start..],
+            pos: 0,
+            format,
+        };
+
+        let mut mesh = Mesh::default();
+        for element in &elements {
+            match element.name.as_str() {
+                "vertex" => read_vertices(&mut body, element, &mut mesh)?,
+                "face" => read_faces(&mut body, element, &mut mesh)?,
+                _ => {
+                    for _ in 0..element.count {
+                        for property in &element.properties {
+                            body.read_property(property)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        if mesh.triangles.iter().flatten().chain(mesh.quads.iter().flatten()).any(|&i| i >= mesh.positions.len()) {
+            return Err(invalid_data("PLY face refers to a vertex that doesn't exist"));
+        }
+        Ok(mesh)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::I8,
+            "uchar" | "uint8" => Scalar::U8,
+            "short" | "int16" => Scalar::I16,
+            "ushort" | "uint16" => Scalar::U16,
+            "int" | "int32" => Scalar::I32,
+            "uint" | "uint32" => Scalar::U32,
+            "float" | "float32" => Scalar::F32,
+            "double" | "float64" => Scalar::F64,
+            _ => return Err(invalid_data(format!("unknown PLY property type {name}"))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            Scalar::I8 | Scalar::U8 => 1,
+            Scalar::I16 | Scalar::U16 => 2,
+            Scalar::I32 | Scalar::U32 | Scalar::F32 => 4,
+            Scalar::F64 => 8,
+        }
+    }
+
+    // the value meaning full intensity for a colour of this type
+    fn full(self) -> Option<f64> {
+        match self {
+            Scalar::I8 => Some(i8::MAX as f64),
+            Scalar::U8 => Some(u8::MAX as f64),
+            Scalar::I16 => Some(i16::MAX as f64),
+            Scalar::U16 => Some(u16::MAX as f64),
+            Scalar::I32 => Some(i32::MAX as f64),
+            Scalar::U32 => Some(u32::MAX as f64),
+            Scalar::F32 | Scalar::F64 => None,
+        }
+    }
+}
+
+enum Kind {
+    Scalar(Scalar),
+    // the type of the length, then of the items
+    List(Scalar, Scalar),
+}
+
+struct Property {
+    name: String,
+    kind: Kind,
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+fn parse_header(header: &str) -> io::Result<(Format, Vec<Element>)> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+
+    for line in header.lines().skip(1) {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words[..] {
+            ["format", name, _] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::LittleEndian,
+                    "binary_big_endian" => Format::BigEndian,
+                    _ => return Err(invalid_data(format!("unknown PLY format {name}"))),
+                });
+            }
+            ["element", name, count] => {
+                elements.push(Element {
+                    name: name.to_string(),
+                    count: count.parse().map_err(|_| invalid_data(format!("bad PLY element count {count}")))?,
+                    properties: Vec::new(),
+                });
+            }
+            ["property", "list", length, item, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("PLY property before any element"))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: Kind::List(Scalar::parse(length)?, Scalar::parse(item)?),
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements.last_mut().ok_or_else(|| invalid_data("PLY property before any element"))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    kind: Kind::Scalar(Scalar::parse(ty)?),
+                });
+            }
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(invalid_data(format!("unrecognised PLY header line: {line}"))),
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid_data("PLY header has no format"))?;
+    Ok((format, elements))
+}
+
+// the data after the header, read one number at a time
+struct Body<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    format: Format,
+}
+
+impl Body<'_> {
+    fn read(&mut self, scalar: Scalar) -> io::Result<f64> {
+        let little = match self.format {
+            Format::Ascii => return self.read_text(),
+            Format::LittleEndian => true,
+            Format::BigEndian => false,
+        };
+
+        let bytes = self.bytes.get(self.pos..self.pos + scalar.size()).ok_or_else(|| invalid_data("truncated PLY data"))?;
+        self.pos += scalar.size();
+
+        // put into little endian order, so every size and order reads the same way
+        let mut v = [0; 8];
+        v[..bytes.len()].copy_from_slice(bytes);
+        if !little {
+            v[..bytes.len()].reverse();
+        }
+        Ok(match scalar {
+            Scalar::I8 => v[0] as i8 as f64,
+            Scalar::U8 => v[0] as f64,
+            Scalar::I16 => i16::from_le_bytes([v[0], v[1]]) as f64,
+            Scalar::U16 => u16::from_le_bytes([v[0], v[1]]) as f64,
+            Scalar::I32 => i32::from_le_bytes([v[0], v[1], v[2], v[3]]) as f64,
+            Scalar::U32 => u32::from_le_bytes([v[0], v[1], v[2], v[3]]) as f64,
+            Scalar::F32 => f32::from_le_bytes([v[0], v[1], v[2], v[3]]) as f64,
+            Scalar::F64 => f64::from_le_bytes(v),
+        })
+    }
+
+    fn read_text(&mut self) -> io::Result<f64> {
+        let rest = &self.bytes[self.pos..];
+        let start = rest.iter().position(|b| !b.is_ascii_whitespace()).ok_or_else(|| invalid_data("truncated PLY data"))?;
+        let len = rest[start..].iter().position(|b| b.is_ascii_whitespace()).unwrap_or(rest.len() - start);
+        self.pos += start + len;
+
+        let word = String::from_utf8_lossy(&rest[start..start + len]);
+        word.parse().map_err(|_| invalid_data(format!("bad number in PLY data: {word}")))
+    }
+
+    // the values of one property, one for a scalar or any number for a list
+    fn read_property(&mut self, property: &Property) -> io::Result<Vec<f64>> {
+        match property.kind {
+            Kind::Scalar(scalar) => Ok(vec![self.read(scalar)?]),
+            Kind::List(length, item) => {
+                let length = self.read(length)?;
+                if length < 0.0 {
+                    return Err(invalid_data("negative PLY list length"));
+                }
+                (0..length as usize).map(|_| self.read(item)).collect()
+            }
+        }
+    }
+}
+
+fn read_vertices(body: &mut Body, element: &Element, mesh: &mut Mesh) -> io::Result<()> {
+    let find = |names: &[&str]| element.properties.iter().position(|p| names.contains(&p.name.as_str()));
+    let all = |names: [&[&str]; 3]| -> Option<[usize; 3]> {Some([find(names[0])?, find(names[1])?, find(names[2])?])};
+
+    let position = all([&["x"], &["y"], &["z"]]).ok_or_else(|| invalid_data("PLY vertices have no position"))?;
+    let normal = all([&["nx"], &["ny"], &["nz"]]);
+    let color = all([&["red", "diffuse_red"], &["green", "diffuse_green"], &["blue", "diffuse_blue"]]);
+    let uv = find(&["u", "s", "texture_u", "texture_s"]).zip(find(&["v", "t", "texture_v", "texture_t"]));
+
+    let color_scale = color.map(|c| match element.properties[c[0]].kind {
+        Kind::Scalar(scalar) => scalar.full(),
+        Kind::List(..) => None,
+    });
+
+    for _ in 0..element.count {
+        let mut values = Vec::with_capacity(element.properties.len());
+        for property in &element.properties {
+            values.push(body.read_property(property)?.first().copied().unwrap_or(0.0));
+        }
+
+        let vec = |[x, y, z]: [usize; 3]| Vec3::new(values[x], values[y], values[z]);
+        mesh.positions.push(vec(position));
+        if let Some(normal) = normal {
+            mesh.normals.push(vec(normal));
+        }
+        if let (Some(color), Some(scale)) = (color, color_scale) {
+            let c = vec(color);
+            mesh.colors.push(match scale {
+                Some(full) => Color::new(srgb_to_linear(c.x / full), srgb_to_linear(c.y / full), srgb_to_linear(c.z / full)),
+                None => c,
+            });
+        }
+        if let Some((u, v)) = uv {
+            mesh.uvs.push((values[u], values[v]));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_faces(body: &mut Body, element: &Element, mesh: &mut Mesh) -> io::Result<()> {
+    let indices = element.properties.iter()
+        .position(|p| matches!(p.kind, Kind::List(..)) && (p.name == "vertex_indices" || p.name == "vertex_index"))
+        .ok_or_else(|| invalid_data("PLY faces have no vertex indices"))?;
+
+    for _ in 0..element.count {
+        for (i, property) in element.properties.iter().enumerate() {
+            let values = body.read_property(property)?;
+            if i != indices {
+                continue;
+            }
+            if values.iter().any(|&v| v < 0.0) {
+                return Err(invalid_data("PLY face refers to a vertex that doesn't exist"));
+            }
+
+            // quads are kept whole, and larger faces fanned out from the first corner
+            let corners: Vec<usize> = values.iter().map(|&v| v as usize).collect();
+            if let [a, b, c, d] = corners[..] {
+                mesh.quads.push([a, b, c, d]);
+                continue;
+            }
+            for k in 1..corners.len().saturating_sub(1) {
+                mesh.triangles.push([corners[0], corners[k], corners[k + 1]]);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,377 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::color::{luminance, Color};
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::microfacet::{fresnel_dielectric, Ggx};
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::util::random;
+use crate::vec3::Vec3;
+
+// the clearcoat is a thin, nearly smooth varnish with the reflectance of a polyurethane
+const CLEARCOAT_ROUGHNESS: f64 = 0.1;
+const CLEARCOAT_F0: f64 = 0.04;
+
+/// A principled material in the style of Disney's BSDF, blending a diffuse base, metal, glossy and
+/// transmissive dielectric, sheen and clearcoat from a handful of artist friendly parameters, each of
+/// which can be driven by a texture. Made with a [`PrincipledBuilder`].
+///
+/// Specular highlights use the GGX microfacet distribution, and the clearcoat a second, smoother GGX
+/// layer, rather than the original's GTR1.
+pub struct Principled {
+    base_color: Arc<dyn Texture>,
+    metallic: Arc<dyn Texture>,
+    roughness: Arc<dyn Texture>,
+    specular: Arc<dyn Texture>,
+    specular_tint: Arc<dyn Texture>,
+    sheen: Arc<dyn Texture>,
+    clearcoat: Arc<dyn Texture>,
+    transmission: Arc<dyn Texture>,
+    ior: Arc<dyn Texture>,
+}
+
+// the parameters looked up at one point on the surface
+struct Params {
+    base_color: Color,
+    metallic: f64,
+    roughness: f64,
+    specular: f64,
+    specular_tint: f64,
+    sheen: f64,
+    clearcoat: f64,
+    transmission: f64,
+    ior: f64,
+    ggx: Ggx,
+    clearcoat_ggx: Ggx,
+}
+
+// the chance of sampling each lobe, which sum to 1
+struct Lobes {
+    diffuse: f64,
+    specular: f64,
+    clearcoat: f64,
+    transmission: f64,
+}
+
+impl Principled {
+    fn params(&self, rec: &HitRecord) -> Params {
+        let (u, v, p) = (rec.u, rec.v, rec.p);
+        let roughness = self.roughness.scalar(u, v, p).clamp(0.0, 1.0);
+
+        Params {
+            base_color: self.base_color.value(u, v, p),
+            metallic: self.metallic.scalar(u, v, p).clamp(0.0, 1.0),
+            roughness,
+            specular: self.specular.scalar(u, v, p).max(0.0),
+            specular_tint: self.specular_tint.scalar(u, v, p).clamp(0.0, 1.0),
+            sheen: self.sheen.scalar(u, v, p).max(0.0),
+            clearcoat: self.clearcoat.scalar(u, v, p).max(0.0),
+            transmission: self.transmission.scalar(u, v, p).clamp(0.0, 1.0),
+            ior: self.ior.scalar(u, v, p),
+            ggx: Ggx::new(roughness),
+            clearcoat_ggx: Ggx::new(CLEARCOAT_ROUGHNESS),
+        }
+    }
+
+    fn lobes(p: &Params, cos_o: f64) -> Option<Lobes> {
+        let dielectric = 1.0 - p.metallic;
+        let fresnel = fresnel_dielectric(cos_o, p.ior);
+
+        let diffuse = dielectric * (1.0 - p.transmission) * (luminance(p.base_color) + p.sheen);
+        let specular = p.metallic * luminance(schlick(p.base_color, cos_o))
+            + dielectric * (1.0 - p.transmission) * luminance(schlick(p.specular_color(), cos_o))
+            + dielectric * p.transmission * fresnel;
+        let clearcoat = 0.25 * p.clearcoat * schlick_scalar(CLEARCOAT_F0, cos_o);
+        let transmission = dielectric * p.transmission * (1.0 - fresnel);
+
+        let total = diffuse + specular + clearcoat + transmission;
+        if total <= 0.0 {
+            return None;
+        }
+
+        Some(Lobes {
+            diffuse: diffuse / total,
+            specular: specular / total,
+            clearcoat: clearcoat / total,
+            transmission: transmission / total,
+        })
+    }
+
+    // the BSDF times the cosine term and the probability density of `scatter` choosing `wi`, seen from
+    // outside the surface
+    fn evaluate_outside(p: &Params, lobes: &Lobes, n: Vec3, wo: Vec3, wi: Vec3) -> (Color, f64) {
+        let (cos_o, cos_i) = (n.dot(&wo), n.dot(&wi));
+        let dielectric = 1.0 - p.metallic;
+        let mut f_cos = Color::new(0,0,0);
+        let mut pdf = 0.0;
+
+        if cos_i > 0.0 {
+            let h = (wo + wi).unit_vector();
+            let cos_d = wi.dot(&h);
+
+            // Burley's diffuse, which darkens smooth surfaces and brightens rough ones at grazing
+            // angles, plus sheen for cloth
+            let fd90 = 0.5 + 2.0 * p.roughness * cos_d * cos_d;
+            let fd = (1.0 + (fd90 - 1.0) * (1.0 - cos_i).powi(5)) * (1.0 + (fd90 - 1.0) * (1.0 - cos_o).powi(5));
+            let sheen = p.sheen * (0.5 * Color::new(1,1,1) + 0.5 * p.tint()) * (1.0 - cos_d).powi(5);
+            f_cos += dielectric * (1.0 - p.transmission) * (p.base_color * fd / PI + sheen) * cos_i;
+            pdf += lobes.diffuse * cos_i / PI;
+
+            if let Some((g, g_pdf, m)) = p.ggx.reflection(n, wo, wi) {
+                let cos_m = wo.dot(&m);
+                let fresnel = p.metallic * schlick(p.base_color, cos_m)
+                    + dielectric * (1.0 - p.transmission) * schlick(p.specular_color(), cos_m)
+                    + dielectric * p.transmission * fresnel_dielectric(cos_m, p.ior) * Color::new(1,1,1);
+                f_cos += fresnel * g;
+                pdf += lobes.specular * g_pdf;
+            }
+
+            if let Some((g, g_pdf, m)) = p.clearcoat_ggx.reflection(n, wo, wi) {
+                f_cos += Color::new(1,1,1) * (0.25 * p.clearcoat * schlick_scalar(CLEARCOAT_F0, wo.dot(&m)) * g);
+                pdf += lobes.clearcoat * g_pdf;
+            }
+        } else if let Some((g, g_pdf, m)) = p.ggx.transmission(n, wo, wi, p.ior) {
+            let transmitted = 1.0 - fresnel_dielectric(wo.dot(&m), p.ior);
+            f_cos += dielectric * p.transmission * transmitted * g * p.transmission_tint();
+            pdf += lobes.transmission * g_pdf;
+        }
+
+        (f_cos, pdf)
+    }
+
+    fn scatter_outside(p: &Params, n: Vec3, wo: Vec3) -> Option<(Color, Vec3)> {
+        let lobes = Self::lobes(p, n.dot(&wo))?;
+
+        let choice = random::<f64>();
+        let wi = if choice < lobes.diffuse {
+            let direction = n + Vec3::random_unit_vector();
+            if direction.near_zero() {n} else {direction.unit_vector()}
+        } else if choice < lobes.diffuse + lobes.specular + lobes.clearcoat {
+            let ggx = if choice < lobes.diffuse + lobes.specular {&p.ggx} else {&p.clearcoat_ggx};
+            let wi = Vec3::reflect(&-wo, &ggx.sample_visible_normal(n, wo));
+            if n.dot(&wi) <= 0.0 {
+                return None;
+            }
+            wi
+        } else {
+            let m = p.ggx.sample_visible_normal(n, wo);
+            if fresnel_dielectric(wo.dot(&m), p.ior) >= 1.0 {
+                return None;
+            }
+            let wi = Vec3::refract(&-wo, &m, 1.0 / p.ior);
+            if n.dot(&wi) >= 0.0 {
+                return None;
+            }
+            wi
+        };
+
+        // whichever lobe chose the direction, weight it by all of them
+        let (f_cos, pdf) = Self::evaluate_outside(p, &lobes, n, wo, wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some((f_cos / pdf, wi))
+    }
+
+    // light only gets inside through the transmission, and inside the surface is plain rough glass
+    fn scatter_inside(p: &Params, n: Vec3, wo: Vec3) -> Option<(Color, Vec3)> {
+        let eta = 1.0 / p.ior;
+        let m = p.ggx.sample_visible_normal(n, wo);
+
+        let (atten, wi) = if fresnel_dielectric(wo.dot(&m), eta) > random::<f64>() {
+            let wi = Vec3::reflect(&-wo, &m);
+            if n.dot(&wi) <= 0.0 {
+                return None;
+            }
+            (Color::new(1,1,1), wi)
+        } else {
+            let wi = Vec3::refract(&-wo, &m, p.ior);
+            if n.dot(&wi) >= 0.0 {
+                return None;
+            }
+            (p.transmission_tint(), wi)
+        };
+
+        Some((atten * (p.ggx.g2(n, wo, wi) / p.ggx.g1(n, wo)), wi))
+    }
+
+    fn evaluate_inside(p: &Params, n: Vec3, wo: Vec3, wi: Vec3) -> (Color, f64) {
+        let eta = 1.0 / p.ior;
+
+        if let Some((g, g_pdf, m)) = p.ggx.reflection(n, wo, wi) {
+            let fresnel = fresnel_dielectric(wo.dot(&m), eta);
+            return (Color::new(1,1,1) * (fresnel * g), fresnel * g_pdf);
+        }
+        if let Some((g, g_pdf, m)) = p.ggx.transmission(n, wo, wi, eta) {
+            let transmitted = 1.0 - fresnel_dielectric(wo.dot(&m), eta);
+            return (p.transmission_tint() * (transmitted * g), transmitted * g_pdf);
+        }
+        (Color::new(0,0,0), 0.0)
+    }
+}
+
+impl Params {
+    // the base colour's hue at full brightness
+    fn tint(&self) -> Color {
+        let l = luminance(self.base_color);
+        if l > 0.0 {self.base_color / l} else {Color::new(1,1,1)}
+    }
+
+    // reflectance at normal incidence of the non-metallic specular, 0.04 (an IOR of 1.5) at the
+    // default `specular` of 0.5
+    fn specular_color(&self) -> Color {
+        0.08 * self.specular * ((1.0 - self.specular_tint) * Color::new(1,1,1) + self.specular_tint * self.tint())
+    }
+
+    // light is tinted by the square root of the base colour on the way in and again on the way out
+    fn transmission_tint(&self) -> Color {
+        let c = self.base_color;
+        Color::new(c.x.max(0.0).sqrt(), c.y.max(0.0).sqrt(), c.z.max(0.0).sqrt())
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let p = self.params(rec);
+        let (n, wo) = (rec.normal, -r_in.dir.unit_vector());
+
+        let (atten, wi) = if rec.front_face {
+            Self::scatter_outside(&p, n, wo)?
+        } else {
+            Self::scatter_inside(&p, n, wo)?
+        };
+        Some((atten, Ray::new(rec.p, wi)))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let p = self.params(rec);
+        let (n, wo, wi) = (rec.normal, -r_in.dir.unit_vector(), direction.unit_vector());
+
+        if !rec.front_face {
+            return Some(Self::evaluate_inside(&p, n, wo, wi));
+        }
+        let Some(lobes) = Self::lobes(&p, n.dot(&wo)) else {return Some((Color::new(0,0,0), 0.0))};
+        Some(Self::evaluate_outside(&p, &lobes, n, wo, wi))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base_color.value(rec.u, rec.v, rec.p)
+    }
+}
+
+/// Collects the parameters of a [`Principled`] material; anything left unset gets a default when it's
+/// built. Every parameter takes a texture, and plain colours and numbers are textures too:
+///
+/// ```
+/// use std::sync::Arc;
+///
+/// use raytracing::{Color, PrincipledBuilder};
+///
+/// let gold = PrincipledBuilder::new()
+///     .base_color(Arc::new(Color::new(1.0, 0.78, 0.34)))
+///     .metallic(Arc::new(1.0))
+///     .roughness(Arc::new(0.3))
+///     .build();
+/// ```
+#[derive(Default)]
+pub struct PrincipledBuilder {
+    base_color: Option<Arc<dyn Texture>>,
+    metallic: Option<Arc<dyn Texture>>,
+    roughness: Option<Arc<dyn Texture>>,
+    specular: Option<Arc<dyn Texture>>,
+    specular_tint: Option<Arc<dyn Texture>>,
+    sheen: Option<Arc<dyn Texture>>,
+    clearcoat: Option<Arc<dyn Texture>>,
+    transmission: Option<Arc<dyn Texture>>,
+    ior: Option<Arc<dyn Texture>>,
+}
+
+impl PrincipledBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn build(&self) -> Principled {
+        let or = |texture: &Option<Arc<dyn Texture>>, default: f64| {
+            texture.clone().unwrap_or_else(|| Arc::new(default))
+        };
+
+        Principled {
+            base_color: self.base_color.clone().unwrap_or_else(|| Arc::new(Color::new(0.8, 0.8, 0.8))),
+            metallic: or(&self.metallic, 0.0),
+            roughness: or(&self.roughness, 0.5),
+            specular: or(&self.specular, 0.5),
+            specular_tint: or(&self.specular_tint, 0.0),
+            sheen: or(&self.sheen, 0.0),
+            clearcoat: or(&self.clearcoat, 0.0),
+            transmission: or(&self.transmission, 0.0),
+            ior: or(&self.ior, 1.5),
+        }
+    }
+
+    /// The colour of the diffuse base, of metal, and of light passing through. Defaults to 0.8 grey.
+    pub fn base_color(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.base_color = Some(texture);
+        self
+    }
+
+    /// 0 for a dielectric and 1 for a metal. Defaults to 0.
+    pub fn metallic(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.metallic = Some(texture);
+        self
+    }
+
+    /// From 0, a mirror finish, to 1. Defaults to 0.5.
+    pub fn roughness(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.roughness = Some(texture);
+        self
+    }
+
+    /// The strength of a dielectric's reflections, where 0.5 is typical of most materials. Defaults to
+    /// 0.5.
+    pub fn specular(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.specular = Some(texture);
+        self
+    }
+
+    /// How much a dielectric's reflections take on the base colour. Defaults to 0.
+    pub fn specular_tint(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.specular_tint = Some(texture);
+        self
+    }
+
+    /// A soft glow at grazing angles, as on cloth. Defaults to 0.
+    pub fn sheen(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.sheen = Some(texture);
+        self
+    }
+
+    /// The strength of a glossy varnish over everything else. Defaults to 0.
+    pub fn clearcoat(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.clearcoat = Some(texture);
+        self
+    }
+
+    /// How much of a dielectric lets light through instead of scattering it diffusely. Defaults to 0.
+    pub fn transmission(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.transmission = Some(texture);
+        self
+    }
+
+    /// The index of refraction for transmission. Defaults to 1.5.
+    pub fn ior(&mut self, texture: Arc<dyn Texture>) -> &mut Self {
+        self.ior = Some(texture);
+        self
+    }
+}
+
+// Schlick's approximation to the Fresnel term, for reflectance `f0` at normal incidence
+fn schlick(f0: Color, cos: f64) -> Color {
+    f0 + (Color::new(1,1,1) - f0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
+
+fn schlick_scalar(f0: f64, cos: f64) -> f64 {
+    f0 + (1.0 - f0) * (1.0 - cos.clamp(0.0, 1.0)).powi(5)
+}
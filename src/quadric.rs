@@ -0,0 +1,599 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::csg::{spans_between, Solid, Span};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// A cylinder of `radius` around the line from `base` to `base + axis`, optionally closed at both
+/// ends with flat caps. `u` goes around the axis and `v` along it; on the caps `v` goes out from the
+/// middle.
+pub struct Cylinder {
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, mat: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(base, axis),
+            radius,
+            height: axis.length(),
+            capped,
+            mat,
+        }
+    }
+
+    fn local_hit(&self, o: Vec3, d: Vec3, ray_t: Interval) -> Option<Local> {
+        let (r, h) = (self.radius, self.height);
+        let a = d.x * d.x + d.z * d.z;
+        let b = 2.0 * (o.x * d.x + o.z * d.z);
+        let c = o.x * o.x + o.z * o.z - r * r;
+
+        let side = solve_quadratic(a, b, c).into_iter().find_map(|t| {
+            let p = o + t * d;
+            if !ray_t.surrounds(t) || p.y < 0.0 || p.y > h {
+                return None;
+            }
+
+            Some(Local {
+                t,
+                p,
+                normal: Vec3::new(p.x / r, 0.0, p.z / r),
+                u: azimuth(p),
+                v: p.y / h,
+                dpdu: around(p),
+                dpdv: Vec3::new(0.0, h, 0.0),
+            })
+        });
+
+        if !self.capped {
+            return side;
+        }
+        let bottom = disk_hit(o, d, ray_t, 0.0, r, -1.0);
+        let top = disk_hit(o, d, ray_t, h, r, 1.0);
+        nearest([side, bottom, top])
+    }
+}
+
+/// A cone with a base of `radius` at `base`, narrowing to a point at `base + axis`, optionally closed
+/// with a flat cap over the base. `u` goes around the axis and `v` up from the base to the tip.
+pub struct Cone {
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: Arc<dyn Material>,
+}
+
+impl Cone {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, mat: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(base, axis),
+            radius,
+            height: axis.length(),
+            capped,
+            mat,
+        }
+    }
+
+    fn local_hit(&self, o: Vec3, d: Vec3, ray_t: Interval) -> Option<Local> {
+        let (r, h) = (self.radius, self.height);
+        // the radius narrows by `k` for each unit up the axis
+        let k = r / h;
+        let rho_o = r - k * o.y;
+        let a = d.x * d.x + d.z * d.z - k * k * d.y * d.y;
+        let b = 2.0 * (o.x * d.x + o.z * d.z + k * d.y * rho_o);
+        let c = o.x * o.x + o.z * o.z - rho_o * rho_o;
+
+        let side = solve_quadratic(a, b, c).into_iter().find_map(|t| {
+            let p = o + t * d;
+            if !ray_t.surrounds(t) || p.y < 0.0 || p.y > h {
+                return None;
+            }
+
+            let rho = r - k * p.y;
+            let normal = Vec3::new(p.x, k * rho, p.z);
+            let normal = if normal.near_zero() {Vec3::new(0,1,0)} else {normal.unit_vector()};
+
+            // the direction up the slope, which is along the axis at the tip where there's no circle
+            let out = if rho > 0.0 {Vec3::new(p.x / rho, 0.0, p.z / rho)} else {Vec3::new(0,0,0)};
+            Some(Local {
+                t,
+                p,
+                normal,
+                u: azimuth(p),
+                v: p.y / h,
+                dpdu: around(p),
+                dpdv: h * (Vec3::new(0,1,0) - k * out),
+            })
+        });
+
+        if !self.capped {
+            return side;
+        }
+        nearest([side, disk_hit(o, d, ray_t, 0.0, r, -1.0), None])
+    }
+}
+
+/// A flat disk of `radius` centred on `center`, facing along `normal`. `u` goes around the centre and
+/// `v` out from it.
+pub struct Disk {
+    frame: Frame,
+    radius: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new(center: Point3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(center, normal),
+            radius,
+            mat,
+        }
+    }
+
+    fn local_hit(&self, o: Vec3, d: Vec3, ray_t: Interval) -> Option<Local> {
+        disk_hit(o, d, ray_t, 0.0, self.radius, 1.0)
+    }
+}
+
+/// A torus centred on `center`, lying in the plane facing along `axis`: a tube of radius `minor`
+/// following a circle of radius `major`. `u` goes around the axis and `v` around the tube.
+pub struct Torus {
+    frame: Frame,
+    major: f64,
+    minor: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new(center: Point3, axis: Vec3, major: f64, minor: f64, mat: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(center, axis),
+            major,
+            minor,
+            mat,
+        }
+    }
+
+    fn local_hit(&self, o: Vec3, d: Vec3, ray_t: Interval) -> Option<Local> {
+        let (big, small) = (self.major, self.minor);
+
+        // solve along a unit direction, which keeps the quartic's coefficients in proportion
+        let length = d.length();
+        let dir = d / length;
+        let e = o.length_squared() - big * big - small * small;
+        let f = o.dot(&dir);
+        let four_r2 = 4.0 * big * big;
+        let coefficients = [
+            1.0,
+            4.0 * f,
+            2.0 * e + 4.0 * f * f + four_r2 * dir.y * dir.y,
+            4.0 * f * e + 2.0 * four_r2 * o.y * dir.y,
+            e * e - four_r2 * (small * small - o.y * o.y),
+        ];
+
+        let mut roots = solve_quartic(coefficients);
+        roots.sort_by(f64::total_cmp);
+        let t = roots.into_iter().map(|s| s / length).find(|&t| ray_t.surrounds(t))?;
+
+        let p = o + t * d;
+        let rho = (p.x * p.x + p.z * p.z).sqrt();
+        let ring = if rho > 0.0 {Vec3::new(p.x, 0.0, p.z) * (big / rho)} else {Vec3::new(big, 0.0, 0.0)};
+        let normal = (p - ring).unit_vector();
+
+        // around the tube, starting from the outside of the ring
+        let theta = p.y.atan2(rho - big);
+        let v = theta.rem_euclid(2.0 * PI) / (2.0 * PI);
+        let out = if rho > 0.0 {Vec3::new(p.x / rho, 0.0, p.z / rho)} else {Vec3::new(1,0,0)};
+        let dpdv = 2.0 * PI * small * (Vec3::new(0.0, theta.cos(), 0.0) - theta.sin() * out);
+
+        Some(Local {
+            t,
+            p,
+            normal,
+            u: azimuth(p),
+            v,
+            dpdu: around(p),
+            dpdv,
+        })
+    }
+}
+
+/// A bowl shaped paraboloid with its lowest point at `base`, opening along `axis` to a rim of
+/// `radius` at `base + axis`, optionally closed with a flat cap over the rim. `u` goes around the axis
+/// and `v` up from the base to the rim.
+pub struct Paraboloid {
+    frame: Frame,
+    radius: f64,
+    height: f64,
+    capped: bool,
+    mat: Arc<dyn Material>,
+}
+
+impl Paraboloid {
+    pub fn new(base: Point3, axis: Vec3, radius: f64, capped: bool, mat: Arc<dyn Material>) -> Self {
+        Self {
+            frame: Frame::new(base, axis),
+            radius,
+            height: axis.length(),
+            capped,
+            mat,
+        }
+    }
+
+    fn local_hit(&self, o: Vec3, d: Vec3, ray_t: Interval) -> Option<Local> {
+        let (r, h) = (self.radius, self.height);
+        // the surface is y = k (x² + z²)
+        let k = h / (r * r);
+        let a = k * (d.x * d.x + d.z * d.z);
+        let b = 2.0 * k * (o.x * d.x + o.z * d.z) - d.y;
+        let c = k * (o.x * o.x + o.z * o.z) - o.y;
+
+        let side = solve_quadratic(a, b, c).into_iter().find_map(|t| {
+            let p = o + t * d;
+            if !ray_t.surrounds(t) || p.y < 0.0 || p.y > h {
+                return None;
+            }
+
+            let rho = (p.x * p.x + p.z * p.z).sqrt();
+            let out = if rho > 0.0 {Vec3::new(p.x / rho, 0.0, p.z / rho)} else {Vec3::new(0,0,0)};
+            // the slope outwards per unit up the axis, infinite at the bottom where the bowl is flat
+            let spread = if rho > 0.0 {1.0 / (2.0 * k * rho)} else {0.0};
+            Some(Local {
+                t,
+                p,
+                normal: Vec3::new(2.0 * k * p.x, -1.0, 2.0 * k * p.z).unit_vector(),
+                u: azimuth(p),
+                v: p.y / h,
+                dpdu: around(p),
+                dpdv: h * (Vec3::new(0,1,0) + spread * out),
+            })
+        });
+
+        if !self.capped {
+            return side;
+        }
+        nearest([side, disk_hit(o, d, ray_t, h, r, 1.0), None])
+    }
+}
+
+impl Hittable for Cylinder {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (o, d) = self.frame.to_local(r);
+        let local = self.local_hit(o, d, ray_t)?;
+        Some(self.frame.record(r, local, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (base, top) = (self.frame.to_world(Vec3::new(0,0,0)), self.frame.to_world(Vec3::new(0.0, self.height, 0.0)));
+        Some(disk_box(base, self.frame.axis, self.radius).union(&disk_box(top, self.frame.axis, self.radius)))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+impl Hittable for Cone {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (o, d) = self.frame.to_local(r);
+        let local = self.local_hit(o, d, ray_t)?;
+        Some(self.frame.record(r, local, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (base, tip) = (self.frame.to_world(Vec3::new(0,0,0)), self.frame.to_world(Vec3::new(0.0, self.height, 0.0)));
+        Some(disk_box(base, self.frame.axis, self.radius).union(&Aabb::new(tip, tip)))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+impl Hittable for Disk {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (o, d) = self.frame.to_local(r);
+        let local = self.local_hit(o, d, ray_t)?;
+        Some(self.frame.record(r, local, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(disk_box(self.frame.origin, self.frame.axis, self.radius))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+impl Hittable for Torus {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (o, d) = self.frame.to_local(r);
+        let local = self.local_hit(o, d, ray_t)?;
+        Some(self.frame.record(r, local, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // the ring's circle, thickened by the tube in every direction
+        let ring = disk_box(self.frame.origin, self.frame.axis, self.major);
+        let tube = Vec3::new(self.minor, self.minor, self.minor);
+        Some(Aabb::new(ring.min - tube, ring.max + tube))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+impl Hittable for Paraboloid {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (o, d) = self.frame.to_local(r);
+        let local = self.local_hit(o, d, ray_t)?;
+        Some(self.frame.record(r, local, &self.mat))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (base, rim) = (self.frame.to_world(Vec3::new(0,0,0)), self.frame.to_world(Vec3::new(0.0, self.height, 0.0)));
+        // the bowl bulges out past the cone from its base to its rim, but stays within the cylinder
+        Some(disk_box(rim, self.frame.axis, self.radius).union(&disk_box(base, self.frame.axis, self.radius)))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+impl Solid for Cylinder {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        if !self.capped {
+            return Vec::new();
+        }
+        spans_between(self, r, Interval::new(f64::NEG_INFINITY, f64::INFINITY))
+    }
+}
+
+impl Solid for Cone {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        if !self.capped {
+            return Vec::new();
+        }
+        spans_between(self, r, Interval::new(f64::NEG_INFINITY, f64::INFINITY))
+    }
+}
+
+impl Solid for Torus {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        spans_between(self, r, Interval::new(f64::NEG_INFINITY, f64::INFINITY))
+    }
+}
+
+impl Solid for Paraboloid {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        if !self.capped {
+            return Vec::new();
+        }
+        spans_between(self, r, Interval::new(f64::NEG_INFINITY, f64::INFINITY))
+    }
+}
+
+// a shape's own coordinates, with `axis` as y, and `x` and `z` across it
+struct Frame {
+    origin: Point3,
+    x: Vec3,
+    axis: Vec3,
+    z: Vec3,
+}
+
+impl Frame {
+    fn new(origin: Point3, axis: Vec3) -> Self {
+        let axis = axis.unit_vector();
+        let (x, z) = axis.orthonormal_basis();
+        // keep the frame right handed, so that the parameterisations face the way they should
+        let z = if x.cross(&axis).dot(&z) < 0.0 {-z} else {z};
+
+        Self {
+            origin,
+            x,
+            axis,
+            z,
+        }
+    }
+
+    // the ray's origin and direction in the frame, in which `t` means the same
+    fn to_local(&self, r: &Ray) -> (Vec3, Vec3) {
+        let o = r.orig - self.origin;
+        let local = |v: Vec3| Vec3::new(v.dot(&self.x), v.dot(&self.axis), v.dot(&self.z));
+        (local(o), local(r.dir))
+    }
+
+    fn to_world(&self, p: Vec3) -> Point3 {
+        self.origin + self.direction_to_world(p)
+    }
+
+    fn direction_to_world(&self, v: Vec3) -> Vec3 {
+        v.x * self.x + v.y * self.axis + v.z * self.z
+    }
+
+    fn record(&self, r: &Ray, local: Local, mat: &Arc<dyn Material>) -> HitRecord {
+        let outward_normal = self.direction_to_world(local.normal).unit_vector();
+        let mut rec = HitRecord {
+            p: self.to_world(local.p),
+            normal: outward_normal,
+            geometric_normal: outward_normal,
+            dpdu: self.direction_to_world(local.dpdu),
+            dpdv: self.direction_to_world(local.dpdv),
+            mat: Arc::clone(mat),
+            t: local.t,
+            u: local.u,
+            v: local.v,
+            barycentric: None,
+            color: None,
+            front_face: false,
+            object_id: 0,
+            material_id: 0,
+        };
+
+        rec.set_face_normal(r, &outward_normal);
+        rec
+    }
+}
+
+// a hit in a shape's frame
+struct Local {
+    t: f64,
+    p: Vec3,
+    normal: Vec3,
+    u: f64,
+    v: f64,
+    dpdu: Vec3,
+    dpdv: Vec3,
+}
+
+// the closest of some hits
+fn nearest<const N: usize>(hits: [Option<Local>; N]) -> Option<Local> {
+    hits.into_iter().flatten().min_by(|a, b| a.t.total_cmp(&b.t))
+}
+
+// a disk of `radius` across the axis at height `y`, with its outward normal along the axis in the
+// direction of `facing`
+fn disk_hit(o: Vec3, d: Vec3, ray_t: Interval, y: f64, radius: f64, facing: f64) -> Option<Local> {
+    if d.y == 0.0 {
+        return None;
+    }
+
+    let t = (y - o.y) / d.y;
+    let p = o + t * d;
+    let rho = (p.x * p.x + p.z * p.z).sqrt();
+    if !ray_t.surrounds(t) || rho > radius {
+        return None;
+    }
+
+    let out = if rho > 0.0 {Vec3::new(p.x / rho, 0.0, p.z / rho)} else {Vec3::new(1,0,0)};
+    // going around and then out turns the other way to the normal when it faces up the axis, so `u`
+    // runs backwards there to keep the tangents right handed
+    let (u, dpdu) = if facing > 0.0 {(1.0 - azimuth(p), -around(p))} else {(azimuth(p), around(p))};
+    Some(Local {
+        t,
+        p,
+        normal: Vec3::new(0.0, facing, 0.0),
+        u,
+        v: rho / radius,
+        dpdu,
+        dpdv: radius * out,
+    })
+}
+
+// how far around the axis `p` is, from 0 to 1, as for spheres
+fn azimuth(p: Vec3) -> f64 {
+    ((-p.z).atan2(p.x) + PI) / (2.0 * PI)
+}
+
+// how `p` moves as `azimuth` goes from 0 to 1
+fn around(p: Vec3) -> Vec3 {
+    2.0 * PI * Vec3::new(p.z, 0.0, -p.x)
+}
+
+// the bounding box of a disk, which reaches less far along the axes its normal leans towards
+fn disk_box(center: Point3, normal: Vec3, radius: f64) -> Aabb {
+    let extent = |n: f64| radius * (1.0 - n * n).max(0.0).sqrt();
+    let e = Vec3::new(extent(normal.x), extent(normal.y), extent(normal.z));
+    Aabb::new(center - e, center + e)
+}
+
+// the real roots of a t² + b t + c, in increasing order
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0.0 {
+        return if b == 0.0 {Vec::new()} else {vec![-c / b]};
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Vec::new();
+    }
+
+    // avoid subtracting nearly equal numbers, which loses precision in the smaller root
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let (t0, t1) = if q == 0.0 {(0.0, 0.0)} else {(q / a, c / q)};
+    if t0 < t1 {vec![t0, t1]} else {vec![t1, t0]}
+}
+
+// the real roots of the cubic t³ + a t² + b t + c
+fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    // with t = s - a/3 this becomes s³ + p s + q
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let shift = -a / 3.0;
+
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+    if discriminant > 0.0 {
+        // one real root, by Cardano's formula
+        let sqrt = discriminant.sqrt();
+        vec![(-q / 2.0 + sqrt).cbrt() + (-q / 2.0 - sqrt).cbrt() + shift]
+    } else if p == 0.0 {
+        vec![shift]
+    } else {
+        // three real roots, by the trigonometric method
+        let m = 2.0 * (-p / 3.0).sqrt();
+        let theta = (3.0 * q / (p * m)).clamp(-1.0, 1.0).acos() / 3.0;
+        (0..3).map(|k| m * (theta - 2.0 * PI * k as f64 / 3.0).cos() + shift).collect()
+    }
+}
+
+// the real roots of the quartic with coefficients `c`, highest power first, by Ferrari's method
+fn solve_quartic(c: [f64; 5]) -> Vec<f64> {
+    let [a4, a3, a2, a1, a0] = c;
+    let (a, b, c, d) = (a3 / a4, a2 / a4, a1 / a4, a0 / a4);
+
+    // with t = y - a/4 this becomes y⁴ + p y² + q y + r
+    let aa = a * a;
+    let p = b - 3.0 * aa / 8.0;
+    let q = c - a * b / 2.0 + aa * a / 8.0;
+    let r = d - a * c / 4.0 + aa * b / 16.0 - 3.0 * aa * aa / 256.0;
+    let shift = -a / 4.0;
+
+    let mut roots = Vec::new();
+    if q.abs() < 1e-12 {
+        // a quadratic in y²
+        for z in solve_quadratic(1.0, p, r) {
+            if z >= 0.0 {
+                roots.extend([z.sqrt(), -z.sqrt()]);
+            }
+        }
+    } else {
+        // split into two quadratics using a positive root of the resolvent cubic
+        let m = solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0).into_iter().fold(f64::MIN, f64::max);
+        if m <= 0.0 {
+            return Vec::new();
+        }
+
+        let s = (2.0 * m).sqrt();
+        roots.extend(solve_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s)));
+        roots.extend(solve_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s)));
+    }
+
+    // polish each root against the original quartic, as the steps above lose precision
+    let f = |t: f64| (((a4 * t + a3) * t + a2) * t + a1) * t + a0;
+    let df = |t: f64| ((4.0 * a4 * t + 3.0 * a3) * t + 2.0 * a2) * t + a1;
+    roots.into_iter()
+        .map(|y| {
+            let mut t = y + shift;
+            for _ in 0..2 {
+                let slope = df(t);
+                if slope != 0.0 {
+                    t -= f(t) / slope;
+                }
+            }
+            t
+        })
+        .collect()
+}
@@ -1,8 +1,11 @@
 use crate::vec3::*;
 
+#[derive(Clone, Copy, Debug)]
 pub struct Ray {
     pub orig: Point3,
     pub dir: Vec3,
+    /// The wavelength in nanometres that decides how the ray disperses, when rendering spectrally.
+    pub wavelength: Option<f64>,
 }
 
 impl Ray {
@@ -10,6 +13,7 @@ impl Ray {
         Self {
             orig,
             dir,
+            wavelength: None,
         }
     }
     
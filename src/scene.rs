@@ -0,0 +1,391 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use gltf::camera::Projection;
+use gltf::mesh::Mode;
+
+use crate::camera::CameraBuilder;
+use crate::color::Color;
+use crate::hittable_list::HittableList;
+use crate::material::Material;
+use crate::mesh::{invalid_data, Mesh, TriangleMesh};
+use crate::normal_map::NormalMap;
+use crate::principled::PrincipledBuilder;
+use crate::texture::{ImageTexture, Texture};
+use crate::vec3::{Point3, Vec3};
+use crate::vertex_color::VertexColor;
+
+// how deep a node hierarchy may go, which stops a file whose nodes are their own ancestors looping forever
+const MAX_DEPTH: usize = 256;
+
+/// A scene loaded from a file: its meshes, placed in the world and each with a material, and its
+/// cameras, ready to have the image size, sampling and so on set before they're built.
+///
+/// The meshes can be changed before the scene is turned into hittables with [`Scene::world`].
+pub struct Scene {
+    pub meshes: Vec<(Mesh, Arc<dyn Material>)>,
+    pub cameras: Vec<CameraBuilder>,
+}
+
+impl Scene {
+    /// Loads a glTF 2.0 file, either a `.gltf` with its buffers and images embedded or in files beside
+    /// it, or a binary `.glb`. Files elsewhere, such as on the web, aren't fetched.
+    ///
+    /// The default scene's node hierarchy is flattened into world space. Metallic-roughness materials
+    /// become [`Principled`](crate::Principled) materials, with their base colour, metalness and
+    /// roughness textures, transmission and index of refraction, and under a [`NormalMap`] if they
+    /// have a normal texture, and tinted by [`VertexColor`] on primitives with vertex colours.
+    /// Perspective cameras keep their field of view and aspect ratio. Everything else, such as emission,
+    /// alpha, animation, skins, lights and orthographic cameras, is ignored.
+    pub fn load_gltf(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let gltf = gltf::Gltf::from_slice(&fs::read(path)?).map_err(|e| invalid_data(format!("bad glTF file: {e}")))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+
+        let buffers = gltf.document.buffers().map(|buffer| {
+            let data = match buffer.source() {
+                gltf::buffer::Source::Bin => gltf.blob.clone().ok_or_else(|| invalid_data("glTF file has no binary chunk"))?,
+                gltf::buffer::Source::Uri(uri) => read_uri(dir, uri)?,
+            };
+            if data.len() < buffer.length() {
+                return Err(invalid_data(format!("truncated glTF buffer {}", buffer.index())));
+            }
+            Ok(data)
+        }).collect::<io::Result<Vec<_>>>()?;
+
+        let mut loader = Loader {
+            dir,
+            buffers,
+            textures: HashMap::new(),
+            materials: HashMap::new(),
+            scene: Scene {
+                meshes: Vec::new(),
+                cameras: Vec::new(),
+            },
+        };
+
+        let scene = gltf.document.default_scene()
+            .or_else(|| gltf.document.scenes().next())
+            .ok_or_else(|| invalid_data("glTF file has no scene"))?;
+        for node in scene.nodes() {
+            loader.node(&node, &IDENTITY, 0)?;
+        }
+
+        Ok(loader.scene)
+    }
+
+    /// The meshes as hittables, to render on their own or to add to a larger world.
+    pub fn world(&self) -> HittableList {
+        let mut world = HittableList::default();
+        for (mesh, mat) in &self.meshes {
+            world.add(Arc::new(TriangleMesh::new(mesh.clone(), Arc::clone(mat))));
+        }
+        world
+    }
+}
+
+// a transform as four columns, the last being the translation
+type Matrix = [[f64; 4]; 4];
+
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut m = [[0.0; 4]; 4];
+    for (c, column) in m.iter_mut().enumerate() {
+        for (r, value) in column.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[k][r] * b[c][k]).sum();
+        }
+    }
+    m
+}
+
+fn column(m: &Matrix, c: usize) -> Vec3 {
+    Vec3::new(m[c][0], m[c][1], m[c][2])
+}
+
+fn transform_point(m: &Matrix, p: Point3) -> Point3 {
+    transform_vector(m, p) + column(m, 3)
+}
+
+fn transform_vector(m: &Matrix, v: Vec3) -> Vec3 {
+    v.x * column(m, 0) + v.y * column(m, 1) + v.z * column(m, 2)
+}
+
+// normals stay at right angles to the surface by going through the inverse transpose, which is the
+// cofactor matrix over the determinant
+fn transform_normal(m: &Matrix, n: Vec3) -> Vec3 {
+    let (a, b, c) = (column(m, 0), column(m, 1), column(m, 2));
+    let det = a.dot(&b.cross(&c));
+    let n = n.x * b.cross(&c) + n.y * c.cross(&a) + n.z * a.cross(&b);
+    if det < 0.0 {-n} else {n}
+}
+
+struct Loader<'a> {
+    dir: &'a Path,
+    buffers: Vec<Vec<u8>>,
+    // by image and whether it holds colour
+    textures: HashMap<(usize, bool), Arc<ImageTexture>>,
+    materials: HashMap<Option<usize>, Arc<dyn Material>>,
+    scene: Scene,
+}
+
+impl Loader<'_> {
+    fn node(&mut self, node: &gltf::Node, parent: &Matrix, depth: usize) -> io::Result<()> {
+        if depth > MAX_DEPTH {
+            return Err(invalid_data("glTF node hierarchy is too deep, or loops"));
+        }
+
+        let local = node.transform().matrix().map(|c| c.map(f64::from));
+        let transform = multiply(parent, &local);
+
+        if let Some(mesh) = node.mesh() {
+            for primitive in mesh.primitives() {
+                if let Some(triangles) = self.primitive(&primitive, &transform)? {
+                    let mut mat = self.material(&primitive.material())?;
+                    // vertex colours multiply the base colour, which tinting the whole material comes
+                    // close to
+                    if !triangles.colors.is_empty() {
+                        mat = Arc::new(VertexColor::new(mat));
+                    }
+                    self.scene.meshes.push((triangles, mat));
+                }
+            }
+        }
+
+        let camera = node.camera();
+        if let Some(Projection::Perspective(perspective)) = camera.as_ref().map(|c| c.projection()) {
+            // cameras look down their -z axis with +y up
+            let from = transform_point(&transform, Point3::new(0,0,0));
+            let forward = transform_vector(&transform, Vec3::new(0,0,-1));
+            let up = transform_vector(&transform, Vec3::new(0,1,0));
+
+            let mut builder = CameraBuilder::new();
+            builder.field_of_view((perspective.yfov() as f64).to_degrees())
+                .set_view(from, from + forward, up);
+            if let Some(aspect_ratio) = perspective.aspect_ratio() {
+                builder.aspect_ratio(aspect_ratio as f64);
+            }
+            self.scene.cameras.push(builder);
+        }
+
+        for child in node.children() {
+            self.node(&child, &transform, depth + 1)?;
+        }
+        Ok(())
+    }
+
+    // the triangles of a primitive in world space, or `None` for points and lines
+    fn primitive(&self, primitive: &gltf::Primitive, transform: &Matrix) -> io::Result<Option<Mesh>> {
+        let reader = primitive.reader(|buffer| self.buffers.get(buffer.index()).map(Vec::as_slice));
+        let mut mesh = Mesh::default();
+
+        let positions = reader.read_positions().ok_or_else(|| invalid_data("glTF primitive has no positions"))?;
+        mesh.positions = positions.map(|[x, y, z]| transform_point(transform, Vec3::new(x, y, z))).collect();
+        if let Some(normals) = reader.read_normals() {
+            mesh.normals = normals.map(|[x, y, z]| {
+                let n = transform_normal(transform, Vec3::new(x, y, z));
+                if n.near_zero() {n} else {n.unit_vector()}
+            }).collect();
+        }
+        if let Some(colors) = reader.read_colors(0) {
+            mesh.colors = colors.into_rgb_f32().map(|[r, g, b]| Color::new(r, g, b)).collect();
+        }
+        // glTF's texture coordinates run down from the top of the image, and ours up from the bottom
+        if let Some(uvs) = reader.read_tex_coords(0) {
+            mesh.uvs = uvs.into_f32().map(|[u, v]| (u as f64, 1.0 - v as f64)).collect();
+        }
+
+        let count = mesh.positions.len();
+        let indices: Vec<usize> = match reader.read_indices() {
+            Some(indices) => indices.into_u32().map(|i| i as usize).collect(),
+            None => (0..count).collect(),
+        };
+        if indices.iter().any(|&i| i >= count) {
+            return Err(invalid_data("glTF primitive refers to a vertex that doesn't exist"));
+        }
+
+        let n = indices.len();
+        mesh.triangles = match primitive.mode() {
+            Mode::Triangles => indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]]).collect(),
+            // every other triangle of a strip is the other way round
+            Mode::TriangleStrip => (0..n.saturating_sub(2)).map(|i| {
+                if i % 2 == 0 {[indices[i], indices[i + 1], indices[i + 2]]} else {[indices[i + 1], indices[i], indices[i + 2]]}
+            }).collect(),
+            Mode::TriangleFan => (1..n.saturating_sub(1)).map(|i| [indices[0], indices[i], indices[i + 1]]).collect(),
+            _ => return Ok(None),
+        };
+
+        // a mirroring transform turns the triangles inside out
+        let (a, b, c) = (column(transform, 0), column(transform, 1), column(transform, 2));
+        if a.dot(&b.cross(&c)) < 0.0 {
+            for t in &mut mesh.triangles {
+                t.swap(1, 2);
+            }
+        }
+
+        Ok(Some(mesh))
+    }
+
+    fn material(&mut self, material: &gltf::Material) -> io::Result<Arc<dyn Material>> {
+        if let Some(mat) = self.materials.get(&material.index()) {
+            return Ok(Arc::clone(mat));
+        }
+
+        let pbr = material.pbr_metallic_roughness();
+        let mut builder = PrincipledBuilder::new();
+
+        let [r, g, b, _] = pbr.base_color_factor();
+        let base_color = Color::new(r, g, b);
+        builder.base_color(match pbr.base_color_texture() {
+            Some(info) => Arc::new(Scaled {
+                texture: self.texture(&info.texture(), true)?,
+                channel: None,
+                factor: base_color,
+            }),
+            None => Arc::new(base_color),
+        });
+
+        // metalness is in the blue channel and roughness in the green, so they can share an image
+        let (metallic, roughness) = (pbr.metallic_factor() as f64, pbr.roughness_factor() as f64);
+        match pbr.metallic_roughness_texture() {
+            Some(info) => {
+                let texture = self.texture(&info.texture(), false)?;
+                builder.metallic(Arc::new(Scaled::channel(Arc::clone(&texture), 2, metallic)))
+                    .roughness(Arc::new(Scaled::channel(texture, 1, roughness)));
+            }
+            None => {
+                builder.metallic(Arc::new(metallic)).roughness(Arc::new(roughness));
+            }
+        }
+
+        if let Some(transmission) = material.transmission() {
+            let factor = transmission.transmission_factor() as f64;
+            builder.transmission(match transmission.transmission_texture() {
+                Some(info) => Arc::new(Scaled::channel(self.texture(&info.texture(), false)?, 0, factor)),
+                None => Arc::new(factor),
+            });
+        }
+        if let Some(ior) = material.ior() {
+            builder.ior(Arc::new(ior as f64));
+        }
+
+        let principled: Arc<dyn Material> = Arc::new(builder.build());
+        let mat: Arc<dyn Material> = match material.normal_texture() {
+            Some(normal) => Arc::new(NormalMap::new(principled, self.texture(&normal.texture(), false)?)),
+            None => principled,
+        };
+
+        self.materials.insert(material.index(), Arc::clone(&mat));
+        Ok(mat)
+    }
+
+    // the image of `texture`, decoded from sRGB if it holds colour
+    fn texture(&mut self, texture: &gltf::Texture, color: bool) -> io::Result<Arc<dyn Texture>> {
+        let index = texture.source().index();
+        if let Some(texture) = self.textures.get(&(index, color)) {
+            return Ok(Arc::clone(texture) as Arc<dyn Texture>);
+        }
+
+        let bytes = match texture.source().source() {
+            gltf::image::Source::View {view, ..} => {
+                let buffer = &self.buffers[view.buffer().index()];
+                let bytes = buffer.get(view.offset()..view.offset() + view.length())
+                    .ok_or_else(|| invalid_data(format!("truncated glTF image {index}")))?;
+                Cow::Borrowed(bytes)
+            }
+            gltf::image::Source::Uri {uri, ..} => Cow::Owned(read_uri(self.dir, uri)?),
+        };
+
+        let decoded = image::load_from_memory(&bytes).map_err(|e| invalid_data(format!("glTF image {index}: {e}")))?;
+        let loaded = Arc::new(if color {ImageTexture::from_color_image(decoded)} else {ImageTexture::from_image(decoded)});
+        self.textures.insert((index, color), Arc::clone(&loaded));
+        Ok(loaded)
+    }
+}
+
+// a texture, or one channel of it as grey, scaled by a factor, as glTF materials use them
+struct Scaled {
+    texture: Arc<dyn Texture>,
+    channel: Option<usize>,
+    factor: Color,
+}
+
+impl Scaled {
+    fn channel(texture: Arc<dyn Texture>, channel: usize, factor: f64) -> Self {
+        Self {
+            texture,
+            channel: Some(channel),
+            factor: Color::new(factor, factor, factor),
+        }
+    }
+}
+
+impl Texture for Scaled {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let c = self.texture.value(u, v, p);
+        let c = match self.channel {
+            Some(channel) => {
+                let x = [c.x, c.y, c.z][channel];
+                Color::new(x, x, x)
+            }
+            None => c,
+        };
+        c * self.factor
+    }
+}
+
+// the contents of a buffer or image a glTF file refers to: embedded in a data URI, or in a file
+// relative to the glTF file
+fn read_uri(dir: &Path, uri: &str) -> io::Result<Vec<u8>> {
+    if let Some(data) = uri.strip_prefix("data:") {
+        let (_, encoded) = data.split_once(";base64,").ok_or_else(|| invalid_data("glTF data URI isn't base64"))?;
+        return decode_base64(encoded);
+    }
+    if uri.contains("://") {
+        return Err(invalid_data(format!("glTF file refers to {uri}, which isn't a local file")));
+    }
+
+    // relative URIs escape characters such as spaces with % and their hex code
+    let mut path = Vec::with_capacity(uri.len());
+    let mut bytes = uri.bytes();
+    while let Some(b) = bytes.next() {
+        let escaped = if b == b'%' {
+            let hex = [bytes.next(), bytes.next()];
+            let hex: Option<Vec<u8>> = hex.into_iter().collect();
+            hex.and_then(|h| u8::from_str_radix(std::str::from_utf8(&h).ok()?, 16).ok())
+        } else {
+            None
+        };
+        path.push(escaped.unwrap_or(b));
+    }
+
+    let path = dir.join(String::from_utf8_lossy(&path).as_ref());
+    fs::read(&path).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+}
+
+fn decode_base64(encoded: &str) -> io::Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(encoded.len() * 3 / 4);
+    let (mut bits, mut count) = (0u32, 0);
+    for b in encoded.bytes().filter(|b| !b.is_ascii_whitespace() && *b != b'=') {
+        let value = match b {
+            b'A'..=b'Z' => b - b'A',
+            b'a'..=b'z' => b - b'a' + 26,
+            b'0'..=b'9' => b - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return Err(invalid_data("bad base64 in glTF data URI")),
+        };
+
+        bits = (bits << 6) | value as u32;
+        count += 6;
+        if count >= 8 {
+            count -= 8;
+            out.push((bits >> count) as u8);
+        }
+    }
+    Ok(out)
+}
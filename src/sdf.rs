@@ -0,0 +1,437 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::csg::{spans_between, Solid, Span};
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+// how close to the surface counts as hitting it, and how far apart normals are sampled
+const SURFACE_DISTANCE: f64 = 1e-4;
+const MAX_STEPS: usize = 512;
+
+/// A shape given by its signed distance function: the distance from any point to the nearest point
+/// on its surface, negative inside. Distances may be underestimated, which only costs more steps, but
+/// never overestimated, or rays step through the surface.
+pub trait Sdf: Send + Sync {
+    /// The signed distance from `p` to the surface.
+    fn distance(&self, p: Point3) -> f64;
+
+    /// A box the whole shape fits inside.
+    fn bounding_box(&self) -> Aabb;
+}
+
+/// A sphere.
+pub struct SdfSphere {
+    center: Point3,
+    radius: f64,
+}
+
+impl SdfSphere {
+    pub fn new(center: Point3, radius: f64) -> Self {
+        Self {
+            center,
+            radius,
+        }
+    }
+}
+
+impl Sdf for SdfSphere {
+    fn distance(&self, p: Point3) -> f64 {
+        (p - self.center).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - r, self.center + r)
+    }
+}
+
+/// A box reaching `half_extents` from its centre along each axis.
+pub struct SdfBox {
+    center: Point3,
+    half_extents: Vec3,
+}
+
+impl SdfBox {
+    pub fn new(center: Point3, half_extents: Vec3) -> Self {
+        Self {
+            center,
+            half_extents,
+        }
+    }
+}
+
+impl Sdf for SdfBox {
+    fn distance(&self, p: Point3) -> f64 {
+        box_distance(abs(p - self.center) - self.half_extents)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.center - self.half_extents, self.center + self.half_extents)
+    }
+}
+
+/// A box reaching `half_extents` from its centre along each axis, with its edges and corners rounded
+/// off to `radius`.
+pub struct SdfRoundedBox {
+    center: Point3,
+    half_extents: Vec3,
+    radius: f64,
+}
+
+impl SdfRoundedBox {
+    pub fn new(center: Point3, half_extents: Vec3, radius: f64) -> Self {
+        Self {
+            center,
+            half_extents,
+            radius,
+        }
+    }
+}
+
+impl Sdf for SdfRoundedBox {
+    fn distance(&self, p: Point3) -> f64 {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        box_distance(abs(p - self.center) - self.half_extents + r) - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::new(self.center - self.half_extents, self.center + self.half_extents)
+    }
+}
+
+/// A torus lying flat, around the y axis through its centre: a tube of radius `minor` following a
+/// circle of radius `major`.
+pub struct SdfTorus {
+    center: Point3,
+    major: f64,
+    minor: f64,
+}
+
+impl SdfTorus {
+    pub fn new(center: Point3, major: f64, minor: f64) -> Self {
+        Self {
+            center,
+            major,
+            minor,
+        }
+    }
+}
+
+impl Sdf for SdfTorus {
+    fn distance(&self, p: Point3) -> f64 {
+        let p = p - self.center;
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - self.major;
+        (ring * ring + p.y * p.y).sqrt() - self.minor
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let outer = self.major + self.minor;
+        let extent = Vec3::new(outer, self.minor, outer);
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+}
+
+/// A capsule: a line segment from `a` to `b` thickened to `radius`.
+pub struct SdfCapsule {
+    a: Point3,
+    b: Point3,
+    radius: f64,
+}
+
+impl SdfCapsule {
+    pub fn new(a: Point3, b: Point3, radius: f64) -> Self {
+        Self {
+            a,
+            b,
+            radius,
+        }
+    }
+}
+
+impl Sdf for SdfCapsule {
+    fn distance(&self, p: Point3) -> f64 {
+        let (pa, ba) = (p - self.a, self.b - self.a);
+        let h = if ba.near_zero() {0.0} else {(pa.dot(&ba) / ba.dot(&ba)).clamp(0.0, 1.0)};
+        (pa - h * ba).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.a - r, self.a + r).union(&Aabb::new(self.b - r, self.b + r))
+    }
+}
+
+/// Two shapes joined with a fillet of about `k` where they meet, or a sharp crease if `k` is 0.
+pub struct SmoothUnion {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl SmoothUnion {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self {
+            a,
+            b,
+            k,
+        }
+    }
+}
+
+impl Sdf for SmoothUnion {
+    fn distance(&self, p: Point3) -> f64 {
+        let (a, b) = (self.a.distance(p), self.b.distance(p));
+        if self.k <= 0.0 {
+            return a.min(b);
+        }
+
+        // the polynomial smooth minimum, which only differs from the minimum within `k` of both
+        let h = (0.5 + 0.5 * (b - a) / self.k).clamp(0.0, 1.0);
+        b + (a - b) * h - self.k * h * (1.0 - h)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        // the fillet only fills in where both shapes are within `k`, so inside the boxes grown by `k`
+        let k = self.k.max(0.0);
+        let k = Vec3::new(k, k, k);
+        let aabb = self.a.bounding_box().union(&self.b.bounding_box());
+        Aabb::new(aabb.min - k, aabb.max + k)
+    }
+}
+
+/// Shape `a` with shape `b` carved out of it, rounding the cut edges by about `k`, or leaving them
+/// sharp if `k` is 0.
+pub struct Subtraction {
+    a: Arc<dyn Sdf>,
+    b: Arc<dyn Sdf>,
+    k: f64,
+}
+
+impl Subtraction {
+    pub fn new(a: Arc<dyn Sdf>, b: Arc<dyn Sdf>, k: f64) -> Self {
+        Self {
+            a,
+            b,
+            k,
+        }
+    }
+}
+
+impl Sdf for Subtraction {
+    fn distance(&self, p: Point3) -> f64 {
+        let (a, b) = (self.a.distance(p), -self.b.distance(p));
+        if self.k <= 0.0 {
+            return a.max(b);
+        }
+
+        // the smooth maximum, mirroring `SmoothUnion`
+        let h = (0.5 - 0.5 * (b - a) / self.k).clamp(0.0, 1.0);
+        b + (a - b) * h + self.k * h * (1.0 - h)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.a.bounding_box()
+    }
+}
+
+/// `count` copies of a shape along each axis, `spacing` apart, starting from the shape itself. The
+/// shape should fit inside one `spacing` cell, or the copies cut into each other.
+pub struct Repetition {
+    sdf: Arc<dyn Sdf>,
+    spacing: Vec3,
+    count: [u32; 3],
+    // the middle of the original shape, which copies are counted from
+    origin: Point3,
+}
+
+impl Repetition {
+    pub fn new(sdf: Arc<dyn Sdf>, spacing: Vec3, count: [u32; 3]) -> Self {
+        let aabb = sdf.bounding_box();
+
+        Self {
+            sdf,
+            spacing,
+            count: count.map(|c| c.max(1)),
+            origin: (aabb.min + aabb.max) / 2.0,
+        }
+    }
+}
+
+impl Sdf for Repetition {
+    fn distance(&self, p: Point3) -> f64 {
+        // move `p` back into the cell of the original shape from the nearest copy's cell
+        let cell = |p: f64, origin: f64, spacing: f64, count: u32| {
+            if spacing == 0.0 {
+                return p;
+            }
+            p - spacing * ((p - origin) / spacing).round().clamp(0.0, (count - 1) as f64)
+        };
+        let ([cx, cy, cz], o, s) = (self.count, self.origin, self.spacing);
+        let q = Point3::new(cell(p.x, o.x, s.x, cx), cell(p.y, o.y, s.y, cy), cell(p.z, o.z, s.z, cz));
+        self.sdf.distance(q)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let aabb = self.sdf.bounding_box();
+        let [cx, cy, cz] = self.count;
+        let offset = Vec3::new(self.spacing.x * (cx - 1) as f64, self.spacing.y * (cy - 1) as f64, self.spacing.z * (cz - 1) as f64);
+        aabb.union(&Aabb::new(aabb.min + offset, aabb.max + offset))
+    }
+}
+
+/// A shape twisted around the y axis by `rate` radians per unit of height.
+pub struct Twist {
+    sdf: Arc<dyn Sdf>,
+    rate: f64,
+    // the furthest the shape reaches from the y axis, and how much the twist can stretch distances
+    reach: f64,
+    stretch: f64,
+}
+
+impl Twist {
+    pub fn new(sdf: Arc<dyn Sdf>, rate: f64) -> Self {
+        let aabb = sdf.bounding_box();
+        let reach = [aabb.min.x, aabb.max.x].iter()
+            .flat_map(|x| [aabb.min.z, aabb.max.z].map(|z| (x * x + z * z).sqrt()))
+            .fold(0.0, f64::max);
+
+        Self {
+            sdf,
+            rate,
+            reach,
+            stretch: (1.0 + (rate * reach).powi(2)).sqrt(),
+        }
+    }
+}
+
+impl Sdf for Twist {
+    fn distance(&self, p: Point3) -> f64 {
+        let (sin, cos) = (-self.rate * p.y).sin_cos();
+        let q = Point3::new(cos * p.x - sin * p.z, p.y, sin * p.x + cos * p.z);
+
+        // twisting stretches space, so the shape's distance can overestimate the real one by as much
+        self.sdf.distance(q) / self.stretch
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        let aabb = self.sdf.bounding_box();
+        Aabb::new(Point3::new(-self.reach, aabb.min.y, -self.reach), Point3::new(self.reach, aabb.max.y, self.reach))
+    }
+}
+
+/// A hittable made from a signed distance function, found by sphere tracing ("Sphere Tracing",
+/// Hart 1996): stepping along the ray by the distance to the surface, which can't skip over it,
+/// within the shape's bounding box. Normals come from the gradient of the distance.
+pub struct SdfHittable {
+    sdf: Arc<dyn Sdf>,
+    aabb: Aabb,
+    mat: Arc<dyn Material>,
+}
+
+impl SdfHittable {
+    pub fn new(sdf: Arc<dyn Sdf>, mat: Arc<dyn Material>) -> Self {
+        // a little room so the surface is never right on the box
+        let margin = Vec3::new(1,1,1) * (4.0 * SURFACE_DISTANCE);
+        let aabb = sdf.bounding_box();
+
+        Self {
+            sdf,
+            aabb: Aabb::new(aabb.min - margin, aabb.max + margin),
+            mat,
+        }
+    }
+
+    fn normal(&self, p: Point3) -> Vec3 {
+        // the tetrahedron technique, sampling the distance at four corners of a tetrahedron around `p`
+        let h = SURFACE_DISTANCE;
+        let corners = [Vec3::new(1, -1, -1), Vec3::new(-1, -1, 1), Vec3::new(-1, 1, -1), Vec3::new(1, 1, 1)];
+        let gradient = corners.iter().fold(Vec3::new(0,0,0), |g, &c| g + c * self.sdf.distance(p + c * h));
+        if gradient.near_zero() {Vec3::new(0,1,0)} else {gradient.unit_vector()}
+    }
+}
+
+impl Hittable for SdfHittable {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (mut t, t1) = self.aabb.hit(r, ray_t)?;
+        let length = r.dir.length();
+
+        // march on the side of the surface the ray starts on. A ray starting on the surface, having
+        // just scattered from it, is on the side it's heading into, and doesn't hit until it has moved
+        // clear of the surface
+        let start = self.sdf.distance(r.at(t));
+        let near_surface = start.abs() < 2.0 * SURFACE_DISTANCE;
+        let side = if near_surface {
+            if self.normal(r.at(t)).dot(&r.dir) < 0.0 {-1.0} else {1.0}
+        } else {
+            start.signum()
+        };
+        let mut clear = !near_surface;
+
+        for _ in 0..MAX_STEPS {
+            let d = side * self.sdf.distance(r.at(t));
+            if d < SURFACE_DISTANCE && clear {
+                let p = r.at(t);
+                let outward_normal = self.normal(p);
+                let mut rec = HitRecord {
+                    p,
+                    normal: outward_normal,
+                    geometric_normal: outward_normal,
+                    dpdu: Vec3::new(0,0,0),
+                    dpdv: Vec3::new(0,0,0),
+                    mat: Arc::clone(&self.mat),
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    barycentric: None,
+                    color: None,
+                    front_face: false,
+                    object_id: 0,
+                    material_id: 0,
+                };
+                rec.set_face_normal(r, &outward_normal);
+                return Some(rec);
+            }
+            if d > 2.0 * SURFACE_DISTANCE {
+                clear = true;
+            }
+
+            t += d.max(SURFACE_DISTANCE) / length;
+            if t >= t1 {
+                return None;
+            }
+        }
+
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.aabb)
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+impl Solid for SdfHittable {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        // the surface is all within the box, so there's nothing to find beyond it either way
+        let Some((t0, t1)) = self.aabb.hit(r, Interval::new(f64::NEG_INFINITY, f64::INFINITY)) else {return Vec::new()};
+        spans_between(self, r, Interval::new(t0, t1))
+    }
+}
+
+fn abs(v: Vec3) -> Vec3 {
+    Vec3::new(v.x.abs(), v.y.abs(), v.z.abs())
+}
+
+// the distance to a box centred on the origin from a point `q` past its extents
+fn box_distance(q: Vec3) -> f64 {
+    let outside = Vec3::new(q.x.max(0.0), q.y.max(0.0), q.z.max(0.0)).length();
+    let inside = q.x.max(q.y).max(q.z).min(0.0);
+    outside + inside
+}
@@ -0,0 +1,178 @@
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::environment::Environment;
+use crate::util::random;
+use crate::vec3::Vec3;
+
+// the sun's angular radius seen from the earth, about a quarter of a degree
+const SUN_ANGULAR_RADIUS: f64 = 0.004654;
+// luminance of the sun's disk outside the atmosphere, in kcd/m², the same units as the sky model
+const SUN_LUMINANCE: f64 = 1.6e6;
+// converts kcd/m² into the renderer's units, an exposure at which a white surface in the midday sun
+// comes out around 1
+const LUMINANCE_SCALE: f64 = 0.04;
+// how often light sampling picks the sun rather than the sky, while the sun is up
+const SUN_SAMPLE_PROBABILITY: f64 = 0.5;
+
+/// The analytic daylight model from "A Practical Analytic Model for Daylight" (Preetham, Shirley and
+/// Smits 1999), with the sun as a disk of the right size and brightness. +y is up.
+///
+/// `turbidity` describes the haziness of the atmosphere, from about 2 for a very clear sky to 10 for
+/// a hazy one. Directions below the horizon see the colour of the sky at the horizon.
+pub struct PreethamSky {
+    sun_direction: Vec3,
+    intensity: f64,
+    zenith: [f64; 3],
+    perez: [[f64; 5]; 3],
+    sun_radiance: Color,
+    cos_sun_radius: f64,
+}
+
+impl PreethamSky {
+    pub fn new(sun_direction: Vec3, turbidity: f64, intensity: f64) -> Self {
+        let sun_direction = sun_direction.unit_vector();
+        let t = turbidity;
+        let theta_s = sun_direction.y.clamp(-1.0, 1.0).acos().min(PI / 2.0);
+
+        let chi = (4.0 / 9.0 - t / 120.0) * (PI - 2.0 * theta_s);
+        let zenith_luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+        let (theta2, theta3) = (theta_s * theta_s, theta_s * theta_s * theta_s);
+        let zenith_x = t * t * (0.00166 * theta3 - 0.00375 * theta2 + 0.00209 * theta_s)
+            + t * (-0.02903 * theta3 + 0.06377 * theta2 - 0.03202 * theta_s + 0.00394)
+            + (0.11693 * theta3 - 0.21196 * theta2 + 0.06052 * theta_s + 0.25886);
+        let zenith_y = t * t * (0.00275 * theta3 - 0.00610 * theta2 + 0.00317 * theta_s)
+            + t * (-0.04214 * theta3 + 0.08970 * theta2 - 0.04153 * theta_s + 0.00516)
+            + (0.15346 * theta3 - 0.26756 * theta2 + 0.06670 * theta_s + 0.26688);
+
+        let perez = [
+            [0.1787 * t - 1.4630, -0.3554 * t + 0.4275, -0.0227 * t + 5.3251, 0.1206 * t - 2.5771, -0.0670 * t + 0.3703],
+            [-0.0193 * t - 0.2592, -0.0665 * t + 0.0008, -0.0004 * t + 0.2125, -0.0641 * t - 0.8989, -0.0033 * t + 0.0452],
+            [-0.0167 * t - 0.2608, -0.0950 * t + 0.0092, -0.0079 * t + 0.2102, -0.0441 * t - 1.6537, -0.0109 * t + 0.0529],
+        ];
+
+        Self {
+            sun_direction,
+            intensity,
+            zenith: [zenith_luminance.max(0.0), zenith_x, zenith_y],
+            perez,
+            sun_radiance: Self::sun_radiance(theta_s, turbidity),
+            cos_sun_radius: SUN_ANGULAR_RADIUS.cos(),
+        }
+    }
+
+    /// A sun direction `elevation` degrees above the horizon, and `azimuth` degrees clockwise from -z
+    /// looking down.
+    pub fn sun_direction(elevation: f64, azimuth: f64) -> Vec3 {
+        let (elevation, azimuth) = (elevation.to_radians(), azimuth.to_radians());
+        Vec3::new(elevation.cos() * azimuth.sin(), elevation.sin(), -elevation.cos() * azimuth.cos())
+    }
+
+    // the sun's disk after the light has passed through the atmosphere, using the paper's transmittance
+    // for Rayleigh and aerosol scattering evaluated at a representative wavelength for each of r, g, b
+    fn sun_radiance(theta_s: f64, turbidity: f64) -> Color {
+        if theta_s >= PI / 2.0 {
+            return Color::new(0,0,0);
+        }
+
+        // relative optical mass of the air the light passes through
+        let m = 1.0 / (theta_s.cos() + 0.15 * (93.885 - theta_s.to_degrees()).powf(-1.253));
+        let beta = 0.04608 * turbidity - 0.04586;
+
+        let transmittance = |lambda: f64| {
+            let rayleigh = (-0.008735 * lambda.powf(-4.08) * m).exp();
+            let aerosol = (-beta * lambda.powf(-1.3) * m).exp();
+            rayleigh * aerosol
+        };
+
+        LUMINANCE_SCALE * SUN_LUMINANCE * Color::new(transmittance(0.680), transmittance(0.550), transmittance(0.440))
+    }
+
+    fn perez(&self, channel: usize, cos_theta: f64, gamma: f64) -> f64 {
+        let [a, b, c, d, e] = self.perez[channel];
+        (1.0 + a * (b / cos_theta).exp()) * (1.0 + c * (d * gamma).exp() + e * gamma.cos() * gamma.cos())
+    }
+
+    fn sky_radiance(&self, direction: Vec3) -> Color {
+        // below the horizon the model breaks down, so hold the horizon's colour
+        let cos_theta = direction.y.max(0.01);
+        let cos_gamma = direction.dot(&self.sun_direction).clamp(-1.0, 1.0);
+        let gamma = cos_gamma.acos();
+        let theta_s = self.sun_direction.y.clamp(-1.0, 1.0).acos().min(PI / 2.0);
+
+        let [luminance, x, y] = [0, 1, 2].map(|channel| {
+            self.zenith[channel] * self.perez(channel, cos_theta, gamma) / self.perez(channel, 1.0, theta_s)
+        });
+
+        LUMINANCE_SCALE * xyy_to_rgb(x, y, luminance)
+    }
+
+    fn in_sun(&self, direction: Vec3) -> bool {
+        direction.dot(&self.sun_direction) >= self.cos_sun_radius
+    }
+
+    fn sun_visible(&self) -> bool {
+        self.sun_direction.y > 0.0
+    }
+
+    fn sun_pdf(&self) -> f64 {
+        1.0 / (2.0 * PI * (1.0 - self.cos_sun_radius))
+    }
+}
+
+impl Environment for PreethamSky {
+    fn radiance(&self, direction: Vec3) -> Color {
+        let direction = direction.unit_vector();
+        let mut radiance = self.sky_radiance(direction);
+
+        if self.sun_visible() && self.in_sun(direction) {
+            radiance += self.sun_radiance;
+        }
+
+        self.intensity * radiance
+    }
+
+    fn sample(&self) -> (Vec3, Color, f64) {
+        let direction = if self.sun_visible() && random::<f64>() < SUN_SAMPLE_PROBABILITY {
+            // uniformly within the cone the sun's disk covers
+            let cos_theta = 1.0 - random::<f64>() * (1.0 - self.cos_sun_radius);
+            let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+            let phi = 2.0 * PI * random::<f64>();
+            let (u, v) = self.sun_direction.orthonormal_basis();
+
+            sin_theta * phi.cos() * u + sin_theta * phi.sin() * v + cos_theta * self.sun_direction
+        } else {
+            Vec3::random_unit_vector()
+        };
+
+        (direction, self.radiance(direction), self.pdf(direction))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let uniform = 1.0 / (4.0 * PI);
+        if !self.sun_visible() {
+            return uniform;
+        }
+
+        let sun = if self.in_sun(direction.unit_vector()) {self.sun_pdf()} else {0.0};
+        SUN_SAMPLE_PROBABILITY * sun + (1.0 - SUN_SAMPLE_PROBABILITY) * uniform
+    }
+}
+
+// CIE xyY to linear sRGB
+fn xyy_to_rgb(x: f64, y: f64, luminance: f64) -> Color {
+    if y <= 0.0 {
+        return Color::new(0,0,0);
+    }
+
+    let big_x = x / y * luminance;
+    let big_z = (1.0 - x - y) / y * luminance;
+
+    // saturated colours can fall outside sRGB, so clip them
+    Color::new(
+        (3.2406 * big_x - 1.5372 * luminance - 0.4986 * big_z).max(0.0),
+        (-0.9689 * big_x + 1.8758 * luminance + 0.0415 * big_z).max(0.0),
+        (0.0557 * big_x - 0.2040 * luminance + 1.0570 * big_z).max(0.0),
+    )
+}
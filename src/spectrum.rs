@@ -0,0 +1,200 @@
+use std::sync::LazyLock;
+
+use crate::color::Color;
+use crate::util::random;
+
+// the range wavelengths are sampled from, in nanometres
+const LAMBDA_MIN: f64 = 380.0;
+const LAMBDA_MAX: f64 = 720.0;
+
+// how many wavelengths each path carries, one per channel of a `Color`
+const WAVELENGTHS: usize = 3;
+
+// the wavelength dispersive materials use when rendering in RGB, the sodium D line that glass catalogues
+// quote refractive indices at
+pub(crate) const RGB_WAVELENGTH: f64 = 587.6;
+
+type Matrix = [[f64; 3]; 3];
+
+const XYZ_TO_SRGB: Matrix = [
+    [3.2406, -1.5372, -0.4986],
+    [-0.9689, 1.8758, 0.0415],
+    [0.0557, -0.2040, 1.0570],
+];
+
+/// The wavelengths a path carries when rendering spectrally ("Hero Wavelength Spectral Sampling",
+/// Wilkie et al. 2014): one chosen at random and the rest evenly spaced from it, so that together they
+/// cover the visible spectrum. The radiance along the path at each wavelength is kept in one channel
+/// of a [`Color`].
+///
+/// Rendering in RGB uses the same type with no wavelengths, where colours pass through unchanged.
+pub(crate) struct Wavelengths {
+    lambda: Option<[f64; WAVELENGTHS]>,
+    // from linear RGB to the value of the upsampled spectrum at each wavelength
+    upsample: Matrix,
+    // from the values at each wavelength to linear RGB, including the sampling probability
+    to_rgb: Matrix,
+    terminated: bool,
+}
+
+impl Wavelengths {
+    pub(crate) fn rgb() -> Self {
+        let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+        Self {
+            lambda: None,
+            upsample: identity,
+            to_rgb: identity,
+            terminated: false,
+        }
+    }
+
+    pub(crate) fn sample() -> Self {
+        let tables = &*TABLES;
+        let hero = random::<f64>();
+        let lambda: [f64; WAVELENGTHS] = std::array::from_fn(|i| {
+            let u = (hero + i as f64 / WAVELENGTHS as f64).fract();
+            LAMBDA_MIN + u * (LAMBDA_MAX - LAMBDA_MIN)
+        });
+
+        let mut upsample = [[0.0; 3]; 3];
+        let mut to_rgb = [[0.0; 3]; 3];
+        for (i, l) in lambda.iter().enumerate() {
+            let basis = basis(*l);
+            upsample[i] = std::array::from_fn(|c| (0..3).map(|k| basis[k] * tables.basis_inverse[k][c]).sum());
+
+            // the average over the wavelengths of radiance times the matching functions, divided by
+            // the uniform probability density of picking each wavelength
+            let rgb = mul(&XYZ_TO_SRGB, cmf(*l));
+            let weight = (LAMBDA_MAX - LAMBDA_MIN) / (WAVELENGTHS as f64 * tables.y_integral);
+            for c in 0..3 {
+                to_rgb[c][i] = weight * rgb[c] / tables.white[c];
+            }
+        }
+
+        Self {
+            lambda: Some(lambda),
+            upsample,
+            to_rgb,
+            terminated: false,
+        }
+    }
+
+    /// The wavelength that decides the direction of dispersive scattering, if rendering spectrally.
+    pub(crate) fn hero(&self) -> Option<f64> {
+        self.lambda.map(|lambda| lambda[0])
+    }
+
+    /// A reflectance or radiance given in linear RGB, at each of the wavelengths.
+    pub(crate) fn upsample(&self, rgb: Color) -> Color {
+        if self.lambda.is_none() {
+            return rgb;
+        }
+
+        // spectra of very saturated colours can dip below zero, which no light or surface can do
+        let values = mul(&self.upsample, [rgb.x, rgb.y, rgb.z]);
+        Color::new(values[0].max(0.0), values[1].max(0.0), values[2].max(0.0))
+    }
+
+    /// Linear RGB from the radiance at each of the wavelengths.
+    pub(crate) fn to_rgb(&self, values: Color) -> Color {
+        let rgb = mul(&self.to_rgb, [values.x, values.y, values.z]);
+        Color::new(rgb[0], rgb[1], rgb[2])
+    }
+
+    /// Once a path has scattered in a direction that depends on wavelength, it only carries light of
+    /// the hero wavelength. Returns what to multiply the path's throughput by: the hero now stands in
+    /// for all of the wavelengths.
+    pub(crate) fn terminate_secondary(&mut self) -> Color {
+        if self.lambda.is_none() || self.terminated {
+            return Color::new(1,1,1);
+        }
+
+        self.terminated = true;
+        Color::new(WAVELENGTHS as f64, 0.0, 0.0)
+    }
+}
+
+// built once from the matching functions
+struct Tables {
+    y_integral: f64,
+    // the RGB of a constant spectrum of 1, which is divided out so that it comes out white
+    white: [f64; 3],
+    basis_inverse: Matrix,
+}
+
+static TABLES: LazyLock<Tables> = LazyLock::new(|| {
+    let steps = (LAMBDA_MAX - LAMBDA_MIN) as usize;
+    let integrate = |f: &dyn Fn(f64) -> [f64; 3]| {
+        let mut sum = [0.0; 3];
+        for step in 0..steps {
+            let l = LAMBDA_MIN + step as f64 + 0.5;
+            let (xyz, value) = (cmf(l), f(l));
+            for c in 0..3 {
+                sum[c] += xyz[c] * value[c];
+            }
+        }
+        sum
+    };
+
+    let y_integral = integrate(&|_| [0.0, 1.0, 0.0])[1];
+    let rgb_of = |f: &dyn Fn(f64) -> f64| {
+        let xyz = integrate(&|l| [f(l); 3]);
+        mul(&XYZ_TO_SRGB, xyz.map(|v| v / y_integral))
+    };
+    let white = rgb_of(&|_| 1.0);
+
+    // the white balanced RGB of each basis spectrum, as the columns of a matrix
+    let columns: [[f64; 3]; 3] = std::array::from_fn(|k| rgb_of(&|l| basis(l)[k]));
+    let basis_matrix = std::array::from_fn(|c| std::array::from_fn(|k| columns[k][c] / white[c]));
+
+    Tables {
+        y_integral,
+        white,
+        basis_inverse: invert(&basis_matrix),
+    }
+});
+
+// three smooth spectra covering the blue, green and red ends of the spectrum, which add up to 1
+// everywhere so that white upsamples to a constant spectrum. An RGB colour is upsampled to the mix of
+// them with that RGB colour
+fn basis(lambda: f64) -> [f64; 3] {
+    let blue = 1.0 - smoothstep(480.0, 510.0, lambda);
+    let red = smoothstep(570.0, 600.0, lambda);
+    [red, 1.0 - red - blue, blue]
+}
+
+fn smoothstep(edge0: f64, edge1: f64, x: f64) -> f64 {
+    let t = ((x - edge0) / (edge1 - edge0)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// the CIE 1931 colour matching functions, from the piecewise Gaussian fit of "Simple Analytic
+// Approximations to the CIE XYZ Color Matching Functions" (Wyman, Sloan and Shirley 2013)
+fn cmf(lambda: f64) -> [f64; 3] {
+    let g = |mu: f64, sigma_below: f64, sigma_above: f64| {
+        let sigma = if lambda < mu {sigma_below} else {sigma_above};
+        let t = (lambda - mu) / sigma;
+        (-0.5 * t * t).exp()
+    };
+
+    let x = 1.056 * g(599.8, 37.9, 31.0) + 0.362 * g(442.0, 16.0, 26.7) - 0.065 * g(501.1, 20.4, 26.2);
+    let y = 0.821 * g(568.8, 46.9, 40.5) + 0.286 * g(530.9, 16.3, 31.1);
+    let z = 1.217 * g(437.0, 11.8, 36.0) + 0.681 * g(459.0, 26.0, 13.8);
+    [x, y, z]
+}
+
+fn mul(m: &Matrix, v: [f64; 3]) -> [f64; 3] {
+    m.map(|row| row[0] * v[0] + row[1] * v[1] + row[2] * v[2])
+}
+
+fn invert(m: &Matrix) -> Matrix {
+    let cofactor = |r: usize, c: usize| {
+        let (r0, r1) = ((r + 1) % 3, (r + 2) % 3);
+        let (c0, c1) = ((c + 1) % 3, (c + 2) % 3);
+        m[r0][c0] * m[r1][c1] - m[r0][c1] * m[r1][c0]
+    };
+
+    let det = m[0][0] * cofactor(0, 0) + m[0][1] * cofactor(0, 1) + m[0][2] * cofactor(0, 2);
+    std::array::from_fn(|r| std::array::from_fn(|c| cofactor(c, r) / det))
+}
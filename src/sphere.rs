@@ -1,15 +1,20 @@
+use std::f64::consts::PI;
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::csg::{Solid, Span};
 use crate::hittable::*;
 use crate::interval::Interval;
 use crate::material::Material;
 use crate::vec3::*;
 use crate::ray::*;
 
+/// A sphere. A negative radius flips the normals, making a hollow bubble when placed inside a
+/// `Dielectric` sphere.
 pub struct Sphere {
     center: Point3,
     radius: f64,
-    mat: Arc<dyn Material>
+    mat: Arc<dyn Material>,
 }
 
 impl Sphere {
@@ -20,10 +25,33 @@ impl Sphere {
             mat,
         }
     }
+
+    // texture coordinates of a point on the unit sphere, u going around from -x and v up from -y
+    fn uv(p: Point3) -> (f64, f64) {
+        let theta = (-p.y).clamp(-1.0, 1.0).acos();
+        let phi = (-p.z).atan2(p.x) + PI;
+
+        (phi / (2.0 * PI), theta / PI)
+    }
+
+    // how a point on the sphere with outward unit normal `n` moves with its texture coordinates
+    fn tangents(&self, n: Vec3) -> (Vec3, Vec3) {
+        let r = self.radius.abs();
+        let s = (n.x * n.x + n.z * n.z).sqrt();
+        if s < 1e-12 {
+            // the texture coordinates pinch together at the poles, so there's no direction to give
+            return (Vec3::new(0,0,0), Vec3::new(0,0,0));
+        }
+
+        let dpdu = 2.0 * PI * r * Vec3::new(n.z, 0.0, -n.x);
+        let dpdv = PI * r * Vec3::new(-n.x * n.y / s, s, -n.y * n.z / s);
+        (dpdu, dpdv)
+    }
 }
 
-impl Hittable for Sphere {
-    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+impl Sphere {
+    // where the line along `r` enters and leaves the ball, in order
+    fn roots(&self, r: &Ray) -> Option<(f64, f64)> {
         let oc = r.orig - self.center;
         let a = r.dir.length_squared();
         let half_b = oc.dot(&r.dir);
@@ -33,26 +61,76 @@ impl Hittable for Sphere {
         if discriminant < 0.0 {return None;}
         let sqrtd = discriminant.sqrt();
 
-        let mut root = (-half_b - sqrtd) / a;
-        if !ray_t.surrounds(root) {
-            root = (-half_b + sqrtd) / a;
-            if !ray_t.surrounds(root) {
-                return None;
-            }
-        }
+        Some(((-half_b - sqrtd) / a, (-half_b + sqrtd) / a))
+    }
 
-        let outward_normal = (r.at(root) - self.center) / self.radius;
+    // the hit at `t` along `r`, with `outward_normal` the unit normal the surface is taken to face
+    fn record(&self, r: &Ray, t: f64, outward_normal: Vec3) -> HitRecord {
+        let p = r.at(t);
+        let n = (p - self.center) / self.radius.abs();
+        let (u, v) = Self::uv(n);
+        let (dpdu, dpdv) = self.tangents(n);
 
         let mut rec = HitRecord {
-            p: r.at(root),
+            p,
             normal: outward_normal,
+            geometric_normal: outward_normal,
+            dpdu,
+            dpdv,
             mat: Arc::clone(&self.mat),
-            t: root,
+            t,
+            u,
+            v,
+            barycentric: None,
+            color: None,
             front_face: false,
+            object_id: 0,
+            material_id: 0,
         };
-        
+
         rec.set_face_normal(r, &outward_normal);
+        rec
+    }
+}
 
-        return Some(rec);
+impl Hittable for Sphere {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (near, far) = self.roots(r)?;
+
+        let mut root = near;
+        if !ray_t.surrounds(root) {
+            root = far;
+            if !ray_t.surrounds(root) {
+                return None;
+            }
+        }
+
+        let outward_normal = (r.at(root) - self.center) / self.radius;
+        Some(self.record(r, root, outward_normal))
     }
-}
\ No newline at end of file
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius.abs();
+        Some(Aabb::new(self.center - Vec3::new(r, r, r), self.center + Vec3::new(r, r, r)))
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+/// As a solid, a sphere is always the ball inside it, whatever the sign of its radius.
+impl Solid for Sphere {
+    fn spans(&self, r: &Ray) -> Vec<Span> {
+        let Some((near, far)) = self.roots(r) else {return Vec::new()};
+        if near >= far {
+            return Vec::new();
+        }
+
+        let outward = |t: f64| (r.at(t) - self.center) / self.radius.abs();
+        vec![Span {
+            enter: self.record(r, near, outward(near)),
+            exit: self.record(r, far, outward(far)),
+        }]
+    }
+}
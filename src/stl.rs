@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::mesh::{invalid_data, Mesh};
+use crate::vec3::Point3;
+
+impl Mesh {
+    /// Loads an STL file, in either its text or binary form, as exported by CAD programs.
+    ///
+    /// STL lists each triangle's corners separately, so corners at exactly the same place are merged
+    /// into shared vertices, joining the triangles up. The facet normals in the file are ignored in
+    /// favour of the order of the corners, which they're meant to agree with.
+    pub fn load_stl(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+
+        // binary files have a header which is free to start with "solid" too, so they're told apart
+        // by their size matching the number of triangles they say they have, or by not being text
+        let binary_size = bytes.get(80..84).map(|n| 84 + 50 * u32::from_le_bytes(n.try_into().unwrap()) as usize);
+        let text = bytes.iter().take(512).all(|b| b.is_ascii_graphic() || b.is_ascii_whitespace());
+        let corners = if bytes.trim_ascii_start().starts_with(b"solid") && binary_size != Some(bytes.len()) && text {
+            read_text(&bytes)?
+        } else {
+            read_binary(&bytes)?
+        };
+
+        let mut mesh = Mesh::default();
+        let mut shared = HashMap::new();
+        for triangle in corners.chunks_exact(3) {
+            let triangle = [triangle[0], triangle[1], triangle[2]].map(|p: Point3| {
+                // adding zero makes -0 and 0 the same
+                let key = [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()];
+                *shared.entry(key).or_insert_with(|| {
+                    mesh.positions.push(p);
+                    mesh.positions.len() - 1
+                })
+            });
+            mesh.triangles.push(triangle);
+        }
+
+        Ok(mesh)
+    }
+}
+
+// the corners of every triangle in a binary file, three at a time
+fn read_binary(bytes: &[u8]) -> io::Result<Vec<Point3>> {
+    let count = bytes.get(80..84).ok_or_else(|| invalid_data("truncated STL header"))?;
+    let count = u32::from_le_bytes(count.try_into().unwrap()) as usize;
+    let data = &bytes[84..];
+    if data.len() < 50 * count {
+        return Err(invalid_data(format!("truncated STL data: {count} triangles need {} bytes but there are {}", 50 * count, data.len())));
+    }
+
+    let float = |b: &[u8], i: usize| f32::from_le_bytes([b[4 * i], b[4 * i + 1], b[4 * i + 2], b[4 * i + 3]]) as f64;
+    // each triangle is a normal, three corners and two bytes of attributes
+    Ok(data.chunks_exact(50)
+        .take(count)
+        .flat_map(|t| (1..4).map(move |c| Point3::new(float(t, 3 * c), float(t, 3 * c + 1), float(t, 3 * c + 2))))
+        .collect())
+}
+
+fn read_text(bytes: &[u8]) -> io::Result<Vec<Point3>> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut words = text.split_whitespace();
+    let mut corners = Vec::new();
+    let mut in_facet = 0;
+
+    let number = |words: &mut std::str::SplitWhitespace| -> io::Result<f64> {
+        let word = words.next().ok_or_else(|| invalid_data("truncated STL file"))?;
+        word.parse().map_err(|_| invalid_data(format!("bad number in STL file: {word}")))
+    };
+
+    while let Some(word) = words.next() {
+        match word {
+            "vertex" => {
+                corners.push(Point3::new(number(&mut words)?, number(&mut words)?, number(&mut words)?));
+                in_facet += 1;
+            }
+            "endfacet" => {
+                if in_facet != 3 {
+                    return Err(invalid_data(format!("STL facet has {in_facet} corners rather than 3")));
+                }
+                in_facet = 0;
+            }
+            "endsolid" => {
+                if in_facet != 0 {
+                    return Err(invalid_data("truncated STL facet"));
+                }
+                return Ok(corners);
+            }
+            _ => {}
+        }
+    }
+
+    Err(invalid_data("truncated STL file: no endsolid"))
+}
@@ -0,0 +1,334 @@
+use std::collections::HashMap;
+use std::f64::consts::PI;
+
+use crate::color::Color;
+use crate::mesh::Mesh;
+use crate::vec3::{Point3, Vec3};
+
+/// The scheme [`Mesh::subdivide`] smooths a mesh by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Subdivision {
+    /// Loop's scheme for triangle meshes, which splits every triangle into four each level. Quads are
+    /// split into triangles first.
+    Loop,
+    /// Catmull and Clark's scheme for quad meshes, which splits every face into one quad per corner each
+    /// level. Triangles are smoothed too, though less evenly than quads.
+    CatmullClark,
+}
+
+impl Mesh {
+    /// A smoother copy of the mesh, subdivided `levels` times by `scheme` and split into triangles.
+    ///
+    /// Edges where faces meet at more than `crease_angle` degrees stay sharp, as do the edges around
+    /// holes, which keep to curves through their own vertices rather than shrinking away. Texture
+    /// coordinates and vertex colours are blended straight across each face, so seams in them stay
+    /// where they were. Faces sharing a place are joined up even where they have separate vertices,
+    /// such as along texture seams, so the surface doesn't tear. The vertex normals are left out, as the
+    /// old ones no longer fit; use [`Mesh::compute_normals`] for new ones.
+    pub fn subdivide(&self, scheme: Subdivision, levels: u32, crease_angle: f64) -> Mesh {
+        let mut cage = Cage::new(self, scheme, crease_angle);
+        for _ in 0..levels {
+            cage = match scheme {
+                Subdivision::Loop => cage.loop_level(),
+                Subdivision::CatmullClark => cage.catmull_clark_level(),
+            };
+        }
+        cage.mesh(!self.uvs.is_empty(), !self.colors.is_empty())
+    }
+}
+
+// what a corner of a face carries along through subdivision, blended linearly across the face
+#[derive(Clone, Copy)]
+struct Corner {
+    uv: (f64, f64),
+    color: Color,
+}
+
+impl Corner {
+    fn mean(corners: &[Corner]) -> Corner {
+        let n = corners.len() as f64;
+        Corner {
+            uv: (corners.iter().map(|c| c.uv.0).sum::<f64>() / n, corners.iter().map(|c| c.uv.1).sum::<f64>() / n),
+            color: corners.iter().map(|c| c.color).sum::<Color>() / n,
+        }
+    }
+}
+
+// a mesh part way through being subdivided: faces around shared points, the attributes at each face's
+// corners, and which edges are sharp
+struct Cage {
+    points: Vec<Point3>,
+    faces: Vec<Vec<usize>>,
+    corners: Vec<Vec<Corner>>,
+    sharp: Vec<bool>,
+    edges: Edges,
+}
+
+// the edges between the faces of a cage, numbered in the order the faces first reach them
+struct Edges {
+    ends: Vec<(usize, usize)>,
+    index: HashMap<(usize, usize), usize>,
+    faces: Vec<Vec<usize>>,
+}
+
+impl Edges {
+    fn new(faces: &[Vec<usize>]) -> Self {
+        let mut edges = Edges {
+            ends: Vec::new(),
+            index: HashMap::new(),
+            faces: Vec::new(),
+        };
+        for (f, face) in faces.iter().enumerate() {
+            for (i, &a) in face.iter().enumerate() {
+                let b = face[(i + 1) % face.len()];
+                let e = *edges.index.entry(key(a, b)).or_insert_with(|| {
+                    edges.ends.push(key(a, b));
+                    edges.faces.push(Vec::new());
+                    edges.ends.len() - 1
+                });
+                edges.faces[e].push(f);
+            }
+        }
+        edges
+    }
+
+    // the edge from `a` to `b`, which some face must have
+    fn between(&self, a: usize, b: usize) -> usize {
+        self.index[&key(a, b)]
+    }
+}
+
+fn key(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+impl Cage {
+    fn new(mesh: &Mesh, scheme: Subdivision, crease_angle: f64) -> Self {
+        // vertices at the same place become one point
+        let mut shared = HashMap::new();
+        let mut points = Vec::new();
+        let point: Vec<usize> = mesh.positions.iter().map(|&p| {
+            let key = [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()];
+            *shared.entry(key).or_insert_with(|| {
+                points.push(p);
+                points.len() - 1
+            })
+        }).collect();
+
+        let mut mesh_faces: Vec<Vec<usize>> = mesh.triangles.iter().map(|t| t.to_vec()).collect();
+        match scheme {
+            Subdivision::Loop => mesh_faces.extend(mesh.quads.iter().flat_map(|&[a, b, c, d]| [vec![a, b, c], vec![a, c, d]])),
+            Subdivision::CatmullClark => mesh_faces.extend(mesh.quads.iter().map(|q| q.to_vec())),
+        }
+
+        let mut faces = Vec::new();
+        let mut corners = Vec::new();
+        for face in mesh_faces {
+            let at: Vec<usize> = face.iter().map(|&v| point[v]).collect();
+            // faces with two corners at the same place have nothing to smooth
+            if (0..at.len()).any(|i| at[i + 1..].contains(&at[i])) {
+                continue;
+            }
+            corners.push(face.iter().map(|&v| Corner {
+                uv: mesh.uvs.get(v).copied().unwrap_or((0.0, 0.0)),
+                color: mesh.colors.get(v).copied().unwrap_or(Color::new(1,1,1)),
+            }).collect());
+            faces.push(at);
+        }
+
+        let edges = Edges::new(&faces);
+        let normals: Vec<Vec3> = faces.iter().map(|face| face_normal(&points, face)).collect();
+        let min_cos = crease_angle.to_radians().cos();
+        let sharp = edges.faces.iter().map(|f| match f[..] {
+            [a, b] => normals[a].dot(&normals[b]) < min_cos && !normals[a].near_zero() && !normals[b].near_zero(),
+            // holes, and edges more than two faces meet at
+            _ => true,
+        }).collect();
+
+        Self {
+            points,
+            faces,
+            corners,
+            sharp,
+            edges,
+        }
+    }
+
+    // the points joined to each point by an edge, and the ones joined by sharp edges
+    fn neighbours(&self) -> (Vec<Vec<usize>>, Vec<Vec<usize>>) {
+        let mut all = vec![Vec::new(); self.points.len()];
+        let mut sharp = vec![Vec::new(); self.points.len()];
+        for (e, &(a, b)) in self.edges.ends.iter().enumerate() {
+            all[a].push(b);
+            all[b].push(a);
+            if self.sharp[e] {
+                sharp[a].push(b);
+                sharp[b].push(a);
+            }
+        }
+        (all, sharp)
+    }
+
+    // where a point moves to when it has sharp edges to `sharp`, or `None` if it's smooth
+    fn crease_point(&self, p: usize, sharp: &[usize]) -> Option<Point3> {
+        match sharp {
+            // a single sharp edge fades out at its end, which is smooth
+            [] | [_] => None,
+            &[a, b] => Some(0.75 * self.points[p] + 0.125 * (self.points[a] + self.points[b])),
+            // corners where several sharp edges meet stay put
+            _ => Some(self.points[p]),
+        }
+    }
+
+    fn loop_level(&self) -> Cage {
+        let (neighbours, sharp_neighbours) = self.neighbours();
+        let mut points: Vec<Point3> = (0..self.points.len()).map(|p| {
+            if let Some(moved) = self.crease_point(p, &sharp_neighbours[p]) {
+                return moved;
+            }
+            let n = neighbours[p].len();
+            if n == 0 {
+                return self.points[p];
+            }
+            let beta = (0.625 - (0.375 + 0.25 * (2.0 * PI / n as f64).cos()).powi(2)) / n as f64;
+            let around: Vec3 = neighbours[p].iter().map(|&q| self.points[q]).sum();
+            (1.0 - n as f64 * beta) * self.points[p] + beta * around
+        }).collect();
+
+        // a point on each edge, weighted towards its ends and a little towards the far corners of the
+        // triangles either side
+        let first_edge = points.len();
+        for (e, &(a, b)) in self.edges.ends.iter().enumerate() {
+            let ends = self.points[a] + self.points[b];
+            points.push(match self.edges.faces[e][..] {
+                [f, g] if !self.sharp[e] => {
+                    let far = |f: usize| self.faces[f].iter().copied().find(|&c| c != a && c != b).unwrap();
+                    0.375 * ends + 0.125 * (self.points[far(f)] + self.points[far(g)])
+                }
+                _ => 0.5 * ends,
+            });
+        }
+
+        let mut faces = Vec::with_capacity(4 * self.faces.len());
+        let mut corners = Vec::with_capacity(4 * self.faces.len());
+        for (face, at) in self.faces.iter().zip(&self.corners) {
+            let (a, b, c) = (face[0], face[1], face[2]);
+            let [ab, bc, ca] = [(a, b), (b, c), (c, a)].map(|(p, q)| first_edge + self.edges.between(p, q));
+            let [cab, cbc, cca] = [(0, 1), (1, 2), (2, 0)].map(|(i, j)| Corner::mean(&[at[i], at[j]]));
+            faces.extend([vec![a, ab, ca], vec![ab, b, bc], vec![ca, bc, c], vec![ab, bc, ca]]);
+            corners.extend([vec![at[0], cab, cca], vec![cab, at[1], cbc], vec![cca, cbc, at[2]], vec![cab, cbc, cca]]);
+        }
+
+        self.split(points, faces, corners, first_edge)
+    }
+
+    fn catmull_clark_level(&self) -> Cage {
+        let (neighbours, sharp_neighbours) = self.neighbours();
+        let centres: Vec<Point3> = self.faces.iter().map(|face| {
+            face.iter().map(|&p| self.points[p]).sum::<Point3>() / face.len() as f64
+        }).collect();
+
+        let mut around = vec![Vec::new(); self.points.len()];
+        for (f, face) in self.faces.iter().enumerate() {
+            for &p in face {
+                around[p].push(f);
+            }
+        }
+
+        let mut points: Vec<Point3> = (0..self.points.len()).map(|p| {
+            if let Some(moved) = self.crease_point(p, &sharp_neighbours[p]) {
+                return moved;
+            }
+            let n = neighbours[p].len();
+            if n == 0 || around[p].is_empty() {
+                return self.points[p];
+            }
+            let n = n as f64;
+            let faces = around[p].iter().map(|&f| centres[f]).sum::<Point3>() / around[p].len() as f64;
+            let edges = neighbours[p].iter().map(|&q| 0.5 * (self.points[p] + self.points[q])).sum::<Point3>() / n;
+            (faces + 2.0 * edges + (n - 3.0) * self.points[p]) / n
+        }).collect();
+
+        // a point on each edge, between its ends and the centres of the faces either side
+        let first_edge = points.len();
+        for (e, &(a, b)) in self.edges.ends.iter().enumerate() {
+            let ends = self.points[a] + self.points[b];
+            points.push(match self.edges.faces[e][..] {
+                [f, g] if !self.sharp[e] => 0.25 * (ends + centres[f] + centres[g]),
+                _ => 0.5 * ends,
+            });
+        }
+
+        let first_centre = points.len();
+        points.extend(&centres);
+
+        let mut faces = Vec::new();
+        let mut corners = Vec::new();
+        for (f, (face, at)) in self.faces.iter().zip(&self.corners).enumerate() {
+            let n = face.len();
+            let centre = Corner::mean(at);
+            for i in 0..n {
+                let (prev, next) = ((i + n - 1) % n, (i + 1) % n);
+                let to_next = first_edge + self.edges.between(face[i], face[next]);
+                let to_prev = first_edge + self.edges.between(face[prev], face[i]);
+                faces.push(vec![face[i], to_next, first_centre + f, to_prev]);
+                corners.push(vec![at[i], Corner::mean(&[at[i], at[next]]), centre, Corner::mean(&[at[prev], at[i]])]);
+            }
+        }
+
+        self.split(points, faces, corners, first_edge)
+    }
+
+    // the next level's cage, where the halves of each sharp edge, split at the edge's point, stay sharp
+    fn split(&self, points: Vec<Point3>, faces: Vec<Vec<usize>>, corners: Vec<Vec<Corner>>, first_edge: usize) -> Cage {
+        let edges = Edges::new(&faces);
+        let mut sharp = vec![false; edges.ends.len()];
+        for (e, &(a, b)) in self.edges.ends.iter().enumerate() {
+            if self.sharp[e] {
+                sharp[edges.between(a, first_edge + e)] = true;
+                sharp[edges.between(first_edge + e, b)] = true;
+            }
+        }
+
+        Cage {
+            points,
+            faces,
+            corners,
+            sharp,
+            edges,
+        }
+    }
+
+    // the cage as triangles, with a vertex for each point and set of corner attributes
+    fn mesh(&self, uvs: bool, colors: bool) -> Mesh {
+        let mut mesh = Mesh::default();
+        let mut vertices = HashMap::new();
+        for (face, at) in self.faces.iter().zip(&self.corners) {
+            let vertex: Vec<usize> = face.iter().zip(at).map(|(&p, c)| {
+                let uv = if uvs {[c.uv.0.to_bits(), c.uv.1.to_bits()]} else {[0; 2]};
+                let color = if colors {[c.color.x.to_bits(), c.color.y.to_bits(), c.color.z.to_bits()]} else {[0; 3]};
+                *vertices.entry((p, uv, color)).or_insert_with(|| {
+                    mesh.positions.push(self.points[p]);
+                    if uvs {
+                        mesh.uvs.push(c.uv);
+                    }
+                    if colors {
+                        mesh.colors.push(c.color);
+                    }
+                    mesh.positions.len() - 1
+                })
+            }).collect();
+
+            for k in 1..vertex.len() - 1 {
+                mesh.triangles.push([vertex[0], vertex[k], vertex[k + 1]]);
+            }
+        }
+        mesh
+    }
+}
+
+// the normal of a face that may not be flat, by Newell's method, or zero if it has no area
+fn face_normal(points: &[Point3], face: &[usize]) -> Vec3 {
+    let n: Vec3 = (0..face.len()).map(|i| points[face[i]].cross(&points[face[(i + 1) % face.len()]])).sum();
+    if n.length() > 0.0 {n.unit_vector()} else {n}
+}
@@ -0,0 +1,101 @@
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::{Dielectric, Material};
+use crate::ray::Ray;
+use crate::util::random;
+use crate::vec3::Vec3;
+
+/// A translucent material such as skin, wax, marble or milk, where light passes through a glassy
+/// surface and wanders about inside, scattering off particles and being partly absorbed, before coming
+/// back out somewhere else.
+///
+/// The walk inside takes one bounce of the camera's maximum depth per scattering, so a mean free path
+/// much shorter than the object needs a higher maximum depth, or the object comes out too dark. The
+/// object must be closed, and nothing else may be inside it.
+pub struct Subsurface {
+    sigma_a: Color,
+    sigma_s: Color,
+    albedo: Color,
+    boundary: Dielectric,
+}
+
+impl Subsurface {
+    /// A material that looks roughly `color` overall, with light travelling `mean_free_path` on
+    /// average between scatterings, per channel, under a surface with index of refraction `ior`. Longer
+    /// paths let light further through, making the material look softer and more translucent.
+    pub fn new(color: Color, mean_free_path: Color, ior: f64) -> Self {
+        // the scattering albedo that gives `color` after many scatterings ("Practical and Controllable
+        // Subsurface Scattering for Production Path Tracing", Chiang et al. 2016)
+        let single = |a: f64| {
+            let a = a.clamp(0.0, 1.0);
+            1.0 - (4.09712 + 4.20863 * a - (9.59217 + 41.6808 * a + 17.7126 * a * a).sqrt()).powi(2)
+        };
+        let sigma_t = |mfp: f64| 1.0 / mfp.max(1e-6);
+
+        let sigma_t = Color::new(sigma_t(mean_free_path.x), sigma_t(mean_free_path.y), sigma_t(mean_free_path.z));
+        let alpha = Color::new(single(color.x), single(color.y), single(color.z));
+
+        Self {
+            sigma_a: sigma_t * (Color::new(1,1,1) - alpha),
+            sigma_s: sigma_t * alpha,
+            albedo: color,
+            boundary: Dielectric::new(ior),
+        }
+    }
+
+    /// A material from the absorption and scattering coefficients of what's inside, per unit distance
+    /// and per channel, as measured for real materials.
+    pub fn coefficients(sigma_a: Color, sigma_s: Color, ior: f64) -> Self {
+        let sigma_t = sigma_a + sigma_s;
+        let ratio = |s: f64, t: f64| if t > 0.0 {s / t} else {0.0};
+
+        Self {
+            sigma_a,
+            sigma_s,
+            albedo: Color::new(ratio(sigma_s.x, sigma_t.x), ratio(sigma_s.y, sigma_t.y), ratio(sigma_s.z, sigma_t.z)),
+            boundary: Dielectric::new(ior),
+        }
+    }
+}
+
+impl Material for Subsurface {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        // entering, or reflecting off the outside of the surface
+        if rec.front_face {
+            return self.boundary.scatter(r_in, rec);
+        }
+
+        // the ray has travelled through the inside to reach the surface, and might have scattered on the
+        // way. The distance to that is sampled for one channel picked at random, and weighted by the
+        // chance of any channel picking it
+        let sigma_t = self.sigma_a + self.sigma_s;
+        let channel = [sigma_t.x, sigma_t.y, sigma_t.z][(3.0 * random::<f64>()) as usize % 3];
+        let distance = -(1.0 - random::<f64>()).ln() / channel;
+        let length = r_in.dir.length();
+        let transmittance = |d: f64| Color::new((-sigma_t.x * d).exp(), (-sigma_t.y * d).exp(), (-sigma_t.z * d).exp());
+
+        if distance < rec.t * length {
+            let t = transmittance(distance);
+            let pdf = (sigma_t.x * t.x + sigma_t.y * t.y + sigma_t.z * t.z) / 3.0;
+            if pdf <= 0.0 {
+                return None;
+            }
+
+            let scatter = Ray::new(r_in.at(distance / length), Vec3::random_unit_vector());
+            return Some((self.sigma_s * t / pdf, scatter));
+        }
+
+        let t = transmittance(rec.t * length);
+        let p_reached = (t.x + t.y + t.z) / 3.0;
+        if p_reached <= 0.0 {
+            return None;
+        }
+
+        let (atten, scatter) = self.boundary.scatter(r_in, rec)?;
+        Some((atten * t / p_reached, scatter))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+}
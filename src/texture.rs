@@ -0,0 +1,150 @@
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use image::DynamicImage;
+
+use crate::color::Color;
+use crate::vec3::Point3;
+
+/// A colour that varies over a surface, looked up by texture coordinates or by position.
+pub trait Texture: Send + Sync {
+    /// The colour at texture coordinates `u`, `v` and point `p` on a surface.
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color;
+
+    /// A single number from the texture, for textures that drive scalar parameters such as roughness:
+    /// the mean of the channels.
+    fn scalar(&self, u: f64, v: f64, p: Point3) -> f64 {
+        let c = self.value(u, v, p);
+        (c.x + c.y + c.z) / 3.0
+    }
+}
+
+/// A colour is a texture that's the same everywhere.
+impl Texture for Color {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        *self
+    }
+}
+
+/// So is a number, grey when seen as a colour.
+impl Texture for f64 {
+    fn value(&self, _u: f64, _v: f64, _p: Point3) -> Color {
+        Color::new(*self, *self, *self)
+    }
+}
+
+/// A 3D checkerboard of `even` and `odd`, in cubes `scale` across.
+pub struct CheckerTexture {
+    inv_scale: f64,
+    even: Arc<dyn Texture>,
+    odd: Arc<dyn Texture>,
+}
+
+impl CheckerTexture {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self {
+            inv_scale: 1.0 / scale,
+            even,
+            odd,
+        }
+    }
+}
+
+impl Texture for CheckerTexture {
+    fn value(&self, u: f64, v: f64, p: Point3) -> Color {
+        let x = (self.inv_scale * p.x).floor() as i64;
+        let y = (self.inv_scale * p.y).floor() as i64;
+        let z = (self.inv_scale * p.z).floor() as i64;
+
+        if (x + y + z) % 2 == 0 {self.even.value(u, v, p)} else {self.odd.value(u, v, p)}
+    }
+}
+
+/// An image wrapped over a surface's texture coordinates, with `v` running up from the bottom row and
+/// repeating outside [0, 1].
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl ImageTexture {
+    /// Loads a colour texture. 8 and 16 bit images are taken to be sRGB encoded, as paint programs
+    /// save them, and converted to linear colour; floating point images are used as they are.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let image = image::open(path).map_err(io::Error::other)?;
+        Ok(Self::from_color_image(image))
+    }
+
+    /// Loads a texture holding data rather than colour, such as a roughness map, without any
+    /// conversion.
+    pub fn load_linear(path: impl AsRef<Path>) -> io::Result<Self> {
+        let image = image::open(path).map_err(io::Error::other)?;
+        Ok(Self::from_image(image))
+    }
+
+    /// Loads the alpha channel of an image as a grey texture, for [`AlphaMask`](crate::AlphaMask).
+    /// Images without one are opaque everywhere.
+    pub fn load_alpha(path: impl AsRef<Path>) -> io::Result<Self> {
+        let image = image::open(path).map_err(io::Error::other)?.into_rgba32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = image.pixels().map(|p| Color::new(p[3], p[3], p[3])).collect();
+
+        Ok(Self::new(width, height, pixels))
+    }
+
+    /// A texture from `width * height` pixels in scanline order, top row first.
+    pub fn new(width: usize, height: usize, pixels: Vec<Color>) -> Self {
+        assert_eq!(pixels.len(), width * height);
+
+        Self {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    // a colour texture from a decoded image, converted from sRGB unless it's floating point
+    pub(crate) fn from_color_image(image: DynamicImage) -> Self {
+        let encoded = !matches!(image, DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_));
+
+        let mut texture = Self::from_image(image);
+        if encoded {
+            for p in &mut texture.pixels {
+                *p = Color::new(srgb_to_linear(p.x), srgb_to_linear(p.y), srgb_to_linear(p.z));
+            }
+        }
+        texture
+    }
+
+    pub(crate) fn from_image(image: DynamicImage) -> Self {
+        let image = image.into_rgb32f();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let pixels = image.pixels().map(|p| Color::new(p[0], p[1], p[2])).collect();
+
+        Self::new(width, height, pixels)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn value(&self, u: f64, v: f64, _p: Point3) -> Color {
+        if self.pixels.is_empty() {
+            return Color::new(0,0,0);
+        }
+
+        let u = u.rem_euclid(1.0);
+        let v = 1.0 - v.rem_euclid(1.0);
+        let i = ((u * self.width as f64) as usize).min(self.width - 1);
+        let j = ((v * self.height as f64) as usize).min(self.height - 1);
+        self.pixels[j * self.width + i]
+    }
+}
+
+pub(crate) fn srgb_to_linear(c: f64) -> f64 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
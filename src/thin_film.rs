@@ -0,0 +1,228 @@
+use std::f64::consts::PI;
+use std::ops::{Add, Div, Mul, Sub};
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::microfacet::fresnel_conductor;
+use crate::ray::Ray;
+use crate::texture::Texture;
+use crate::util::random;
+use crate::vec3::Vec3;
+
+// the wavelengths, in nanometres, standing in for red, green and blue
+const CHANNEL_WAVELENGTHS: [f64; 3] = [650.0, 550.0, 450.0];
+
+/// A transparent film a few hundred nanometres thick over another material, like soap or oil, whose
+/// reflections interfere with each other to make shifting rainbow colours.
+///
+/// The film works over materials that have a [`Material::refractive_index`]: for clear ones such as
+/// [`Dielectric`](crate::Dielectric) it decides how much light is reflected rather than let through,
+/// and for metals it changes the colour of the reflection. Other materials are left as they are. The
+/// film itself is smooth, so it reflects like a mirror, while light let through is refracted, roughened
+/// and absorbed by the base as it would be without the film.
+///
+/// The interference colours are worked out at one wavelength each for red, green and blue, unless the
+/// base disperses light, when the film follows the wavelength being traced instead.
+///
+/// A soap bubble is a film over a `Dielectric` with an index of refraction of 1.
+pub struct ThinFilm {
+    base: Arc<dyn Material>,
+    thickness: Arc<dyn Texture>,
+    ior: f64,
+}
+
+impl ThinFilm {
+    /// A film `thickness` nanometres thick, which can vary over the surface, with index of refraction
+    /// `ior`, over `base`. Visible colours come from films up to about 1000 nm thick.
+    pub fn new(base: Arc<dyn Material>, thickness: Arc<dyn Texture>, ior: f64) -> Self {
+        Self {
+            base,
+            thickness,
+            ior,
+        }
+    }
+
+    // the fraction of light reflected by the film and what's beneath it, per channel, for light
+    // arriving through a medium of index `outside` at `cos_i` to the surface
+    fn reflectance(&self, r_in: &Ray, rec: &HitRecord, cos_i: f64, outside: f64, eta: Color, k: Color) -> Color {
+        let thickness = self.thickness.scalar(rec.u, rec.v, rec.p).max(0.0);
+        // a path through a dispersive base carries only its own wavelength
+        let wavelengths = match r_in.wavelength {
+            Some(lambda) if self.base.disperses() => [lambda; 3],
+            _ => CHANNEL_WAVELENGTHS,
+        };
+
+        let [r, g, b] = [(0, eta.x, k.x), (1, eta.y, k.y), (2, eta.z, k.z)].map(|(c, eta, k)| {
+            airy_reflectance(cos_i, outside, self.ior, Complex::new(eta, k), thickness, wavelengths[c])
+        });
+        Color::new(r, g, b)
+    }
+
+    // light arriving from outside a clear material, or from inside it, reflects or passes through in
+    // proportion to the film's reflectance
+    fn scatter_clear(&self, r_in: &Ray, rec: &HitRecord, ir: f64) -> Option<(Color, Ray)> {
+        let unit_direction = r_in.dir.unit_vector();
+        let cos_i = (-unit_direction).dot(&rec.normal).clamp(0.0, 1.0);
+        let (outside, inside) = if rec.front_face {(1.0, ir)} else {(ir, 1.0)};
+
+        let substrate = Color::new(inside, inside, inside);
+        let reflectance = self.reflectance(r_in, rec, cos_i, outside, substrate, Color::new(0,0,0));
+        let sin_i = (1.0 - cos_i * cos_i).sqrt();
+        let cannot_refract = outside / inside * sin_i > 1.0;
+
+        // choose by the mean reflectance over the channels, and weight each channel by its own
+        let p_reflect = if cannot_refract {1.0} else {(reflectance.x + reflectance.y + reflectance.z) / 3.0};
+        if random::<f64>() < p_reflect {
+            let direction = Vec3::reflect(&unit_direction, &rec.normal);
+            Some((reflectance / p_reflect, Ray::new(rec.p, direction)))
+        } else {
+            // what the film lets through meets the base as it would without the film, so the base's
+            // own scattering, absorption included, is passed on as it is
+            let (atten, scatter) = self.base.scatter(r_in, rec)?;
+            Some(((Color::new(1,1,1) - reflectance) / (1.0 - p_reflect) * atten, scatter))
+        }
+    }
+
+    // the factor a metal's own reflection is scaled by, swapping its reflectance for the film's
+    fn metal_factor(&self, r_in: &Ray, rec: &HitRecord, eta: Color, k: Color) -> Color {
+        let cos_i = (-r_in.dir.unit_vector()).dot(&rec.normal).clamp(0.0, 1.0);
+        let film = self.reflectance(r_in, rec, cos_i, 1.0, eta, k);
+        let bare = fresnel_conductor(cos_i, eta, k);
+
+        let ratio = |film: f64, bare: f64| if bare > 0.0 {film / bare} else {0.0};
+        Color::new(ratio(film.x, bare.x), ratio(film.y, bare.y), ratio(film.z, bare.z))
+    }
+}
+
+impl Material for ThinFilm {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let Some((eta, k)) = self.base.refractive_index(r_in, rec) else {return self.base.scatter(r_in, rec)};
+
+        if k.near_zero() {
+            return self.scatter_clear(r_in, rec, eta.x);
+        }
+
+        let (atten, scatter) = self.base.scatter(r_in, rec)?;
+        Some((atten * self.metal_factor(r_in, rec, eta, k), scatter))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let (eta, k) = self.base.refractive_index(r_in, rec)?;
+        if k.near_zero() {
+            return None;
+        }
+
+        let (f_cos, pdf) = self.base.evaluate(r_in, rec, direction)?;
+        Some((f_cos * self.metal_factor(r_in, rec, eta, k), pdf))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        self.base.albedo(rec)
+    }
+
+    fn disperses(&self) -> bool {
+        self.base.disperses()
+    }
+
+    fn refractive_index(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Color)> {
+        self.base.refractive_index(r_in, rec)
+    }
+}
+
+// the reflectance of a film of index `film` and `thickness` nanometres between a medium of index
+// `outside` and one of complex index `inside`, for unpolarised light of wavelength `lambda` arriving at
+// `cos_i`, summing every reflection back and forth inside the film
+fn airy_reflectance(cos_i: f64, outside: f64, film: f64, inside: Complex, thickness: f64, lambda: f64) -> f64 {
+    let n0 = Complex::new(outside, 0.0);
+    let n1 = Complex::new(film, 0.0);
+    let n2 = inside;
+
+    // Snell's law, with complex cosines for absorbing media and for light that can't get through
+    let sin2 = outside * outside * (1.0 - cos_i * cos_i);
+    let cos_in = |n: Complex| (Complex::new(1.0, 0.0) - Complex::new(sin2, 0.0) / (n * n)).sqrt();
+    let (cos0, cos1, cos2) = (Complex::new(cos_i, 0.0), cos_in(n1), cos_in(n2));
+
+    let s = |na: Complex, ca: Complex, nb: Complex, cb: Complex| (na * ca - nb * cb) / (na * ca + nb * cb);
+    let p = |na: Complex, ca: Complex, nb: Complex, cb: Complex| (nb * ca - na * cb) / (nb * ca + na * cb);
+
+    // the phase difference picked up by light crossing the film and back
+    let delta = Complex::new(4.0 * PI * thickness / lambda, 0.0) * n1 * cos1;
+    let phase = Complex::exp_i(delta);
+
+    let total = |r01: Complex, r12: Complex| {
+        let r = (r01 + r12 * phase) / (Complex::new(1.0, 0.0) + r01 * r12 * phase);
+        r.norm_squared()
+    };
+
+    let rs = total(s(n0, cos0, n1, cos1), s(n1, cos1, n2, cos2));
+    let rp = total(p(n0, cos0, n1, cos1), p(n1, cos1, n2, cos2));
+    (0.5 * (rs + rp)).clamp(0.0, 1.0)
+}
+
+#[derive(Clone, Copy)]
+struct Complex {
+    re: f64,
+    im: f64,
+}
+
+impl Complex {
+    fn new(re: f64, im: f64) -> Self {
+        Self {
+            re,
+            im,
+        }
+    }
+
+    fn norm_squared(self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    // the principal square root, with a non-negative real part
+    fn sqrt(self) -> Self {
+        let r = self.norm_squared().sqrt();
+        let re = ((r + self.re) / 2.0).max(0.0).sqrt();
+        let im = ((r - self.re) / 2.0).max(0.0).sqrt();
+        Self::new(re, if self.im < 0.0 {-im} else {im})
+    }
+
+    // e to the power of i times `z`
+    fn exp_i(z: Self) -> Self {
+        let magnitude = (-z.im).exp();
+        Self::new(magnitude * z.re.cos(), magnitude * z.re.sin())
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(self.re + rhs.re, self.im + rhs.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::new(self.re - rhs.re, self.im - rhs.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(self.re * rhs.re - self.im * rhs.im, self.re * rhs.im + self.im * rhs.re)
+    }
+}
+
+impl Div for Complex {
+    type Output = Self;
+
+    fn div(self, rhs: Self) -> Self::Output {
+        let d = rhs.norm_squared();
+        Self::new((self.re * rhs.re + self.im * rhs.im) / d, (self.im * rhs.re - self.re * rhs.im) / d)
+    }
+}
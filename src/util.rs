@@ -1,7 +1,82 @@
-use rand::prelude::*;
+use std::cell::RefCell;
+
+use rand::distributions::{Distribution, Standard};
+use rand::{Error, Rng as _, RngCore};
 
 use crate::interval::Interval;
 
 pub fn random_f64(interval: &Interval) -> f64 {
     interval.min + (interval.max - interval.min) * random::<f64>()
-}
\ No newline at end of file
+}
+
+/// PCG32 (XSH-RR). Its whole state is two integers, so the state of a render can be written to a
+/// checkpoint and restored bit-for-bit, which isn't possible with the generators in `rand`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rng {
+    pub state: u64,
+    pub inc: u64,
+}
+
+impl Rng {
+    const MULTIPLIER: u64 = 6364136223846793005;
+
+    pub fn new(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: (stream << 1) | 1,
+        };
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(Self::MULTIPLIER).wrapping_add(self.inc);
+    }
+}
+
+impl RngCore for Rng {
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+        xorshifted.rotate_right(rot)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        ((self.next_u32() as u64) << 32) | self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+thread_local! {
+    static THREAD_RNG: RefCell<Rng> = RefCell::new(Rng::new(rand::random(), rand::random()));
+}
+
+/// Drop-in for `rand::random` that draws from this thread's [`Rng`].
+pub fn random<T>() -> T where Standard: Distribution<T> {
+    THREAD_RNG.with(|rng| rng.borrow_mut().gen())
+}
+
+/// Runs `f` with every call to [`random`] on this thread drawing from `rng`, leaving `rng` advanced
+/// past the numbers that were used. Useful for generating the same scene every time.
+pub fn with_rng<R>(rng: &mut Rng, f: impl FnOnce() -> R) -> R {
+    let previous = THREAD_RNG.with(|thread_rng| thread_rng.replace(*rng));
+    let result = f();
+    *rng = THREAD_RNG.with(|thread_rng| thread_rng.replace(previous));
+    result
+}
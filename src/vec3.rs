@@ -1,10 +1,8 @@
 use std::{iter::Sum, ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub}};
 
-use rand::random;
+use crate::{interval::Interval, util::{random, random_f64}};
 
-use crate::{interval::Interval, util::random_f64};
-
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Vec3 {
     pub x: f64,
     pub y: f64,
@@ -12,6 +10,7 @@ pub struct Vec3 {
 }
 
 impl Sum for Vec3 {
+    #[allow(clippy::needless_return)]
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         let mut sum = Vec3::new(0,0,0);
         for vec in iter {
@@ -93,6 +92,7 @@ impl Vec3 {
         Self::random_in_unit_sphere().unit_vector()
     }
 
+    #[allow(clippy::needless_return)]
     pub fn random_on_hemisphere(normal: &Vec3) -> Vec3 {
         let on_unit_sphere = Self::random_unit_vector();
         
@@ -115,6 +115,14 @@ impl Vec3 {
         r_out_perp + r_out_parallel
     }
 
+    // two unit vectors perpendicular to this unit vector and to each other
+    pub fn orthonormal_basis(&self) -> (Self, Self) {
+        let a = if self.x.abs() > 0.9 {Vec3::new(0,1,0)} else {Vec3::new(1,0,0)};
+        let v = self.cross(&a).unit_vector();
+        let u = v.cross(self);
+        (u, v)
+    }
+
     pub fn near_zero(&self) -> bool {
         let s = 1e-8;
         
@@ -0,0 +1,53 @@
+use std::sync::Arc;
+
+use crate::color::Color;
+use crate::hittable::HitRecord;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// Tints `base` by the vertex colours of a [`TriangleMesh`](crate::TriangleMesh), as blended across
+/// each triangle from its corners. Give `base` a white albedo for the mesh's own colours, or another to
+/// shade them. Hits without a vertex colour, such as on other objects, are left as `base` has them.
+pub struct VertexColor {
+    base: Arc<dyn Material>,
+}
+
+impl VertexColor {
+    pub fn new(base: Arc<dyn Material>) -> Self {
+        Self {
+            base,
+        }
+    }
+}
+
+impl Material for VertexColor {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let (attenuation, scattered) = self.base.scatter(r_in, rec)?;
+        Some((tint(attenuation, rec), scattered))
+    }
+
+    fn evaluate(&self, r_in: &Ray, rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let (f, pdf) = self.base.evaluate(r_in, rec, direction)?;
+        Some((tint(f, rec), pdf))
+    }
+
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        tint(self.base.albedo(rec), rec)
+    }
+
+    fn disperses(&self) -> bool {
+        self.base.disperses()
+    }
+
+    fn refractive_index(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Color)> {
+        self.base.refractive_index(r_in, rec)
+    }
+}
+
+fn tint(c: Color, rec: &HitRecord) -> Color {
+    match rec.color {
+        Some(color) => c * color,
+        None => c,
+    }
+}
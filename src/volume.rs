@@ -0,0 +1,385 @@
+use std::f64::consts::PI;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::color::Color;
+use crate::hittable::{HitRecord, Hittable};
+use crate::interval::Interval;
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::util::random;
+use crate::vec3::{Point3, Vec3};
+
+/// A dense 3D grid of densities, looked up with trilinear interpolation between voxel centres. Voxels
+/// are stored with `x` varying fastest, then `y`, then `z`, as in a NumPy array of shape
+/// `(nz, ny, nx)` indexed `[z, y, x]`.
+pub struct VoxelGrid {
+    nx: usize,
+    ny: usize,
+    nz: usize,
+    densities: Vec<f64>,
+    max: f64,
+}
+
+impl VoxelGrid {
+    /// A grid of `nx` by `ny` by `nz` voxels. Panics if any of them is 0, or if there aren't that many
+    /// densities.
+    pub fn new(nx: usize, ny: usize, nz: usize, densities: Vec<f64>) -> Self {
+        assert!(nx > 0 && ny > 0 && nz > 0, "a voxel grid needs at least one voxel along each axis");
+        assert_eq!(densities.len(), nx * ny * nz);
+
+        let max = densities.iter().copied().fold(0.0, f64::max);
+        Self {
+            nx,
+            ny,
+            nz,
+            densities,
+            max,
+        }
+    }
+
+    /// A grid with the density of each voxel given by `f` at its centre, in coordinates from 0 to 1
+    /// across the grid.
+    pub fn from_fn(nx: usize, ny: usize, nz: usize, f: impl Fn(f64, f64, f64) -> f64) -> Self {
+        let mut densities = Vec::with_capacity(nx * ny * nz);
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let centre = |i: usize, n: usize| (i as f64 + 0.5) / n as f64;
+                    densities.push(f(centre(x, nx), centre(y, ny), centre(z, nz)).max(0.0));
+                }
+            }
+        }
+
+        Self::new(nx, ny, nz, densities)
+    }
+
+    /// A billowing cloud filling a cube `resolution` voxels across, made from fractal noise that fades
+    /// out towards the edges. Different seeds give different clouds.
+    pub fn cloud(resolution: usize, seed: u32) -> Self {
+        Self::from_fn(resolution, resolution, resolution, |x, y, z| {
+            let p = Vec3::new(x, y, z);
+            let r = 2.0 * (p - Vec3::new(0.5, 0.5, 0.5)).length();
+            let fbm = (0..5).map(|octave| {
+                let scale = (1 << octave) as f64;
+                value_noise(p * (3.0 * scale), seed.wrapping_add(octave)) / scale
+            }).sum::<f64>() / 1.9375;
+
+            // dense in the middle, breaking up into billows and wisps further out
+            (4.0 * (fbm + 0.6 * (1.0 - r) - 0.75)).clamp(0.0, 1.0)
+        })
+    }
+
+    /// Loads a 3D NumPy `.npy` array of 32 or 64 bit floats or bytes, such as one saved with
+    /// `numpy.save`.
+    pub fn load_npy(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        if bytes.len() < 10 || &bytes[..6] != b"\x93NUMPY" {
+            return Err(invalid_data("not a NumPy file"));
+        }
+
+        // version 1 files have a two byte header length, later versions four
+        let (header_len, start) = match bytes[6] {
+            1 => (u16::from_le_bytes([bytes[8], bytes[9]]) as usize, 10),
+            _ if bytes.len() >= 12 => (u32::from_le_bytes([bytes[8], bytes[9], bytes[10], bytes[11]]) as usize, 12),
+            _ => return Err(invalid_data("truncated NumPy header")),
+        };
+        let header = bytes.get(start..start + header_len).ok_or_else(|| invalid_data("truncated NumPy header"))?;
+        let header = String::from_utf8_lossy(header);
+
+        let descr = header_value(&header, "descr").ok_or_else(|| invalid_data("NumPy header has no dtype"))?;
+        let descr = descr.get(1..).and_then(|d| d.split(['\'', '"']).next()).unwrap_or("");
+        let fortran_order = header_value(&header, "fortran_order").is_some_and(|v| v.starts_with("True"));
+        let shape = header_value(&header, "shape").ok_or_else(|| invalid_data("NumPy header has no shape"))?;
+        let shape = shape.trim_start_matches('(').split(')').next().unwrap_or("")
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|s| s.parse::<usize>().map_err(|_| invalid_data("bad NumPy shape")))
+            .collect::<io::Result<Vec<_>>>()?;
+        let [d0, d1, d2] = shape[..] else {return Err(invalid_data("NumPy array isn't 3D"))};
+        if d0 == 0 || d1 == 0 || d2 == 0 {
+            return Err(invalid_data("NumPy array is empty"));
+        }
+
+        let data = &bytes[start + header_len..];
+        let values: Vec<f64> = match descr {
+            "<f4" => data.chunks_exact(4).map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64).collect(),
+            "<f8" => data.chunks_exact(8).map(|b| f64::from_le_bytes(b.try_into().unwrap())).collect(),
+            "|u1" | "<u1" => data.iter().map(|&b| b as f64 / 255.0).collect(),
+            _ => return Err(invalid_data("unsupported NumPy dtype, expected float32, float64 or uint8")),
+        };
+        if values.len() < d0 * d1 * d2 {
+            return Err(invalid_data("truncated NumPy data"));
+        }
+
+        // Fortran order stores the first index fastest, so it's transposed into the usual order
+        let (nz, ny, nx) = (d0, d1, d2);
+        let densities = if fortran_order {
+            let mut densities = Vec::with_capacity(nx * ny * nz);
+            for z in 0..nz {
+                for y in 0..ny {
+                    for x in 0..nx {
+                        densities.push(values[z + nz * (y + ny * x)]);
+                    }
+                }
+            }
+            densities
+        } else {
+            values[..nx * ny * nz].to_vec()
+        };
+
+        Ok(Self::new(nx, ny, nz, densities.into_iter().map(|d| d.max(0.0)).collect()))
+    }
+
+    /// Loads a headerless grid of `nx * ny * nz` little-endian 32 bit floats.
+    pub fn load_raw(path: impl AsRef<Path>, nx: usize, ny: usize, nz: usize) -> io::Result<Self> {
+        if nx == 0 || ny == 0 || nz == 0 {
+            return Err(invalid_data("raw volume has no voxels"));
+        }
+        let bytes = fs::read(path)?;
+        if bytes.len() < 4 * nx * ny * nz {
+            return Err(invalid_data("raw volume is smaller than its dimensions"));
+        }
+
+        let densities = bytes.chunks_exact(4)
+            .take(nx * ny * nz)
+            .map(|b| (f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64).max(0.0))
+            .collect();
+        Ok(Self::new(nx, ny, nz, densities))
+    }
+
+    /// The density at `p`, in coordinates from 0 to 1 across the grid, and 0 outside it.
+    pub fn density(&self, p: Vec3) -> f64 {
+        if !(0.0..=1.0).contains(&p.x) || !(0.0..=1.0).contains(&p.y) || !(0.0..=1.0).contains(&p.z) {
+            return 0.0;
+        }
+
+        // the voxels either side of `p` along each axis, and how far it is between their centres
+        let axis = |c: f64, n: usize| {
+            let f = (c * n as f64 - 0.5).clamp(0.0, (n - 1) as f64);
+            let i = (f as usize).min(n.saturating_sub(2));
+            (i, (i + 1).min(n - 1), f - i as f64)
+        };
+        let (x0, x1, fx) = axis(p.x, self.nx);
+        let (y0, y1, fy) = axis(p.y, self.ny);
+        let (z0, z1, fz) = axis(p.z, self.nz);
+
+        let at = |x: usize, y: usize, z: usize| self.densities[(z * self.ny + y) * self.nx + x];
+        let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+        let along_x = |y: usize, z: usize| lerp(at(x0, y, z), at(x1, y, z), fx);
+        lerp(lerp(along_x(y0, z0), along_x(y1, z0), fy), lerp(along_x(y0, z1), along_x(y1, z1), fy), fz)
+    }
+}
+
+/// Smoke, cloud or fire filling the box from `min` to `max`, with its density given by a voxel grid
+/// stretched over the box and scaled by `density`, the chance per unit distance of light hitting a
+/// particle where the grid is 1. Rays are scattered by `mat` at the particles they hit, usually a
+/// [`HenyeyGreenstein`].
+///
+/// Where rays hit a particle is found by delta tracking ("Monte Carlo Methods for Volumetric Light
+/// Transport Simulation", Novák et al. 2018), and how much light gets through to the sky by ratio
+/// tracking. Hits have a normal facing back along the ray, as particles have no surface.
+pub struct Volume {
+    grid: Arc<VoxelGrid>,
+    min: Point3,
+    max: Point3,
+    density: f64,
+    mat: Arc<dyn Material>,
+}
+
+impl Volume {
+    pub fn new(grid: Arc<VoxelGrid>, min: Point3, max: Point3, density: f64, mat: Arc<dyn Material>) -> Self {
+        Self {
+            grid,
+            min,
+            max,
+            density,
+            mat,
+        }
+    }
+
+    fn density_at(&self, p: Point3) -> f64 {
+        let size = self.max - self.min;
+        let local = p - self.min;
+        self.density * self.grid.density(Vec3::new(local.x / size.x, local.y / size.y, local.z / size.z))
+    }
+
+    // the density everywhere is at most this, which lets distances be sampled as if it were uniform
+    fn majorant(&self) -> f64 {
+        self.density * self.grid.max
+    }
+}
+
+impl Hittable for Volume {
+    fn hit(&self, r: &Ray, ray_t: Interval) -> Option<HitRecord> {
+        let (mut t, t1) = Aabb::new(self.min, self.max).hit(r, ray_t)?;
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return None;
+        }
+
+        // step through the box as if the density were the majorant everywhere, treating the difference
+        // as particles that let rays straight through
+        let step = 1.0 / (majorant * r.dir.length());
+        loop {
+            t -= (1.0 - random::<f64>()).ln() * step;
+            if t >= t1 {
+                return None;
+            }
+
+            let p = r.at(t);
+            if random::<f64>() * majorant < self.density_at(p) {
+                let mut rec = HitRecord {
+                    p,
+                    normal: Vec3::new(0,0,0),
+                    geometric_normal: Vec3::new(0,0,0),
+                    dpdu: Vec3::new(0,0,0),
+                    dpdv: Vec3::new(0,0,0),
+                    mat: Arc::clone(&self.mat),
+                    t,
+                    u: 0.0,
+                    v: 0.0,
+                    barycentric: None,
+                    color: None,
+                    front_face: false,
+                    object_id: 0,
+                    material_id: 0,
+                };
+                rec.set_face_normal(r, &-r.dir.unit_vector());
+                return Some(rec);
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(self.min, self.max))
+    }
+
+    fn transmittance(&self, r: &Ray, ray_t: Interval) -> f64 {
+        let Some((mut t, t1)) = Aabb::new(self.min, self.max).hit(r, ray_t) else {return 1.0};
+        let majorant = self.majorant();
+        if majorant <= 0.0 {
+            return 1.0;
+        }
+
+        // the same steps as `hit`, but rather than stopping at a particle, the light getting through is
+        // reduced by the chance of there being one
+        let step = 1.0 / (majorant * r.dir.length());
+        let mut transmittance = 1.0;
+        loop {
+            t -= (1.0 - random::<f64>()).ln() * step;
+            if t >= t1 {
+                return transmittance;
+            }
+
+            transmittance *= 1.0 - self.density_at(r.at(t)) / majorant;
+
+            // once little light is left, stop at random rather than following it all the way through
+            if transmittance < 0.1 {
+                if random::<f64>() < 0.5 {
+                    return 0.0;
+                }
+                transmittance *= 2.0;
+            }
+        }
+    }
+
+    fn materials(&self) -> Vec<Arc<dyn Material>> {
+        vec![Arc::clone(&self.mat)]
+    }
+}
+
+/// How particles in a [`Volume`] scatter light: a fraction `albedo` of it is scattered rather than
+/// absorbed, in directions following the Henyey–Greenstein phase function with anisotropy `g` between
+/// -1 and 1. Positive `g` scatters light mostly onwards, as water droplets in clouds do, negative `g`
+/// mostly back the way it came, and 0 equally in every direction.
+pub struct HenyeyGreenstein {
+    albedo: Color,
+    g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Color, g: f64) -> Self {
+        Self {
+            albedo,
+            g: g.clamp(-0.99, 0.99),
+        }
+    }
+
+    // the probability density of scattering at `cos` to the direction of travel
+    fn phase(&self, cos: f64) -> f64 {
+        let g = self.g;
+        let denom = 1.0 + g * g - 2.0 * g * cos;
+        (1.0 - g * g) / (4.0 * PI * denom * denom.sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<(Color, Ray)> {
+        let g = self.g;
+        let u = random::<f64>();
+        let cos = if g.abs() < 1e-3 {
+            1.0 - 2.0 * u
+        } else {
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sin = (1.0 - cos * cos).max(0.0).sqrt();
+        let phi = 2.0 * PI * random::<f64>();
+
+        let forward = r_in.dir.unit_vector();
+        let (t, b) = forward.orthonormal_basis();
+        let direction = sin * phi.cos() * t + sin * phi.sin() * b + cos * forward;
+        Some((self.albedo, Ray::new(rec.p, direction)))
+    }
+
+    fn evaluate(&self, r_in: &Ray, _rec: &HitRecord, direction: Vec3) -> Option<(Color, f64)> {
+        let pdf = self.phase(r_in.dir.unit_vector().dot(&direction.unit_vector()));
+        Some((self.albedo * pdf, pdf))
+    }
+
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        self.albedo
+    }
+}
+
+// the value for key `key` in the Python dict literal of a NumPy header, up to the next key
+fn header_value<'a>(header: &'a str, key: &str) -> Option<&'a str> {
+    let start = header.find(&format!("'{key}'"))? + key.len() + 2;
+    let rest = header[start..].trim_start().strip_prefix(':')?.trim_start();
+    Some(rest)
+}
+
+// smoothly interpolated random values at the corners of a unit lattice, between 0 and 1
+fn value_noise(p: Vec3, seed: u32) -> f64 {
+    let hash = |x: i64, y: i64, z: i64| {
+        let mut h = (x as u64).wrapping_mul(0x9e3779b97f4a7c15)
+            ^ (y as u64).wrapping_mul(0xc2b2ae3d27d4eb4f)
+            ^ (z as u64).wrapping_mul(0x165667b19e3779f9)
+            ^ (seed as u64).wrapping_mul(0x27d4eb2f165667c5);
+        h ^= h >> 33;
+        h = h.wrapping_mul(0xff51afd7ed558ccd);
+        h ^= h >> 33;
+        (h >> 11) as f64 / (1u64 << 53) as f64
+    };
+
+    let (ix, iy, iz) = (p.x.floor() as i64, p.y.floor() as i64, p.z.floor() as i64);
+    let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+    let (fx, fy, fz) = (smooth(p.x - p.x.floor()), smooth(p.y - p.y.floor()), smooth(p.z - p.z.floor()));
+
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let along_x = |y: i64, z: i64| lerp(hash(ix, y, z), hash(ix + 1, y, z), fx);
+    lerp(
+        lerp(along_x(iy, iz), along_x(iy + 1, iz), fy),
+        lerp(along_x(iy, iz + 1), along_x(iy + 1, iz + 1), fy),
+        fz,
+    )
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
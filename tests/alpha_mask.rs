@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{AlphaMask, CameraBuilder, Color, ConstantEnvironment, Hittable, HittableList, Interval, Lambertian, Material, Mesh, Point3, Ray, Sphere, Texture, TriangleMesh, Vec3};
+
+fn grey() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
+
+// a square facing up z across (-1, -1) to (1, 1), with `u` running along x
+fn square(z: f64) -> Arc<TriangleMesh> {
+    Arc::new(TriangleMesh::new(Mesh {
+        positions: vec![Point3::new(-1.0, -1.0, z), Point3::new(1.0, -1.0, z), Point3::new(1.0, 1.0, z), Point3::new(-1.0, 1.0, z)],
+        uvs: vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+        ..Mesh::default()
+    }, grey()))
+}
+
+fn down(x: f64, y: f64) -> Ray {
+    Ray::new(Point3::new(x, y, 5.0), Vec3::new(0, 0, -1))
+}
+
+const ALL: Interval = Interval {min: 0.001, max: f64::INFINITY};
+
+// an opacity rising from 0 to 1 along u
+struct Ramp;
+
+impl Texture for Ramp {
+    fn value(&self, u: f64, _v: f64, _p: Point3) -> Color {
+        Color::new(u, u, u)
+    }
+}
+
+// an opacity of 0 on the near side of things, facing up z, and 1 on the far side
+struct FarSide;
+
+impl Texture for FarSide {
+    fn value(&self, _u: f64, _v: f64, p: Point3) -> Color {
+        if p.z > 0.0 {Color::new(0, 0, 0)} else {Color::new(1, 1, 1)}
+    }
+}
+
+#[test]
+fn transparent_masks_let_rays_through_to_what_is_behind() {
+    let mut world = HittableList::default();
+    world.add(Arc::new(AlphaMask::new(square(1.0), Arc::new(0.0), 0.5)));
+    world.add(square(0.0));
+
+    let rec = world.hit(&down(0.2, 0.3), ALL).expect("the square behind should be hit");
+    assert!((rec.t - 5.0).abs() < 1e-9, "hit at {} rather than on the square behind", rec.t);
+
+    // with nothing behind, nothing is hit
+    let mask = AlphaMask::new(square(1.0), Arc::new(0.0), 0.5);
+    assert!(mask.hit(&down(0.2, 0.3), ALL).is_none());
+}
+
+#[test]
+fn opaque_masks_change_nothing() {
+    let sphere: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(0, 0, 0), 1.0, grey()));
+    let masks = [AlphaMask::new(sphere.clone(), Arc::new(1.0), 0.5), AlphaMask::stochastic(sphere.clone(), Arc::new(1.0))];
+    for mask in masks {
+        for (x, y) in [(0.0, 0.0), (0.5, -0.3), (-0.9, 0.1), (0.8, 0.8)] {
+            let r = down(x, y);
+            let (masked, bare) = (mask.hit(&r, ALL), sphere.hit(&r, ALL));
+            let (Some(masked), Some(bare)) = (&masked, &bare) else {
+                assert_eq!(masked.is_some(), bare.is_some());
+                continue;
+            };
+            assert_eq!(masked.t, bare.t);
+            assert_eq!(masked.p, bare.p);
+            assert_eq!(masked.normal, bare.normal);
+            assert_eq!((masked.u, masked.v, masked.front_face), (bare.u, bare.v, bare.front_face));
+        }
+    }
+}
+
+#[test]
+fn thresholds_cut_hard_edges() {
+    let mask = AlphaMask::new(square(0.0), Arc::new(Ramp), 0.25);
+    for i in 0..40 {
+        // u is a quarter of the way across at x = -0.5
+        let x = -0.975 + 0.05 * i as f64;
+        assert_eq!(mask.hit(&down(x, 0.1), ALL).is_some(), x > -0.5, "at {x}");
+    }
+}
+
+#[test]
+fn stochastic_masks_cover_as_much_as_they_are_opaque() {
+    let samples = 100_000;
+    let mut rng = Rng::new(7, 4);
+    with_rng(&mut rng, || {
+        for alpha in [0.0, 0.3, 0.75] {
+            let mask = AlphaMask::stochastic(square(0.0), Arc::new(alpha));
+            let hits = (0..samples).filter(|_| mask.hit(&down(0.2, 0.3), ALL).is_some()).count();
+            let covered = hits as f64 / samples as f64;
+            assert!((covered - alpha).abs() < 0.005, "an opacity of {alpha} covers {covered}");
+        }
+    });
+}
+
+// rays passing through a hole keep going, and can hit the same object again further on
+#[test]
+fn rays_go_on_past_holes() {
+    let sphere = Arc::new(Sphere::new(Point3::new(0, 0, 0), 1.0, grey()));
+    let mask = AlphaMask::new(sphere, Arc::new(FarSide), 0.5);
+    let rec = mask.hit(&down(0.2, 0.3), ALL).expect("the far side of the sphere should be hit");
+    assert!(rec.p.z < 0.0 && !rec.front_face, "hit at {:?} rather than inside the far side", rec.p);
+
+    // but not past the end of the ray
+    assert!(mask.hit(&down(0.2, 0.3), Interval::new(0.001, 5.0)).is_none());
+}
+
+// the camera sees straight through a transparent object, and the light reaching a surface through
+// one isn't shadowed by it
+#[test]
+fn rendering_sees_through_holes() {
+    let sky = Color::new(0.4, 0.6, 0.9);
+    let render = |world: &HittableList, samples: u32| CameraBuilder::new()
+        .width(16)
+        .aspect_ratio(1.0)
+        .samples_per_pixel(samples)
+        .max_depth(8)
+        .environment(Arc::new(ConstantEnvironment::new(sky)))
+        .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build()
+        .render(world);
+
+    let mut world = HittableList::default();
+    let veil = Arc::new(Sphere::new(Point3::new(0, 0, -3), 2.0, grey()));
+    world.add(Arc::new(AlphaMask::new(veil, Arc::new(0.0), 0.5)));
+    let fb = render(&world, 4);
+    for y in 0..fb.height() {
+        for x in 0..fb.width() {
+            assert!((fb.pixel(x, y) - sky).length() < 1e-9, "the pixel at {x}, {y} is {:?}", fb.pixel(x, y));
+        }
+    }
+
+    // a sphere inside a transparent shell looks as it does without it
+    let average = |world: &HittableList| {
+        let fb = render(world, 256);
+        let mut sum = Color::new(0, 0, 0);
+        for y in 0..fb.height() {
+            for x in 0..fb.width() {
+                sum += fb.pixel(x, y);
+            }
+        }
+        sum / (fb.width() * fb.height()) as f64
+    };
+    let ball: Arc<dyn Hittable> = Arc::new(Sphere::new(Point3::new(0, 0, -3), 1.0, grey()));
+    let mut bare = HittableList::default();
+    bare.add(ball.clone());
+    let mut shelled = HittableList::default();
+    shelled.add(ball);
+    shelled.add(Arc::new(AlphaMask::new(Arc::new(Sphere::new(Point3::new(0, 0, -3), 1.5, grey())), Arc::new(0.0), 0.5)));
+    let (bare, shelled) = (average(&bare), average(&shelled));
+    assert!((bare - shelled).length() < 0.01, "the ball is {shelled:?} in its shell and {bare:?} without");
+}
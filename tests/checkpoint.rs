@@ -0,0 +1,112 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use raytracing::{Camera, CameraBuilder, Checkpoint, Color, ConstantEnvironment, Framebuffer, HittableList, Lambertian, Metal, Point3, Sphere, Vec3};
+
+fn path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("raytracing-{}-{name}.ckpt", std::process::id()))
+}
+
+fn scene() -> HittableList {
+    let mut world = HittableList::default();
+    world.add(Arc::new(Sphere::new(Point3::new(-0.6, 0.0, -2.0), 0.5, Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.2))))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.6, 0.0, -2.0), 0.5, Arc::new(Metal::new(Color::new(0.7, 0.7, 0.9), 0.2)))));
+    world
+}
+
+fn camera(samples: u32) -> Camera {
+    CameraBuilder::new()
+        .width(20)
+        .aspect_ratio(1.0)
+        .samples_per_pixel(samples)
+        .environment(Arc::new(ConstantEnvironment::new(Color::new(0.5, 0.6, 0.7))))
+        .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build()
+}
+
+fn assert_same(a: &Framebuffer, b: &Framebuffer) {
+    assert_eq!(a.samples(), b.samples());
+    for y in 0..a.height() {
+        for x in 0..a.width() {
+            assert_eq!(a.pixel(x, y), b.pixel(x, y), "the colour at {x}, {y} changed");
+            assert_eq!(a.depth(x, y).to_bits(), b.depth(x, y).to_bits(), "the depth at {x}, {y} changed");
+            assert_eq!(a.normal(x, y), b.normal(x, y), "the normal at {x}, {y} changed");
+            assert_eq!(a.albedo(x, y), b.albedo(x, y), "the albedo at {x}, {y} changed");
+            assert_eq!(a.position(x, y), b.position(x, y), "the position at {x}, {y} changed");
+            assert_eq!(a.object_id(x, y), b.object_id(x, y), "the object ID at {x}, {y} changed");
+            assert_eq!(a.material_id(x, y), b.material_id(x, y), "the material ID at {x}, {y} changed");
+        }
+    }
+}
+
+// resuming a finished render loads everything it saved, and has nothing left to add
+#[test]
+fn checkpoints_keep_every_pass() {
+    let path = path("round-trip");
+    let checkpoint = Checkpoint::new(&path, Duration::from_secs(3600));
+    let (world, cam) = (scene(), camera(6));
+
+    let saved = cam.render_checkpointed(&world, &checkpoint, false).unwrap();
+    let loaded = cam.render_checkpointed(&world, &checkpoint, true).unwrap();
+    assert_same(&saved, &loaded);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn resuming_stops_at_the_samples_asked_for() {
+    let path = path("resume");
+    let checkpoint = Checkpoint::new(&path, Duration::from_secs(3600));
+    let world = scene();
+
+    let first = camera(4).render_checkpointed(&world, &checkpoint, false).unwrap();
+    assert_eq!(first.samples(), 4);
+    let saved = fs::read(&path).unwrap();
+
+    // carrying on takes the render up to the new count and no further, and the same way every time
+    // from the same checkpoint
+    let more = camera(10).render_checkpointed(&world, &checkpoint, true).unwrap();
+    assert_eq!(more.samples(), 10);
+    fs::write(&path, &saved).unwrap();
+    let again = camera(10).render_checkpointed(&world, &checkpoint, true).unwrap();
+    assert_same(&more, &again);
+
+    // and a render that already has more samples than asked for is left as it is
+    let fewer = camera(3).render_checkpointed(&world, &checkpoint, true).unwrap();
+    assert_same(&more, &fewer);
+    fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn checkpoints_from_other_scenes_are_refused() {
+    let path = path("mismatch");
+    let checkpoint = Checkpoint::new(&path, Duration::from_secs(3600));
+    let world = scene();
+    camera(2).render_checkpointed(&world, &checkpoint, false).unwrap();
+
+    // a different camera
+    let moved = CameraBuilder::new()
+        .width(20)
+        .aspect_ratio(1.0)
+        .samples_per_pixel(4)
+        .environment(Arc::new(ConstantEnvironment::new(Color::new(0.5, 0.6, 0.7))))
+        .set_view(Point3::new(0.1, 0.0, 0.0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build();
+    let error = moved.render_checkpointed(&world, &checkpoint, true).err().expect("a moved camera shouldn't resume");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    // a different scene
+    let mut other = scene();
+    other.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, -1.5), 0.2, Arc::new(Lambertian::new(Color::new(0.1, 0.9, 0.1))))));
+    let error = camera(4).render_checkpointed(&other, &checkpoint, true).err().expect("another scene shouldn't resume");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+    // and the checkpoint is still there for the right scene
+    assert_eq!(camera(4).render_checkpointed(&world, &checkpoint, true).unwrap().samples(), 4);
+    fs::remove_file(&path).unwrap();
+
+    let error = camera(4).render_checkpointed(&world, &checkpoint, true).err().expect("there's nothing to resume");
+    assert_eq!(error.kind(), io::ErrorKind::NotFound);
+}
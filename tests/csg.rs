@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use raytracing::{Color, Cone, Csg, Cylinder, Lambertian, Material, Paraboloid, Point3, Ray, SdfHittable, SdfSphere, SdfTorus, Solid, Sphere, Torus, Vec3};
+
+fn mat() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
+
+// where along the ray each span starts and ends, checking that entries and exits face the right way
+fn intervals(solid: &dyn Solid, r: &Ray) -> Vec<(f64, f64)> {
+    solid.spans(r)
+        .into_iter()
+        .map(|span| {
+            assert!(span.enter.front_face && !span.exit.front_face);
+            (span.enter.t, span.exit.t)
+        })
+        .collect()
+}
+
+fn assert_intervals(solid: &dyn Solid, r: &Ray, expected: &[(f64, f64)], tolerance: f64) {
+    let found = intervals(solid, r);
+    assert_eq!(found.len(), expected.len(), "spans {found:?} rather than {expected:?}");
+    for (&(a, b), &(x, y)) in found.iter().zip(expected) {
+        assert!((a - x).abs() < tolerance && (b - y).abs() < tolerance, "spans {found:?} rather than {expected:?}");
+    }
+}
+
+#[test]
+fn capped_quadrics_are_solid() {
+    let cylinder = Cylinder::new(Point3::new(0, -1, 0), Vec3::new(0, 2, 0), 1.0, true, mat());
+    let cone = Cone::new(Point3::new(0, -1, 0), Vec3::new(0, 2, 0), 1.0, true, mat());
+    let paraboloid = Paraboloid::new(Point3::new(0, -1, 0), Vec3::new(0, 2, 0), 1.0, true, mat());
+
+    // down the axis, through both caps of the cylinder, and the tip and base of the others
+    let down = Ray::new(Point3::new(0, 5, 0), Vec3::new(0, -1, 0));
+    assert_intervals(&cylinder, &down, &[(4.0, 6.0)], 1e-9);
+    assert_intervals(&cone, &down, &[(4.0, 6.0)], 1e-9);
+    assert_intervals(&paraboloid, &down, &[(4.0, 6.0)], 1e-9);
+
+    // across, through the sides, from behind the ray's origin too
+    let across = Ray::new(Point3::new(0, 0, 0), Vec3::new(1, 0, 0));
+    assert_intervals(&cylinder, &across, &[(-1.0, 1.0)], 1e-9);
+    assert_intervals(&cone, &across, &[(-0.5, 0.5)], 1e-9);
+    assert_intervals(&paraboloid, &across, &[(-0.5f64.sqrt(), 0.5f64.sqrt())], 1e-9);
+}
+
+#[test]
+fn open_quadrics_have_no_inside() {
+    let cylinder = Cylinder::new(Point3::new(0, -1, 0), Vec3::new(0, 2, 0), 1.0, false, mat());
+    let r = Ray::new(Point3::new(0, 0, 5), Vec3::new(0, 0, -1));
+    assert!(intervals(&cylinder, &r).is_empty());
+}
+
+#[test]
+fn tori_are_solid() {
+    let torus = Torus::new(Point3::new(0, 0, 0), Vec3::new(0, 1, 0), 2.0, 0.5, mat());
+    let r = Ray::new(Point3::new(-5, 0, 0), Vec3::new(1, 0, 0));
+    assert_intervals(&torus, &r, &[(2.5, 3.5), (6.5, 7.5)], 1e-6);
+}
+
+#[test]
+fn distance_functions_are_solid() {
+    let sphere = SdfHittable::new(Arc::new(SdfSphere::new(Point3::new(0, 0, 0), 1.0)), mat());
+    let r = Ray::new(Point3::new(0, 0, 5), Vec3::new(0, 0, -1));
+    assert_intervals(&sphere, &r, &[(4.0, 6.0)], 1e-3);
+
+    let torus = SdfHittable::new(Arc::new(SdfTorus::new(Point3::new(0, 0, 0), 2.0, 0.5)), mat());
+    let r = Ray::new(Point3::new(-5, 0, 0), Vec3::new(1, 0, 0));
+    assert_intervals(&torus, &r, &[(2.5, 3.5), (6.5, 7.5)], 1e-3);
+}
+
+#[test]
+fn solids_other_than_spheres_combine() {
+    let cylinder = Arc::new(Cylinder::new(Point3::new(0, -1, 0), Vec3::new(0, 2, 0), 1.0, true, mat()));
+    let hole = Arc::new(Sphere::new(Point3::new(0, 0, 0), 0.5, mat()));
+    let cut = Csg::difference(cylinder, hole);
+
+    let down = Ray::new(Point3::new(0, 5, 0), Vec3::new(0, -1, 0));
+    assert_intervals(&cut, &down, &[(4.0, 4.5), (5.5, 6.0)], 1e-9);
+}
@@ -0,0 +1,49 @@
+use std::sync::Arc;
+
+use raytracing::{Camera, CameraBuilder, Color, Denoiser, Framebuffer, HittableList, Lambertian, Metal, Point3, Sphere, Vec3};
+
+fn scene() -> HittableList {
+    let mut world = HittableList::default();
+
+    let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let diffuse = Arc::new(Lambertian::new(Color::new(0.7, 0.2, 0.1)));
+    let metal = Arc::new(Metal::new(Color::new(0.8, 0.8, 0.8), 0.3));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, -100.5, -1.0), 100.0, ground)));
+    world.add(Arc::new(Sphere::new(Point3::new(-0.5, 0.0, -1.2), 0.5, diffuse)));
+    world.add(Arc::new(Sphere::new(Point3::new(0.6, 0.0, -1.0), 0.4, metal)));
+
+    world
+}
+
+fn camera(samples: u32) -> Camera {
+    CameraBuilder::new()
+        .width(48)
+        .samples_per_pixel(samples)
+        .max_depth(8)
+        .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+        .build()
+}
+
+fn mean_squared_error(fb: &Framebuffer, reference: &Framebuffer) -> f64 {
+    let mut sum = 0.0;
+    for y in 0..fb.height() {
+        for x in 0..fb.width() {
+            sum += (fb.pixel(x, y) - reference.pixel(x, y)).length_squared();
+        }
+    }
+    sum / (fb.width() * fb.height()) as f64
+}
+
+#[test]
+fn denoising_reduces_error_against_reference() {
+    let world = scene();
+    let reference = camera(1024).render(&world);
+
+    let mut fb = camera(4).render(&world);
+    let noisy_error = mean_squared_error(&fb, &reference);
+
+    fb.denoise(&Denoiser::default());
+    let denoised_error = mean_squared_error(&fb, &reference);
+
+    assert!(denoised_error < 0.75 * noisy_error, "denoised MSE {denoised_error} vs noisy MSE {noisy_error}");
+}
@@ -0,0 +1,86 @@
+use std::collections::HashMap;
+
+use raytracing::{Mesh, Point3, Vec3, Weighting};
+
+// a unit cube centred on the origin, its faces as quads facing outwards
+fn cube() -> Mesh {
+    let mut mesh = Mesh::default();
+    for i in 0..8 {
+        let corner = |bit: usize| if i & bit == 0 {-0.5} else {0.5};
+        mesh.positions.push(Point3::new(corner(1), corner(2), corner(4)));
+    }
+    mesh.quads = vec![[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]];
+    mesh
+}
+
+fn key(p: Point3) -> [u64; 3] {
+    [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()]
+}
+
+// the ends of every triangle's edges, in order around it
+fn edges(mesh: &Mesh) -> Vec<(Point3, Point3)> {
+    mesh.triangles.iter().flat_map(|&t| (0..3).map(move |i| (t[i], t[(i + 1) % 3])))
+        .map(|(a, b)| (mesh.positions[a], mesh.positions[b]))
+        .collect()
+}
+
+#[test]
+fn tessellated_edges_are_no_longer_than_asked() {
+    let sliver = Mesh {
+        positions: vec![Point3::new(0, 0, 0), Point3::new(10, 0, 0), Point3::new(5.0, 0.1, 0.0)],
+        triangles: vec![[0, 1, 2]],
+        ..Mesh::default()
+    };
+
+    for mesh in [cube(), sliver] {
+        for max_edge in [0.9, 0.3, 0.07] {
+            let tessellated = mesh.tessellate(max_edge);
+            for (a, b) in edges(&tessellated) {
+                assert!((a - b).length() <= max_edge, "an edge from {a:?} to {b:?} is longer than {max_edge}");
+            }
+        }
+    }
+}
+
+#[test]
+#[should_panic]
+fn tessellating_infinite_positions_panics() {
+    let mesh = Mesh {
+        positions: vec![Point3::new(0, 0, 0), Point3::new(f64::INFINITY, 0.0, 0.0), Point3::new(0, 1, 0)],
+        triangles: vec![[0, 1, 2]],
+        ..Mesh::default()
+    };
+    mesh.tessellate(0.5);
+}
+
+// with sharp edges, the cube's vertices are split between its faces, so vertices in the same place
+// have to move together for it to stay closed
+#[test]
+fn constant_displacement_moves_out_by_the_scale_without_cracks() {
+    let mut mesh = cube();
+    mesh.compute_normals(Weighting::Angle, 30.0);
+    let mesh = mesh.tessellate(0.3);
+    let scale = 0.1;
+    let displaced = mesh.displace(&1.0, scale, 30.0);
+
+    // each place moves by the scale along the sum of the normals of the faces it's on
+    let moved: Vec<Point3> = mesh.positions.iter().map(|&p| {
+        let along = |x: f64| if x.abs() == 0.5 {x.signum()} else {0.0};
+        p + scale * Vec3::new(along(p.x), along(p.y), along(p.z)).unit_vector()
+    }).collect();
+
+    for &q in &displaced.positions {
+        let error = moved.iter().map(|&p| (p - q).length()).fold(f64::INFINITY, f64::min);
+        assert!(error < 1e-12, "{q:?} isn't where any vertex should have moved to");
+    }
+
+    // every edge, matched up by position, has a triangle either side of it
+    let mut sides: HashMap<([u64; 3], [u64; 3]), i32> = HashMap::new();
+    for (a, b) in edges(&displaced) {
+        *sides.entry((key(a), key(b))).or_default() += 1;
+    }
+    for (&(a, b), &count) in &sides {
+        assert_eq!(count, 1, "an edge is used {count} times the same way round");
+        assert_eq!(sides.get(&(b, a)), Some(&1), "an edge has a crack along it");
+    }
+}
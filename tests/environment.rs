@@ -0,0 +1,155 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{CameraBuilder, Color, ConstantEnvironment, Environment, HittableList, ImageEnvironment, Lambertian, Point3, PreethamSky, Sphere, Vec3};
+
+// the integral of `f` over the sphere of directions around `axis`, as sums over a grid of equal areas
+// in each band between two cosines of the angle to the axis, so that small bright spots around the
+// axis can be given bands of their own
+fn integrate(f: impl Fn(Vec3) -> f64, axis: Vec3, bands: &[(f64, f64, usize)]) -> f64 {
+    let columns = 2048;
+    let (x, z) = perpendicular(axis);
+    let mut sum = 0.0;
+    for &(top, bottom, rows) in bands {
+        let area = 2.0 * PI * (top - bottom) / (rows * columns) as f64;
+        for i in 0..rows {
+            let cos = top - (top - bottom) * (i as f64 + 0.5) / rows as f64;
+            let sin = (1.0 - cos * cos).max(0.0).sqrt();
+            for j in 0..columns {
+                let phi = 2.0 * PI * (j as f64 + 0.5) / columns as f64;
+                sum += f(sin * phi.cos() * x + sin * phi.sin() * z + cos * axis) * area;
+            }
+        }
+    }
+    sum
+}
+
+// two directions at right angles to `axis` and to each other
+fn perpendicular(axis: Vec3) -> (Vec3, Vec3) {
+    let other = if axis.x.abs() > 0.9 {Vec3::new(0, 1, 0)} else {Vec3::new(1, 0, 0)};
+    let x = axis.cross(&other).unit_vector();
+    (x, axis.cross(&x))
+}
+
+// a dim sky with a gradient, a patch of bright blue and a single dazzling pixel
+fn panorama() -> ImageEnvironment {
+    let (width, height) = (64, 32);
+    let mut pixels = Vec::new();
+    for j in 0..height {
+        for i in 0..width {
+            let mut c = Color::new(0.1, 0.2, 0.3) * (1.0 + i as f64 / width as f64);
+            if (20..30).contains(&i) && (4..10).contains(&j) {
+                c = Color::new(0.5, 0.5, 4.0);
+            }
+            if (i, j) == (50, 14) {
+                c = Color::new(500.0, 450.0, 400.0);
+            }
+            pixels.push(c);
+        }
+    }
+    ImageEnvironment::new(width, height, pixels, 0.0, 1.0)
+}
+
+// bands lined up with the panorama's rows of pixels, as the grid's columns are with its columns, since
+// the density jumps from one pixel to the next
+fn pixel_bands() -> Vec<(f64, f64, usize)> {
+    (0..32).map(|j| ((PI * j as f64 / 32.0).cos(), (PI * (j + 1) as f64 / 32.0).cos(), 32)).collect()
+}
+
+#[test]
+fn panorama_pdf_integrates_to_one() {
+    let env = panorama();
+    let total = integrate(|d| env.pdf(d), Vec3::new(0, 1, 0), &pixel_bands());
+    assert!((total - 1.0).abs() < 1e-3, "the pdf integrates to {total}");
+}
+
+// sampled directions fall in each part of the sphere as often as the pdf says
+#[test]
+fn panorama_samples_follow_the_pdf() {
+    let env = panorama();
+    let samples = 200_000;
+    let bin = |d: Vec3| {
+        let band = (((d.y + 1.0) * 3.0) as usize).min(5);
+        let quarter = ((d.z.atan2(d.x) + PI) / (0.5 * PI)) as usize % 4;
+        4 * band + quarter
+    };
+
+    let mut rng = Rng::new(5, 8);
+    let mut found = [0.0; 24];
+    with_rng(&mut rng, || {
+        for _ in 0..samples {
+            let (direction, radiance, pdf) = env.sample();
+            assert!((pdf - env.pdf(direction)).abs() <= 1e-9 * pdf, "sampled with a pdf of {pdf} rather than {}", env.pdf(direction));
+            assert!((radiance - env.radiance(direction)).length() <= 1e-9 * radiance.length());
+            found[bin(direction)] += 1.0 / samples as f64;
+        }
+    });
+
+    for (i, &found) in found.iter().enumerate() {
+        let expected = integrate(|d| if bin(d) == i {env.pdf(d)} else {0.0}, Vec3::new(0, 1, 0), &pixel_bands());
+        assert!((found - expected).abs() < 0.005, "{found} of the samples are in bin {i} rather than {expected}");
+    }
+}
+
+// the sun is a tiny disk a quarter of a degree across, so it gets fine bands of its own
+fn sun_bands() -> Vec<(f64, f64, usize)> {
+    let edge = 0.004654f64.cos();
+    vec![(1.0, edge, 64), (edge, -1.0, 1000)]
+}
+
+#[test]
+fn sky_pdf_integrates_to_one() {
+    for elevation in [60.0, 3.0, -10.0] {
+        let sun = PreethamSky::sun_direction(elevation, 40.0);
+        let sky = PreethamSky::new(sun, 3.0, 1.0);
+        let total = integrate(|d| sky.pdf(d), sun, &sun_bands());
+        assert!((total - 1.0).abs() < 1e-3, "with the sun at {elevation}°, the pdf integrates to {total}");
+    }
+}
+
+#[test]
+fn sky_samples_follow_the_pdf() {
+    let sun = PreethamSky::sun_direction(35.0, 120.0);
+    let sky = PreethamSky::new(sun, 3.0, 1.0);
+    let samples = 200_000;
+
+    let mut rng = Rng::new(2, 9);
+    let mut in_sun = 0.0;
+    with_rng(&mut rng, || {
+        for _ in 0..samples {
+            let (direction, radiance, pdf) = sky.sample();
+            assert!((pdf - sky.pdf(direction)).abs() <= 1e-9 * pdf, "sampled with a pdf of {pdf} rather than {}", sky.pdf(direction));
+            assert!((radiance - sky.radiance(direction)).length() <= 1e-9 * radiance.length());
+            if direction.dot(&sun) >= 0.004654f64.cos() {
+                in_sun += 1.0 / samples as f64;
+            }
+        }
+    });
+
+    let expected = integrate(|d| sky.pdf(d), sun, &sun_bands()[..1]);
+    assert!((in_sun - expected).abs() < 0.005, "{in_sun} of the samples are in the sun rather than {expected}");
+}
+
+// with no bounces allowed, objects are black but the environment is still seen past them
+#[test]
+fn rendering_without_bounces_sees_only_the_environment() {
+    let sky = Color::new(0.3, 0.6, 0.9);
+    let mut world = HittableList::default();
+    world.add(Arc::new(Sphere::new(Point3::new(0, 0, -2), 0.5, Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))))));
+
+    for depth in [0, 1] {
+        let fb = CameraBuilder::new()
+            .width(16)
+            .aspect_ratio(1.0)
+            .samples_per_pixel(4)
+            .max_depth(depth)
+            .environment(Arc::new(ConstantEnvironment::new(sky)))
+            .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+            .build()
+            .render(&world);
+
+        assert_eq!(fb.pixel(8, 8), Color::new(0, 0, 0), "with a depth of {depth}, the sphere isn't black");
+        assert!((fb.pixel(0, 0) - sky).length() < 1e-9, "with a depth of {depth}, the sky is {:?}", fb.pixel(0, 0));
+    }
+}
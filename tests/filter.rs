@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use raytracing::{BoxFilter, CameraBuilder, ConstantEnvironment, Filter, GaussianFilter, HittableList, LanczosFilter, MitchellFilter, TentFilter, Color, Point3, Vec3};
+
+fn filters() -> Vec<(&'static str, Arc<dyn Filter>)> {
+    vec![
+        ("box", Arc::new(BoxFilter::new(0.5))),
+        ("wide box", Arc::new(BoxFilter::new(1.5))),
+        ("tent", Arc::new(TentFilter::new(1.0))),
+        ("gaussian", Arc::new(GaussianFilter::new(1.5, 0.5))),
+        ("mitchell", Arc::new(MitchellFilter::new(2.0, 1.0 / 3.0, 1.0 / 3.0))),
+        ("narrow mitchell", Arc::new(MitchellFilter::new(1.0, 1.0 / 3.0, 1.0 / 3.0))),
+        ("lanczos", Arc::new(LanczosFilter::new(3.0))),
+    ]
+}
+
+#[test]
+fn filters_integrate_to_one() {
+    for (name, filter) in filters() {
+        let radius = filter.radius();
+        let steps = 100_000;
+        let step = 2.0 * radius / steps as f64;
+        let integral: f64 = (0..steps).map(|i| filter.evaluate(-radius + (i as f64 + 0.5) * step)).sum::<f64>() * step;
+        assert!((integral - 1.0).abs() < 1e-3, "{name} filter integrates to {integral}");
+    }
+}
+
+#[test]
+fn filters_are_zero_outside_their_radius() {
+    for (name, filter) in filters() {
+        let radius = filter.radius();
+        for i in 0..100 {
+            let x = radius + i as f64 * 0.05;
+            assert_eq!(filter.evaluate(x), 0.0, "{name} filter at {x}");
+            assert_eq!(filter.evaluate(-x), 0.0, "{name} filter at {}", -x);
+        }
+    }
+}
+
+// with the same colour coming from every direction, every pixel should come out that colour, including
+// those along the edges of tiles, whose filtered sums are gathered from the tiles either side
+#[test]
+fn splats_across_tiles_add_up() {
+    let sky = Color::new(0.25, 0.5, 0.75);
+    let world = HittableList::default();
+
+    for (name, filter) in filters() {
+        let fb = CameraBuilder::new()
+            .width(40)
+            .aspect_ratio(1.0)
+            .samples_per_pixel(4)
+            .environment(Arc::new(ConstantEnvironment::new(sky)))
+            .filter(filter)
+            .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+            .build()
+            .render(&world);
+
+        for y in 0..fb.height() {
+            for x in 0..fb.width() {
+                let error = (fb.pixel(x, y) - sky).length();
+                assert!(error < 1e-9, "{name} filter is off by {error} at {x},{y}");
+            }
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use std::fs;
+use std::io::{self, Cursor};
+use std::path::PathBuf;
+
+use image::{ImageFormat, Rgb, RgbImage};
+use raytracing::{Hittable, Interval, Mesh, Point3, Ray, Scene, Vec3};
+
+// six vertices in the xy plane, facing +z
+const POSITIONS: [[f32; 3]; 6] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [1.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]];
+const UVS: [[f32; 2]; 6] = [[0.1, 0.2], [0.9, 0.2], [0.1, 0.7], [0.9, 0.7], [0.0, 0.25], [0.5, 1.0]];
+
+// the indices of a triangle, a strip of two and a fan of three, each facing +z
+const TRIANGLE: [u16; 3] = [0, 1, 2];
+const STRIP: [u16; 4] = [0, 1, 2, 3];
+const FAN: [u16; 5] = [0, 1, 3, 2, 4];
+
+// the meshes in every file: the triangle, strip and fan with a metallic-roughness texture that's only
+// metal, then the triangle again with one that's only rough
+const MESHES: &str = r#"[
+    {"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}, "indices": 3, "material": 0}]},
+    {"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}, "indices": 4, "material": 0, "mode": 5}]},
+    {"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}, "indices": 5, "material": 0, "mode": 6}]},
+    {"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1, "TEXCOORD_0": 2}, "indices": 3, "material": 1}]}
+]"#;
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            out.push(if i <= chunk.len() {ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char} else {'='});
+        }
+    }
+    out
+}
+
+// a one pixel PNG, as a data URI
+fn png(pixel: [u8; 3]) -> String {
+    let mut bytes = Cursor::new(Vec::new());
+    RgbImage::from_pixel(1, 1, Rgb(pixel)).write_to(&mut bytes, ImageFormat::Png).unwrap();
+    format!("data:image/png;base64,{}", base64(bytes.get_ref()))
+}
+
+// the vertices and indices, and the views and accessors into them
+fn buffer() -> (Vec<u8>, String, String) {
+    let mut bytes = Vec::new();
+    let mut views = Vec::new();
+    let mut accessors = Vec::new();
+    let mut add = |data: Vec<u8>, accessor: String| {
+        views.push(format!(r#"{{"buffer": 0, "byteOffset": {}, "byteLength": {}}}"#, bytes.len(), data.len()));
+        accessors.push(format!(r#"{{"bufferView": {}, {accessor}}}"#, views.len() - 1));
+        bytes.extend(data);
+        while bytes.len() % 4 != 0 {
+            bytes.push(0);
+        }
+    };
+
+    let floats = |values: &[f32]| values.iter().flat_map(|v| v.to_le_bytes()).collect::<Vec<u8>>();
+    add(floats(POSITIONS.as_flattened()), r#""componentType": 5126, "count": 6, "type": "VEC3", "min": [-1, -1, 0], "max": [1, 1, 0]"#.into());
+    add(floats(&[[0.0, 0.0, 1.0f32]; 6].concat()), r#""componentType": 5126, "count": 6, "type": "VEC3""#.into());
+    add(floats(UVS.as_flattened()), r#""componentType": 5126, "count": 6, "type": "VEC2""#.into());
+    for indices in [&TRIANGLE[..], &STRIP, &FAN] {
+        let data = indices.iter().flat_map(|i| i.to_le_bytes()).collect();
+        add(data, format!(r#""componentType": 5123, "count": {}, "type": "SCALAR""#, indices.len()));
+    }
+
+    (bytes, views.join(", "), accessors.join(", "))
+}
+
+// a glTF file of `nodes`, of which the scene is the first, with its buffer at `uri`, or embedded
+fn document(name: &str, nodes: &str, uri: Option<&str>) -> PathBuf {
+    let (bytes, views, accessors) = buffer();
+    let uri = uri.map_or_else(|| format!("data:application/octet-stream;base64,{}", base64(&bytes)), String::from);
+    let json = format!(r#"{{
+        "asset": {{"version": "2.0"}},
+        "scene": 0,
+        "scenes": [{{"nodes": [0]}}],
+        "nodes": {nodes},
+        "meshes": {MESHES},
+        "materials": [
+            {{"pbrMetallicRoughness": {{"metallicRoughnessTexture": {{"index": 0}}}}}},
+            {{"pbrMetallicRoughness": {{"metallicRoughnessTexture": {{"index": 1}}}}}}
+        ],
+        "textures": [{{"source": 0}}, {{"source": 1}}],
+        "images": [{{"uri": "{}"}}, {{"uri": "{}"}}],
+        "buffers": [{{"byteLength": {}, "uri": "{uri}"}}],
+        "bufferViews": [{views}],
+        "accessors": [{accessors}]
+    }}"#, png([0, 0, 255]), png([0, 255, 0]), bytes.len());
+
+    let path = std::env::temp_dir().join(format!("raytracing-{}-{name}.gltf", std::process::id()));
+    fs::write(&path, json).unwrap();
+    path
+}
+
+fn load(name: &str, nodes: &str) -> io::Result<Scene> {
+    let path = document(name, nodes, None);
+    let scene = Scene::load_gltf(&path);
+    fs::remove_file(path).unwrap();
+    scene
+}
+
+// the direction each triangle faces, by its winding
+fn facing(mesh: &Mesh) -> Vec<Vec3> {
+    mesh.triangles.iter().map(|&[a, b, c]| {
+        let (a, b, c) = (mesh.positions[a], mesh.positions[b], mesh.positions[c]);
+        (b - a).cross(&(c - a)).unit_vector()
+    }).collect()
+}
+
+// a parent moved along, holding a child turned a quarter around z and doubled in size
+#[test]
+fn node_transforms_are_flattened_into_the_world() {
+    let scene = load("hierarchy", r#"[
+        {"translation": [1, 2, 3], "children": [1]},
+        {"rotation": [0, 0, 0.70710678, 0.70710678], "scale": [2, 2, 2], "mesh": 0}
+    ]"#).unwrap();
+
+    let [(mesh, _)] = &scene.meshes[..] else {panic!("{} meshes loaded", scene.meshes.len())};
+    for (&p, &original) in mesh.positions.iter().zip(&POSITIONS) {
+        let expected = Point3::new(1.0 - 2.0 * original[1] as f64, 2.0 + 2.0 * original[0] as f64, 3.0);
+        assert!((p - expected).length() < 1e-6, "{original:?} is at {p:?} rather than {expected:?}");
+    }
+    for n in &mesh.normals {
+        assert!((*n - Vec3::new(0, 0, 1)).length() < 1e-6, "a normal turned to {n:?}");
+    }
+}
+
+// mirroring the mesh flips its triangles' winding, which is turned back so they face the way their
+// normals do
+#[test]
+fn mirrored_meshes_keep_facing_out() {
+    let scene = load("mirrored", r#"[{"scale": [-1, 1, 1], "mesh": 0}]"#).unwrap();
+    let (mesh, _) = &scene.meshes[0];
+    assert!((mesh.positions[1] - Point3::new(-1, 0, 0)).length() < 1e-6);
+    for (facing, n) in facing(mesh).into_iter().zip(&mesh.normals) {
+        assert!((facing - Vec3::new(0, 0, 1)).length() < 1e-6, "a mirrored triangle faces {facing:?}");
+        assert!((*n - Vec3::new(0, 0, 1)).length() < 1e-6, "a mirrored normal is {n:?}");
+    }
+}
+
+#[test]
+fn strips_and_fans_are_split_into_triangles() {
+    let scene = load("strips", r#"[{"children": [1, 2]}, {"mesh": 1}, {"mesh": 2}]"#).unwrap();
+    let [(strip, _), (fan, _)] = &scene.meshes[..] else {panic!("{} meshes loaded", scene.meshes.len())};
+
+    assert_eq!(strip.triangles, [[0, 1, 2], [2, 1, 3]]);
+    assert_eq!(fan.triangles, [[0, 1, 3], [0, 3, 2], [0, 2, 4]]);
+    // every other triangle of the strip is turned round to face the same way as the rest
+    for facing in facing(strip).into_iter().chain(facing(fan)) {
+        assert!((facing - Vec3::new(0, 0, 1)).length() < 1e-6, "a triangle faces {facing:?}");
+    }
+}
+
+#[test]
+fn texture_coordinates_run_up_from_the_bottom() {
+    let scene = load("uvs", r#"[{"mesh": 0}]"#).unwrap();
+    let (mesh, _) = &scene.meshes[0];
+    for (&(u, v), &[gltf_u, gltf_v]) in mesh.uvs.iter().zip(&UVS) {
+        assert!((u - gltf_u as f64).abs() < 1e-6 && (v - (1.0 - gltf_v as f64)).abs() < 1e-6, "{gltf_u}, {gltf_v} became {u}, {v}");
+    }
+}
+
+// metalness comes from the blue channel of a metallic-roughness texture and roughness from the
+// green, so the first material is a smooth metal, and the second rough and not metal at all
+#[test]
+fn metalness_and_roughness_come_from_their_own_channels() {
+    let scene = load("materials", r#"[{"children": [1, 2]}, {"mesh": 0}, {"mesh": 3, "translation": [0, 0, -5]}]"#).unwrap();
+    let world = scene.world();
+
+    let reflections = |z: f64| {
+        let r = Ray::new(Point3::new(-0.2, 0.3, z + 1.0), Vec3::new(0.5, 0.0, -1.0));
+        let rec = world.hit(&r, Interval::new(0.001, 1.5)).expect("the triangle should be hit");
+        let mirror = Vec3::new(0.5, 0.0, 1.0).unit_vector();
+        (0..200).filter_map(|_| rec.mat.scatter(&r, &rec)).map(|(_, s)| s.dir.unit_vector().dot(&mirror)).collect::<Vec<f64>>()
+    };
+
+    let metal = reflections(0.0);
+    assert!(!metal.is_empty() && metal.iter().all(|&cos| cos > 0.999), "a smooth metal reflects {metal:?} off the mirror direction");
+    let rough = reflections(-5.0);
+    assert!(rough.iter().any(|&cos| cos < 0.9), "a rough material reflects like a mirror");
+}
+
+// buffers can be embedded in the file with either base64 alphabet, padded or not, and with line breaks
+#[test]
+fn data_uris_are_decoded() {
+    let (bytes, _, _) = buffer();
+    let encoded = base64(&bytes);
+    let url_safe = encoded.replace('+', "-").replace('/', "_").trim_end_matches('=').to_string();
+    let wrapped = encoded.as_bytes().chunks(76).map(|line| std::str::from_utf8(line).unwrap()).collect::<Vec<_>>().join("\\n");
+    assert!(encoded.contains(['+', '/']), "the buffer doesn't test the whole alphabet");
+
+    for (name, encoded) in [("standard", encoded.clone()), ("url-safe", url_safe), ("wrapped", wrapped)] {
+        let uri = format!("data:application/octet-stream;base64,{encoded}");
+        let path = document(name, r#"[{"mesh": 0}]"#, Some(&uri));
+        let scene = Scene::load_gltf(&path);
+        fs::remove_file(path).unwrap();
+        let scene = scene.unwrap_or_else(|e| panic!("the {name} buffer didn't load: {e}"));
+        assert_eq!(scene.meshes[0].0.positions.len(), 6);
+    }
+
+    for (name, uri) in [("not-base64", "data:application/octet-stream,plain"), ("bad-base64", "data:application/octet-stream;base64,AAAA*AAA")] {
+        let path = document(name, r#"[{"mesh": 0}]"#, Some(uri));
+        let error = Scene::load_gltf(&path).err().unwrap_or_else(|| panic!("the {name} buffer loaded"));
+        fs::remove_file(path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData, "the {name} buffer failed with {error}");
+    }
+}
+
+// buffers in files beside the glTF file are found by their names, with escapes such as %20 for a
+// space undone
+#[test]
+fn escaped_file_names_are_found() {
+    let (bytes, _, _) = buffer();
+    let dir = std::env::temp_dir().join(format!("raytracing-{}-escaped", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("vertices #1.bin"), bytes).unwrap();
+
+    let path = dir.join("scene.gltf");
+    fs::rename(document("escaped", r#"[{"mesh": 0}]"#, Some("vertices%20%231.bin")), &path).unwrap();
+    let scene = Scene::load_gltf(&path);
+    fs::remove_dir_all(dir).unwrap();
+    assert_eq!(scene.unwrap().meshes[0].0.positions.len(), 6);
+}
+
+// a node that is its own child is refused rather than followed forever
+#[test]
+fn node_loops_are_refused() {
+    let error = load("loop", r#"[{"children": [1]}, {"children": [0], "mesh": 0}]"#).err().expect("a looping hierarchy loaded");
+    assert_eq!(error.kind(), io::ErrorKind::InvalidData, "a looping hierarchy failed with {error}");
+}
@@ -0,0 +1,57 @@
+use std::sync::Arc;
+
+use raytracing::{Color, Hittable, HittableList, Interval, Lambertian, Material, Point3, Ray, Sphere, Vec3};
+
+// three spheres along the x axis, the outer two sharing a material
+fn scene() -> HittableList {
+    let shared: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let other: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.7, 0.2, 0.1)));
+
+    let mut world = HittableList::default();
+    world.add(Arc::new(Sphere::new(Point3::new(-2.0, 0.0, 0.0), 0.5, Arc::clone(&shared))));
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, other)));
+    world.add(Arc::new(Sphere::new(Point3::new(2.0, 0.0, 0.0), 0.5, shared)));
+    world
+}
+
+// the object and material IDs of what's straight down the z axis from `x`
+fn ids(world: &HittableList, x: f64) -> (u32, u32) {
+    let r = Ray::new(Point3::new(x, 0.0, 5.0), Vec3::new(0, 0, -1));
+    let rec = world.hit(&r, Interval::new(0.001, f64::INFINITY)).expect("ray should hit a sphere");
+    (rec.object_id, rec.material_id)
+}
+
+#[test]
+fn ids_count_up_in_order_and_share_materials() {
+    let world = scene();
+    assert_eq!(ids(&world, -2.0), (1, 1));
+    assert_eq!(ids(&world, 0.0), (2, 2));
+    assert_eq!(ids(&world, 2.0), (3, 1));
+}
+
+#[test]
+fn ids_do_not_depend_on_earlier_scenes() {
+    let first = scene();
+    let firsts: Vec<_> = [-2.0, 0.0, 2.0].map(|x| ids(&first, x)).to_vec();
+    drop(first);
+
+    // build and drop others, so their materials' memory may be reused
+    for _ in 0..10 {
+        drop(scene());
+    }
+
+    let again = scene();
+    let agains: Vec<_> = [-2.0, 0.0, 2.0].map(|x| ids(&again, x)).to_vec();
+    assert_eq!(firsts, agains);
+}
+
+#[test]
+fn nested_lists_are_numbered_by_the_outer_list() {
+    let mut world = HittableList::default();
+    world.add(Arc::new(Sphere::new(Point3::new(0.0, 10.0, 0.0), 0.5, Arc::new(Lambertian::new(Color::new(1, 1, 1))))));
+    world.add(Arc::new(scene()));
+
+    assert_eq!(ids(&world, -2.0), (2, 2));
+    assert_eq!(ids(&world, 0.0), (2, 3));
+    assert_eq!(ids(&world, 2.0), (2, 2));
+}
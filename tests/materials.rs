@@ -0,0 +1,221 @@
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{Coated, Color, Conductor, Dielectric, HenyeyGreenstein, HitRecord, Hittable, Interval, Lambertian, Material, Mesh, MixMaterial, Point3, PrincipledBuilder, Ray, RoughDielectric, Sphere, TriangleMesh, Vec3};
+
+// the bands of the sphere of directions around the normal, each split in four around it
+const BANDS: usize = 6;
+const BINS: usize = 4 * BANDS;
+
+// a hit on a flat-enough surface facing up, from `degrees` off the normal
+fn hit(mat: Arc<dyn Material>, degrees: f64) -> (Ray, HitRecord) {
+    let ground = Sphere::new(Point3::new(0, -1000, 0), 1000.0, mat);
+    let angle = degrees.to_radians();
+    let r = Ray::new(Point3::new(-angle.sin(), angle.cos(), 0.0), Vec3::new(angle.sin(), -angle.cos(), 0.0));
+    let rec = ground.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+    (r, rec)
+}
+
+// the middles of a grid of equal areas over the sphere of directions, each a `4π / count` of it
+fn directions() -> impl Iterator<Item = Vec3> {
+    let (rows, columns) = (1000, 2000);
+    (0..rows).flat_map(move |i| (0..columns).map(move |j| {
+        let y = 1.0 - 2.0 * (i as f64 + 0.5) / rows as f64;
+        let phi = 2.0 * PI * (j as f64 + 0.5) / columns as f64;
+        let s = (1.0 - y * y).sqrt();
+        Vec3::new(s * phi.cos(), y, s * phi.sin())
+    }))
+}
+
+fn bin(n: Vec3, direction: Vec3) -> usize {
+    let d = direction.unit_vector();
+    let band = (((n.dot(&d) + 1.0) / 2.0 * BANDS as f64) as usize).min(BANDS - 1);
+    let quarter = ((d.z.atan2(d.x) + PI) / (0.5 * PI)) as usize % 4;
+    4 * band + quarter
+}
+
+// the chance of `scatter` sending light into each part of the sphere, and the light it sends there,
+// worked out both by scattering and by integrating what `evaluate` says over the sphere. These should
+// agree for light sampled both ways to be weighted properly
+fn assert_consistent(name: &str, mat: Arc<dyn Material>) {
+    for degrees in [20.0, 65.0] {
+        let (r, rec) = hit(mat.clone(), degrees);
+        let n = rec.normal;
+        let mut rng = Rng::new(7, 11);
+
+        let scattered = with_rng(&mut rng, || {
+            let samples = 200_000;
+            let mut bins = [(0.0, 0.0); BINS];
+            for _ in 0..samples {
+                let Some((atten, scatter)) = mat.scatter(&r, &rec) else {continue};
+                let (chance, light) = &mut bins[bin(n, scatter.dir)];
+                *chance += 1.0 / samples as f64;
+                *light += atten.y / samples as f64;
+            }
+            bins
+        });
+
+        let mut evaluated = [(0.0, 0.0); BINS];
+        let area = 4.0 * PI / directions().count() as f64;
+        for direction in directions() {
+            let (f_cos, pdf) = mat.evaluate(&r, &rec, direction).expect("the material can be evaluated");
+            let (chance, light) = &mut evaluated[bin(n, direction)];
+            *chance += pdf * area;
+            *light += f_cos.y * area;
+        }
+
+        for (i, (s, e)) in scattered.iter().zip(&evaluated).enumerate() {
+            assert!((s.0 - e.0).abs() < 0.01, "{name} at {degrees}°: chance of bin {i} is {} by scattering but {} by evaluating", s.0, e.0);
+            assert!((s.1 - e.1).abs() < 0.01, "{name} at {degrees}°: light into bin {i} is {} by scattering but {} by evaluating", s.1, e.1);
+        }
+    }
+}
+
+#[test]
+fn conductors_sample_what_they_evaluate() {
+    assert_consistent("gold", Arc::new(Conductor::gold(0.5)));
+    assert_consistent("rough copper", Arc::new(Conductor::copper(0.8)));
+}
+
+#[test]
+fn rough_dielectrics_sample_what_they_evaluate() {
+    assert_consistent("rough glass", Arc::new(RoughDielectric::new(1.5, 0.5)));
+}
+
+#[test]
+fn principled_materials_sample_what_they_evaluate() {
+    let plastic = PrincipledBuilder::new()
+        .base_color(Arc::new(Color::new(0.8, 0.3, 0.2)))
+        .roughness(Arc::new(0.5))
+        .sheen(Arc::new(0.5))
+        .build();
+    assert_consistent("plastic", Arc::new(plastic));
+
+    let mixed = PrincipledBuilder::new()
+        .base_color(Arc::new(Color::new(0.9, 0.8, 0.4)))
+        .metallic(Arc::new(0.4))
+        .roughness(Arc::new(0.6))
+        .transmission(Arc::new(0.5))
+        .build();
+    assert_consistent("half metal, half glass", Arc::new(mixed));
+}
+
+#[test]
+fn mixes_and_coatings_sample_what_they_evaluate() {
+    let paint: Arc<dyn Material> = Arc::new(Lambertian::new(Color::new(0.2, 0.4, 0.8)));
+    let gold: Arc<dyn Material> = Arc::new(Conductor::gold(0.4));
+
+    assert_consistent("dirty gold", Arc::new(MixMaterial::new(gold.clone(), paint.clone(), Arc::new(0.3))));
+    assert_consistent("varnished paint", Arc::new(Coated::new(paint, 1.5, 0.3)));
+    assert_consistent("varnished gold", Arc::new(Coated::new(gold, 1.5, 0.5)));
+}
+
+// particles in volumes have no surface to scatter off, but are sampled and evaluated all the same
+#[test]
+fn phase_functions_sample_what_they_evaluate() {
+    assert_consistent("cloud", Arc::new(HenyeyGreenstein::new(Color::new(0.9, 0.9, 0.9), 0.7)));
+    assert_consistent("dust", Arc::new(HenyeyGreenstein::new(Color::new(0.6, 0.5, 0.4), -0.3)));
+    assert_consistent("haze", Arc::new(HenyeyGreenstein::new(Color::new(1, 1, 1), 0.0)));
+}
+
+// light from inside, where some of it can't get out
+#[test]
+fn rough_dielectrics_sample_what_they_evaluate_from_inside() {
+    let mat: Arc<dyn Material> = Arc::new(RoughDielectric::new(1.5, 0.6));
+    let (r, mut rec) = hit(mat.clone(), 30.0);
+    rec.front_face = false;
+    let mut rng = Rng::new(3, 5);
+
+    let scattered: f64 = with_rng(&mut rng, || {
+        let samples = 200_000;
+        (0..samples).filter_map(|_| mat.scatter(&r, &rec)).map(|(atten, _)| atten.y / samples as f64).sum()
+    });
+
+    let area = 4.0 * PI / directions().count() as f64;
+    let evaluated: f64 = directions().map(|direction| mat.evaluate(&r, &rec, direction).unwrap().0.y * area).sum();
+    assert!((scattered - evaluated).abs() < 0.01, "{scattered} by scattering but {evaluated} by evaluating");
+}
+
+// however rough, these surfaces never send out more light than arrives
+#[test]
+fn microfacets_make_no_light() {
+    let total = |mat: Arc<dyn Material>| {
+        let (r, rec) = hit(mat.clone(), 40.0);
+        let mut rng = Rng::new(1, 2);
+        with_rng(&mut rng, || {
+            let samples = 100_000;
+            (0..samples).filter_map(|_| mat.scatter(&r, &rec)).map(|(atten, _)| atten.y).sum::<f64>() / samples as f64
+        })
+    };
+
+    let glass = total(Arc::new(RoughDielectric::new(1.5, 0.3)));
+    assert!(glass <= 1.0 + 1e-3, "rough glass sends out {glass} of the light");
+    let white = Color::new(1, 1, 1);
+    let metal = total(Arc::new(Conductor::new(white * 10.0, white * 10.0, 0.3)));
+    assert!(metal <= 1.0 + 1e-3, "a nearly perfect conductor sends out {metal} of the light");
+}
+
+// the light getting through a slab of `glass` `thickness` deep, lying under the origin, for rays
+// arriving `degrees` off its normal, and the light reflected back up, following every bounce inside
+fn through_slab(glass: Arc<dyn Material>, thickness: f64, degrees: f64) -> (Color, Color) {
+    let l = 1000.0;
+    let slab = TriangleMesh::new(Mesh {
+        positions: vec![
+            Point3::new(-l, -l, 0.0), Point3::new(l, -l, 0.0), Point3::new(l, l, 0.0), Point3::new(-l, l, 0.0),
+            Point3::new(-l, -l, -thickness), Point3::new(l, -l, -thickness), Point3::new(l, l, -thickness), Point3::new(-l, l, -thickness),
+        ],
+        // facing up on top and down underneath
+        triangles: vec![[0, 1, 2], [0, 2, 3], [4, 6, 5], [4, 7, 6]],
+        ..Mesh::default()
+    }, glass);
+
+    let angle = degrees.to_radians();
+    let samples = 20_000;
+    let mut rng = Rng::new(5, 9);
+    let (mut transmitted, mut reflected) = (Color::new(0, 0, 0), Color::new(0, 0, 0));
+    with_rng(&mut rng, || {
+        for _ in 0..samples {
+            let mut r = Ray::new(Point3::new(-angle.sin(), 0.0, angle.cos()), Vec3::new(angle.sin(), 0.0, -angle.cos()));
+            let mut throughput = Color::new(1, 1, 1);
+            while let Some(rec) = slab.hit(&r, Interval::new(0.001, f64::INFINITY)) {
+                let (atten, scattered) = rec.mat.scatter(&r, &rec).unwrap();
+                throughput = throughput * atten;
+                r = scattered;
+            }
+            if r.dir.z < 0.0 {
+                transmitted += throughput / samples as f64;
+            } else {
+                reflected += throughput / samples as f64;
+            }
+        }
+    });
+    (transmitted, reflected)
+}
+
+// light is absorbed exponentially with the distance it travels through glass (the Beer–Lambert law),
+// and glass that absorbs nothing lets all the light through that it doesn't reflect
+#[test]
+fn glass_absorbs_along_the_path() {
+    let absorption = Color::new(0.2, 0.5, 1.0);
+    // with the same index inside and out, light arriving head on is neither reflected nor bent, so it
+    // only goes straight through
+    let glass: Arc<dyn Material> = Arc::new(Dielectric::colored(1.0, Color::new(1, 1, 1), absorption));
+    let (thin, _) = through_slab(glass.clone(), 0.5, 0.0);
+    let (thick, _) = through_slab(glass.clone(), 1.5, 0.0);
+    for (d, out) in [(0.5, thin), (1.5, thick)] {
+        let expected = Color::new((-absorption.x * d).exp(), (-absorption.y * d).exp(), (-absorption.z * d).exp());
+        assert!((out - expected).length() < 1e-9, "{out:?} gets through {d} of glass rather than {expected:?}");
+    }
+    // so a slab three times as thick lets through the cube of the light
+    let ratio = Color::new(thick.x / thin.x.powi(3), thick.y / thin.y.powi(3), thick.z / thin.z.powi(3));
+    assert!((ratio - Color::new(1, 1, 1)).length() < 1e-9, "{ratio:?}");
+
+    // glass that bends and reflects light, but absorbs none, keeps all of it
+    let clear: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+    for thickness in [0.5, 1.5] {
+        let (transmitted, reflected) = through_slab(clear.clone(), thickness, 30.0);
+        assert!((transmitted + reflected - Color::new(1, 1, 1)).length() < 1e-9, "clear glass loses {:?}", Color::new(1, 1, 1) - transmitted - reflected);
+        assert!(transmitted.x > 0.85 && reflected.x > 0.03, "{transmitted:?} gets through and {reflected:?} is reflected");
+    }
+}
@@ -0,0 +1,132 @@
+use std::sync::Arc;
+
+use raytracing::{Color, Hittable, Interval, Lambertian, Mesh, Point3, Ray, TriangleMesh, Vec3, Weighting};
+
+// two triangles sharing the edge along the z axis, folded up `degrees` from flat
+fn roof(degrees: f64) -> Mesh {
+    let angle = degrees.to_radians();
+    Mesh {
+        positions: vec![
+            Point3::new(0, 0, 0),
+            Point3::new(0, 0, 1),
+            Point3::new(-1, 0, 0),
+            Point3::new(angle.cos(), angle.sin(), 0.0),
+        ],
+        triangles: vec![[0, 1, 2], [1, 0, 3]],
+        ..Mesh::default()
+    }
+}
+
+// the unit normal of each triangle, from the order of its corners
+fn face_normals(mesh: &Mesh) -> Vec<Vec3> {
+    mesh.triangles.iter().map(|t| {
+        let [a, b, c] = t.map(|v| mesh.positions[v]);
+        (b - a).cross(&(c - a)).unit_vector()
+    }).collect()
+}
+
+#[test]
+fn creases_sharper_than_the_angle_are_kept() {
+    for weighting in [Weighting::Area, Weighting::Angle] {
+        // folded 40°, the edge is sharp at 30° and smooth at 50°
+        let mut sharp = roof(40.0);
+        sharp.compute_normals(weighting, 30.0);
+        assert_eq!(sharp.positions.len(), 6, "the shared edge should be split");
+        let faces = face_normals(&sharp);
+        for (t, corners) in sharp.triangles.iter().enumerate() {
+            for &v in corners {
+                assert!((sharp.normals[v] - faces[t]).length() < 1e-12, "a sharp edge's vertex isn't facing its own triangle");
+            }
+        }
+
+        let mut smooth = roof(40.0);
+        smooth.compute_normals(weighting, 50.0);
+        assert_eq!(smooth.positions.len(), 4, "the shared edge shouldn't be split");
+        let faces = face_normals(&smooth);
+        let middle = (faces[0] + faces[1]).unit_vector();
+        for v in [0, 1] {
+            let shared = smooth.triangles[0].iter().copied().find(|&w| smooth.positions[w] == roof(40.0).positions[v]).unwrap();
+            assert!((smooth.normals[shared] - middle).length() < 1e-12, "the shared edge isn't smoothed across");
+        }
+    }
+}
+
+// a low pyramid, whose sides all meet at less than 60°
+fn pyramid() -> Mesh {
+    Mesh {
+        positions: vec![Point3::new(-1, 0, -1), Point3::new(1, 0, -1), Point3::new(1, 0, 1), Point3::new(-1, 0, 1), Point3::new(0.0, 0.3, 0.0)],
+        triangles: vec![[0, 4, 1], [1, 4, 2], [2, 4, 3], [3, 4, 0]],
+        ..Mesh::default()
+    }
+}
+
+#[test]
+fn only_creases_are_split() {
+    let mut sides = pyramid();
+    sides.compute_normals(Weighting::Angle, 60.0);
+    assert_eq!(sides.positions.len(), 5);
+    assert!((sides.normals[sides.triangles[0][1]] - Vec3::new(0, 1, 0)).length() < 1e-12);
+
+    // the base meets the sides at more than 90°, so its corners are split from theirs, but not across
+    // its own diagonal
+    let mut closed = pyramid();
+    closed.triangles.extend([[0, 1, 2], [0, 2, 3]]);
+    closed.compute_normals(Weighting::Angle, 60.0);
+    assert_eq!(closed.positions.len(), 9);
+}
+
+// a triangle whose corners are red, green and blue, with texture coordinates to match
+fn triangle() -> TriangleMesh {
+    let mesh = Mesh {
+        positions: vec![Point3::new(0, 0, 0), Point3::new(2, 0, 0), Point3::new(0, 2, 0)],
+        colors: vec![Color::new(1, 0, 0), Color::new(0, 1, 0), Color::new(0, 0, 1)],
+        uvs: vec![(0.1, 0.2), (0.9, 0.3), (0.4, 0.8)],
+        triangles: vec![[0, 1, 2]],
+        ..Mesh::default()
+    };
+    TriangleMesh::new(mesh, Arc::new(Lambertian::new(Color::new(1, 1, 1))))
+}
+
+#[test]
+fn barycentrics_blend_the_corners() {
+    let mesh = triangle();
+    let corners = [Point3::new(0, 0, 0), Point3::new(2, 0, 0), Point3::new(0, 2, 0)];
+    let colors = [Color::new(1, 0, 0), Color::new(0, 1, 0), Color::new(0, 0, 1)];
+    let uvs = [(0.1, 0.2), (0.9, 0.3), (0.4, 0.8)];
+
+    for i in 1..20 {
+        for j in 1..20 - i {
+            let target = Point3::new(0.1 * i as f64, 0.1 * j as f64, 0.0);
+            let r = Ray::new(target + Vec3::new(0.3, -0.2, 1.0), Vec3::new(-0.3, 0.2, -1.0));
+            let rec = mesh.hit(&r, Interval::new(0.001, f64::INFINITY)).expect("ray should hit the triangle");
+
+            let b = rec.barycentric.expect("meshes give barycentrics");
+            assert!((b.iter().sum::<f64>() - 1.0).abs() < 1e-12, "barycentrics {b:?} don't add up to 1");
+            assert!(b.iter().all(|&b| (0.0..=1.0).contains(&b)));
+
+            let p: Point3 = (0..3).map(|k| b[k] * corners[k]).sum();
+            assert!((p - rec.p).length() < 1e-9, "barycentrics {b:?} are of {p:?} rather than {:?}", rec.p);
+            let color: Color = (0..3).map(|k| b[k] * colors[k]).sum();
+            assert!((rec.color.unwrap() - color).length() < 1e-12);
+            let uv = (0..3).fold((0.0, 0.0), |uv, k| (uv.0 + b[k] * uvs[k].0, uv.1 + b[k] * uvs[k].1));
+            assert!((rec.u - uv.0).abs() < 1e-12 && (rec.v - uv.1).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn vertices_have_their_own_attributes() {
+    let mesh = triangle();
+    // just inside each corner, where the blend is almost all that corner
+    let near = [(Point3::new(1e-7, 1e-7, 0.0), 0), (Point3::new(2.0 - 2e-7, 1e-7, 0.0), 1), (Point3::new(1e-7, 2.0 - 2e-7, 0.0), 2)];
+    let colors = [Color::new(1, 0, 0), Color::new(0, 1, 0), Color::new(0, 0, 1)];
+    let uvs = [(0.1, 0.2), (0.9, 0.3), (0.4, 0.8)];
+
+    for (target, k) in near {
+        let r = Ray::new(target + Vec3::new(0, 0, 1), Vec3::new(0, 0, -1));
+        let rec = mesh.hit(&r, Interval::new(0.001, f64::INFINITY)).expect("ray should hit the triangle");
+        assert!((rec.barycentric.unwrap()[k] - 1.0).abs() < 1e-6);
+        assert!((rec.color.unwrap() - colors[k]).length() < 1e-6, "corner {k} is {:?}", rec.color);
+        assert!((rec.u - uvs[k].0).abs() < 1e-6 && (rec.v - uvs[k].1).abs() < 1e-6, "corner {k} is at {}, {}", rec.u, rec.v);
+    }
+}
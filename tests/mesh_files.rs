@@ -0,0 +1,181 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use raytracing::{Color, Mesh, Point3, Vec3};
+
+// a file in the temporary directory holding `bytes`, named for the test writing it
+fn write(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("raytracing-{}-{name}", std::process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+fn load_ply(name: &str, bytes: &[u8]) -> io::Result<Mesh> {
+    let path = write(name, bytes);
+    let mesh = Mesh::load_ply(&path);
+    fs::remove_file(path).unwrap();
+    mesh
+}
+
+fn load_stl(name: &str, bytes: &[u8]) -> io::Result<Mesh> {
+    let path = write(name, bytes);
+    let mesh = Mesh::load_stl(&path);
+    fs::remove_file(path).unwrap();
+    mesh
+}
+
+fn assert_invalid(result: io::Result<Mesh>, what: &str) {
+    match result {
+        Ok(_) => panic!("{what} loaded"),
+        Err(e) => assert_eq!(e.kind(), io::ErrorKind::InvalidData, "{what} failed with {e}"),
+    }
+}
+
+// a square of four vertices in the xy plane, facing +z, made of two triangles
+const POSITIONS: [[f32; 3]; 4] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [1.0, 1.0, 0.0], [0.0, 1.0, 0.0]];
+const COLORS: [[u8; 3]; 4] = [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]];
+const UVS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+const FACES: [[i32; 3]; 2] = [[0, 1, 2], [0, 2, 3]];
+
+fn ply_header(format: &str) -> String {
+    format!("ply\nformat {format} 1.0\ncomment a square\nelement vertex 4\n\
+        property float x\nproperty float y\nproperty float z\n\
+        property float nx\nproperty float ny\nproperty float nz\n\
+        property uchar red\nproperty uchar green\nproperty uchar blue\n\
+        property float u\nproperty float v\n\
+        element face 2\nproperty list uchar int vertex_indices\nend_header\n")
+}
+
+fn ascii_ply() -> Vec<u8> {
+    let mut text = ply_header("ascii");
+    for i in 0..4 {
+        let ([x, y, z], [r, g, b], [u, v]) = (POSITIONS[i], COLORS[i], UVS[i]);
+        text += &format!("{x} {y} {z} 0 0 1 {r} {g} {b} {u} {v}\n");
+    }
+    for [a, b, c] in FACES {
+        text += &format!("3 {a} {b} {c}\n");
+    }
+    text.into_bytes()
+}
+
+fn binary_ply(big_endian: bool) -> Vec<u8> {
+    let mut bytes = ply_header(if big_endian {"binary_big_endian"} else {"binary_little_endian"}).into_bytes();
+    let float = |bytes: &mut Vec<u8>, f: f32| bytes.extend(if big_endian {f.to_be_bytes()} else {f.to_le_bytes()});
+    for i in 0..4 {
+        for f in POSITIONS[i].into_iter().chain([0.0, 0.0, 1.0]) {
+            float(&mut bytes, f);
+        }
+        bytes.extend(COLORS[i]);
+        for f in UVS[i] {
+            float(&mut bytes, f);
+        }
+    }
+    for face in FACES {
+        bytes.push(3);
+        for i in face {
+            bytes.extend(if big_endian {i.to_be_bytes()} else {i.to_le_bytes()});
+        }
+    }
+    bytes
+}
+
+fn assert_square(mesh: &Mesh) {
+    let positions: Vec<Point3> = POSITIONS.iter().map(|&[x, y, z]| Point3::new(x, y, z)).collect();
+    assert_eq!(mesh.positions, positions);
+    assert_eq!(mesh.normals, vec![Vec3::new(0, 0, 1); 4]);
+    let colors: Vec<Color> = COLORS.iter().map(|c| c.map(|c| c as f64 / 255.0)).map(|[r, g, b]| Color::new(r, g, b)).collect();
+    assert_eq!(mesh.colors, colors);
+    let uvs: Vec<(f64, f64)> = UVS.iter().map(|&[u, v]| (u as f64, v as f64)).collect();
+    assert_eq!(mesh.uvs, uvs);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+}
+
+#[test]
+fn ply_files_load_in_every_format() {
+    assert_square(&load_ply("ascii.ply", &ascii_ply()).unwrap());
+    assert_square(&load_ply("little.ply", &binary_ply(false)).unwrap());
+    assert_square(&load_ply("big.ply", &binary_ply(true)).unwrap());
+}
+
+#[test]
+fn truncated_ply_files_are_invalid() {
+    let ascii = ascii_ply();
+    let binary = binary_ply(false);
+    let header = ply_header("ascii").len();
+
+    assert_invalid(load_ply("no-end.ply", &ascii[..header - 12]), "PLY without the end of its header");
+    assert_invalid(load_ply("no-data.ply", &ascii[..header]), "PLY without data");
+    assert_invalid(load_ply("short-ascii.ply", &ascii[..ascii.len() - 4]), "ASCII PLY missing a face");
+    assert_invalid(load_ply("short-binary.ply", &binary[..binary.len() - 3]), "binary PLY missing part of a face");
+    assert_invalid(load_ply("short-vertex.ply", &binary[..header + 20]), "binary PLY missing vertices");
+}
+
+fn ascii_stl() -> Vec<u8> {
+    let mut text = String::from("solid square\n");
+    for face in FACES {
+        text += "  facet normal 0 0 1\n    outer loop\n";
+        for i in face {
+            let [x, y, z] = POSITIONS[i as usize];
+            text += &format!("      vertex {x} {y} {z}\n");
+        }
+        text += "    endloop\n  endfacet\n";
+    }
+    text += "endsolid square\n";
+    text.into_bytes()
+}
+
+fn binary_stl(header: &[u8]) -> Vec<u8> {
+    let mut bytes = header.to_vec();
+    bytes.resize(80, b' ');
+    bytes.extend((FACES.len() as u32).to_le_bytes());
+    for face in FACES {
+        for f in [0.0f32, 0.0, 1.0] {
+            bytes.extend(f.to_le_bytes());
+        }
+        for i in face {
+            for f in POSITIONS[i as usize] {
+                bytes.extend(f.to_le_bytes());
+            }
+        }
+        bytes.extend([0, 0]);
+    }
+    bytes
+}
+
+// corners in the same place are shared, so the square has four vertices
+fn assert_stl_square(mesh: &Mesh) {
+    let positions: Vec<Point3> = POSITIONS.iter().map(|&[x, y, z]| Point3::new(x, y, z)).collect();
+    assert_eq!(mesh.positions, positions);
+    assert_eq!(mesh.triangles, vec![[0, 1, 2], [0, 2, 3]]);
+    assert!(mesh.normals.is_empty() && mesh.colors.is_empty() && mesh.uvs.is_empty());
+}
+
+#[test]
+fn stl_files_load_in_either_format() {
+    assert_stl_square(&load_stl("ascii.stl", &ascii_stl()).unwrap());
+    assert_stl_square(&load_stl("binary.stl", &binary_stl(b"exported square")).unwrap());
+    // plenty of exporters start the binary header with "solid" anyway
+    assert_stl_square(&load_stl("solid.stl", &binary_stl(b"solid square")).unwrap());
+}
+
+#[test]
+fn truncated_stl_files_are_invalid() {
+    let ascii = ascii_stl();
+    let binary = binary_stl(b"exported square");
+
+    assert_invalid(load_stl("short-header.stl", &binary[..60]), "STL with half a header");
+
+    // a facet with only two corners, and a file ending in the middle of a facet
+    let text = String::from_utf8(ascii.clone()).unwrap();
+    let two_corners = text.replacen("      vertex 1 1 0\n", "", 1);
+    assert_invalid(load_stl("two-corners.stl", two_corners.as_bytes()), "STL facet with two corners");
+    let cut = text.find("endloop").unwrap();
+    assert_invalid(load_stl("cut-facet.stl", &ascii[..cut]), "STL ending in a facet");
+
+    // more triangles promised than there are bytes for
+    assert_invalid(load_stl("short-binary.stl", &binary[..binary.len() - 10]), "binary STL missing part of a triangle");
+    let mut more = binary.clone();
+    more[80..84].copy_from_slice(&3u32.to_le_bytes());
+    assert_invalid(load_stl("more-triangles.stl", &more), "binary STL promising another triangle");
+}
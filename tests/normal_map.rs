@@ -0,0 +1,131 @@
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{BumpMap, Color, HitRecord, Hittable, Interval, Lambertian, Material, Mesh, Metal, NormalMap, Point3, Ray, Texture, TriangleMesh, Vec3};
+
+// a square facing up z, with `u` running along x, or against it if `mirrored`, and `v` along y
+fn square(mat: Arc<dyn Material>, mirrored: bool) -> TriangleMesh {
+    let u = |x: f64| if mirrored {1.0 - x} else {x};
+    TriangleMesh::new(Mesh {
+        positions: vec![Point3::new(-1, -1, 0), Point3::new(1, -1, 0), Point3::new(1, 1, 0), Point3::new(-1, 1, 0)],
+        uvs: vec![(u(0.0), 0.0), (u(1.0), 0.0), (u(1.0), 1.0), (u(0.0), 1.0)],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+        ..Mesh::default()
+    }, mat)
+}
+
+// where a ray from `from` towards the middle of `surface` hits it
+fn hit(surface: &TriangleMesh, from: Vec3) -> (Ray, HitRecord) {
+    let r = Ray::new(from, Point3::new(0.1, 0.2, 0.0) - from);
+    let rec = surface.hit(&r, Interval::new(0.001, f64::INFINITY)).expect("the ray should hit the square");
+    (r, rec)
+}
+
+// the direction a mirror under `map` reflects a ray arriving from `from`
+fn reflected(map: impl Fn(Arc<dyn Material>) -> Arc<dyn Material>, mirrored: bool, from: Vec3) -> Vec3 {
+    let surface = square(map(Arc::new(Metal::new(Color::new(1, 1, 1), 0.0))), mirrored);
+    let (r, rec) = hit(&surface, from);
+    rec.mat.scatter(&r, &rec).expect("a mirror reflects").1.dir.unit_vector()
+}
+
+fn plain(base: Arc<dyn Material>) -> Arc<dyn Material> {
+    base
+}
+
+// where the incoming rays come from, from straight overhead to low over the surface
+fn viewpoints() -> impl Iterator<Item = Vec3> {
+    [Vec3::new(0, 0, 2), Vec3::new(1.0, 0.5, 2.0), Vec3::new(-3.0, 1.0, 1.0), Vec3::new(0.5, -4.0, 0.5)].into_iter()
+}
+
+#[test]
+fn flat_normal_maps_leave_the_normal_alone() {
+    let flat = |base| -> Arc<dyn Material> {Arc::new(NormalMap::new(base, Arc::new(Color::new(0.5, 0.5, 1.0))))};
+    for from in viewpoints() {
+        for mirrored in [false, true] {
+            let (mapped, bare) = (reflected(flat, mirrored, from), reflected(plain, mirrored, from));
+            assert!((mapped - bare).length() < 1e-9, "from {from:?}, a flat map reflects along {mapped:?} rather than {bare:?}");
+        }
+    }
+}
+
+// red tilts the normal towards increasing u and green towards increasing v, whichever way the
+// texture runs across the surface
+#[test]
+fn normal_maps_follow_the_texture_coordinates() {
+    let straight_down = Vec3::new(0.1, 0.2, 3.0);
+    for mirrored in [false, true] {
+        let towards_u = |base| -> Arc<dyn Material> {Arc::new(NormalMap::new(base, Arc::new(Color::new(0.8, 0.5, 0.9))))};
+        let towards_v = |base| -> Arc<dyn Material> {Arc::new(NormalMap::new(base, Arc::new(Color::new(0.5, 0.8, 0.9))))};
+
+        // tilted by (0.6, 0, 0.8), a ray straight down is reflected at twice the angle
+        let u = reflected(towards_u, mirrored, straight_down);
+        let x = if mirrored {-0.96} else {0.96};
+        assert!((u - Vec3::new(x, 0.0, 0.28)).length() < 1e-9, "tilting towards u reflects along {u:?}");
+        let v = reflected(towards_v, mirrored, straight_down);
+        assert!((v - Vec3::new(0.0, 0.96, 0.28)).length() < 1e-9, "tilting towards v reflects along {v:?}");
+    }
+}
+
+// a height that's the same everywhere raises the surface without tilting it
+#[test]
+fn flat_bump_maps_leave_the_normal_alone() {
+    let flat = |base| -> Arc<dyn Material> {Arc::new(BumpMap::new(base, Arc::new(0.7), 5.0))};
+    for from in viewpoints() {
+        for mirrored in [false, true] {
+            let (bumped, bare) = (reflected(flat, mirrored, from), reflected(plain, mirrored, from));
+            assert!((bumped - bare).length() < 1e-9, "from {from:?}, a flat bump map reflects along {bumped:?} rather than {bare:?}");
+        }
+    }
+
+    // while a slope tilts the normal down it, so a ray straight down is reflected downhill
+    let slope = |base| -> Arc<dyn Material> {Arc::new(BumpMap::new(base, Arc::new(Sloped), 0.5))};
+    let downhill = reflected(slope, false, Vec3::new(0.1, 0.2, 3.0));
+    assert!(downhill.x < -0.1 && downhill.y.abs() < 1e-6, "a slope up u reflects along {downhill:?}");
+}
+
+// a height rising along u
+struct Sloped;
+
+impl Texture for Sloped {
+    fn value(&self, u: f64, _v: f64, _p: Point3) -> Color {
+        Color::new(u, u, u)
+    }
+}
+
+// however far a map tilts the normal, light seen low over a surface never leaves from its far side,
+// but is absorbed instead, by bases that only ever scatter on their own side of the shading normal
+#[test]
+fn shading_normals_never_leak_through_the_surface() {
+    let tilted: [Arc<dyn Texture>; 3] = [
+        Arc::new(Color::new(0.0, 0.5, 0.55)),
+        Arc::new(Color::new(1.0, 0.5, 0.55)),
+        Arc::new(Color::new(0.5, 0.0, 0.5)),
+    ];
+    let bases: [Arc<dyn Material>; 2] = [Arc::new(Lambertian::new(Color::new(0.8, 0.8, 0.8))), Arc::new(Metal::new(Color::new(1, 1, 1), 0.0))];
+
+    let mut absorbed = 0;
+    let mut rng = Rng::new(2, 5);
+    with_rng(&mut rng, || {
+        for texture in tilted {
+            for base in &bases {
+                let surface = square(Arc::new(NormalMap::new(base.clone(), texture.clone())), false);
+                for from in [Vec3::new(-20.0, 0.2, 0.3), Vec3::new(20.0, 0.2, 0.3), Vec3::new(0.1, -20.0, 0.2), Vec3::new(0.1, 0.2, 3.0)] {
+                    let (r, rec) = hit(&surface, from);
+                    for _ in 0..2000 {
+                        match rec.mat.scatter(&r, &rec) {
+                            Some((_, scattered)) => assert!(scattered.dir.dot(&rec.geometric_normal) > 0.0, "from {from:?}, light leaves along {:?}", scattered.dir),
+                            None => absorbed += 1,
+                        }
+                    }
+
+                    // and nothing is sent through it either
+                    let through = Vec3::new(0.3, -0.2, -1.0);
+                    if let Some((f_cos, _)) = rec.mat.evaluate(&r, &rec, through) {
+                        assert!(f_cos.near_zero(), "from {from:?}, {f_cos:?} is sent through the surface");
+                    }
+                }
+            }
+        }
+    });
+    assert!(absorbed > 0, "no light was kept from leaking");
+}
@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use raytracing::{Color, Cone, Cylinder, Disk, Hittable, Interval, Lambertian, Material, Paraboloid, Point3, Ray, Torus, Vec3};
+
+fn mat() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)))
+}
+
+// rays from all around towards points scattered over the middle of the shapes, with their axes
+// leaning so that the frames aren't lined up with the world's
+fn rays() -> Vec<Ray> {
+    let mut rays = Vec::new();
+    for i in 0..24 {
+        for j in 1..12 {
+            let (phi, theta) = (i as f64 * 0.27, j as f64 * 0.26);
+            let from = 6.0 * Vec3::new(theta.sin() * phi.cos(), theta.cos(), theta.sin() * phi.sin());
+            let to = 0.3 * Vec3::new((i % 5) as f64 - 2.0, (j % 3) as f64 - 1.0, ((i + j) % 4) as f64 - 1.5);
+            rays.push(Ray::new(Point3::new(0, 0, 0) + from, to - from));
+        }
+    }
+    rays
+}
+
+// the tangents, in order, turn the same way around the outward normal as x and y do around z
+fn assert_right_handed(name: &str, shape: &dyn Hittable) {
+    let mut hits = 0;
+    for r in rays() {
+        let Some(rec) = shape.hit(&r, Interval::new(0.001, f64::INFINITY)) else {continue};
+        hits += 1;
+
+        let outward = if rec.front_face {rec.normal} else {-rec.normal};
+        let turn = rec.dpdu.cross(&rec.dpdv).dot(&outward);
+        assert!(turn > 0.0, "{name} has left handed tangents at {:?}", rec.p);
+    }
+    assert!(hits > 20, "{name} was only hit {hits} times");
+}
+
+#[test]
+fn tangents_are_right_handed() {
+    let axis = Vec3::new(0.3, 1.0, -0.2);
+    let base = Point3::new(0, 0, 0) - 0.5 * axis;
+
+    assert_right_handed("cylinder", &Cylinder::new(base, axis, 1.0, true, mat()));
+    assert_right_handed("cone", &Cone::new(base, axis, 1.0, true, mat()));
+    assert_right_handed("paraboloid", &Paraboloid::new(base, axis, 1.0, true, mat()));
+    assert_right_handed("torus", &Torus::new(Point3::new(0, 0, 0), axis, 1.5, 0.5, mat()));
+    assert_right_handed("disk", &Disk::new(Point3::new(0, 0, 0), axis, 1.5, mat()));
+    assert_right_handed("upside down disk", &Disk::new(Point3::new(0, 0, 0), -axis, 1.5, mat()));
+}
+
+// the caps only, by looking straight down and up the axis inside the rim
+#[test]
+fn cap_tangents_are_right_handed() {
+    let shapes: [(&str, Box<dyn Hittable>); 3] = [
+        ("cylinder", Box::new(Cylinder::new(Point3::new(0, 0, 0), Vec3::new(0, 2, 0), 1.0, true, mat()))),
+        ("cone", Box::new(Cone::new(Point3::new(0, 0, 0), Vec3::new(0, 2, 0), 1.0, true, mat()))),
+        ("paraboloid", Box::new(Paraboloid::new(Point3::new(0, 0, 0), Vec3::new(0, 2, 0), 1.0, true, mat()))),
+    ];
+
+    for (name, shape) in shapes {
+        // off the middle, where going around doesn't move
+        for i in 1..16 {
+            let angle = i as f64 * 0.4;
+            let (x, z) = (0.05 * i as f64 * angle.cos(), 0.05 * i as f64 * angle.sin());
+            for (y, dy) in [(5.0, -1.0), (-5.0, 1.0)] {
+                let r = Ray::new(Point3::new(x, y, z), Vec3::new(0.0, dy, 0.0));
+                let Some(rec) = shape.hit(&r, Interval::new(0.001, f64::INFINITY)) else {continue};
+
+                let outward = if rec.front_face {rec.normal} else {-rec.normal};
+                let turn = rec.dpdu.cross(&rec.dpdv).dot(&outward);
+                assert!(turn > 0.0, "{name} has a left handed cap at {:?}", rec.p);
+            }
+        }
+    }
+}
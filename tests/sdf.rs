@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use raytracing::{Aabb, Color, Hittable, Interval, Lambertian, Point3, Ray, Repetition, Sdf, SdfBox, SdfCapsule, SdfHittable, SdfRoundedBox, SdfSphere, SdfTorus, SmoothUnion, Subtraction, Twist, Vec3};
+
+// one of each shape, and of each way of combining them, placed off the origin and at odd angles
+fn shapes() -> Vec<(&'static str, Arc<dyn Sdf>)> {
+    let sphere: Arc<dyn Sdf> = Arc::new(SdfSphere::new(Point3::new(0.3, -0.2, 0.1), 0.7));
+    let cube: Arc<dyn Sdf> = Arc::new(SdfBox::new(Point3::new(-0.4, 0.5, 0.2), Vec3::new(0.6, 0.3, 0.5)));
+    let capsule: Arc<dyn Sdf> = Arc::new(SdfCapsule::new(Point3::new(-0.8, -0.5, 0.3), Point3::new(0.6, 0.7, -0.4), 0.25));
+    let torus: Arc<dyn Sdf> = Arc::new(SdfTorus::new(Point3::new(0.2, 0.1, -0.3), 0.8, 0.2));
+    let rounded: Arc<dyn Sdf> = Arc::new(SdfRoundedBox::new(Point3::new(0.5, 0.4, 0.6), Vec3::new(0.3, 0.7, 0.4), 0.15));
+
+    vec![
+        ("sphere", sphere.clone()),
+        ("box", cube.clone()),
+        ("capsule", capsule.clone()),
+        ("torus", torus.clone()),
+        ("rounded box", rounded.clone()),
+        ("smooth union", Arc::new(SmoothUnion::new(sphere.clone(), cube.clone(), 0.4))),
+        ("sharp union", Arc::new(SmoothUnion::new(capsule.clone(), torus.clone(), 0.0))),
+        ("subtraction", Arc::new(Subtraction::new(rounded.clone(), sphere.clone(), 0.2))),
+        ("twisted box", Arc::new(Twist::new(cube.clone(), 2.5))),
+        ("twisted capsule", Arc::new(Twist::new(capsule.clone(), -1.5))),
+        ("repeated torus", Arc::new(Repetition::new(torus, Vec3::new(2.5, 0.0, -2.5), [3, 1, 2]))),
+        ("repeated sphere", Arc::new(Repetition::new(sphere, Vec3::new(1.5, 1.5, 1.5), [2, 3, 1]))),
+    ]
+}
+
+// whether `p` is in the box grown by `margin` each way
+fn contains(aabb: &Aabb, p: Point3, margin: f64) -> bool {
+    let within = |p: f64, min: f64, max: f64| min - margin <= p && p <= max + margin;
+    within(p.x, aabb.min.x, aabb.max.x) && within(p.y, aabb.min.y, aabb.max.y) && within(p.z, aabb.min.z, aabb.max.z)
+}
+
+// nothing outside a shape's box, beyond a rounding error, is inside the shape
+#[test]
+fn bounding_boxes_contain_the_shapes() {
+    let steps = 60;
+    for (name, sdf) in shapes() {
+        let aabb = sdf.bounding_box();
+        // a grid over the box grown by half its size each way, so it reaches well past every side
+        let (size, min) = (aabb.max - aabb.min, aabb.min - 0.5 * (aabb.max - aabb.min));
+        for i in 0..=steps {
+            for j in 0..=steps {
+                for k in 0..=steps {
+                    let f = |n: usize| 2.0 * n as f64 / steps as f64;
+                    let p = min + Vec3::new(f(i), f(j), f(k)) * size;
+                    if !contains(&aabb, p, 1e-9) {
+                        let d = sdf.distance(p);
+                        assert!(d > 0.0, "{p:?} is outside the {name}'s box {aabb:?}, but {d} from its surface");
+                    }
+                }
+            }
+        }
+    }
+}
+
+// rays from all around find the surface only inside the box
+#[test]
+fn hits_are_inside_the_bounding_boxes() {
+    let mat = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    for (name, sdf) in shapes() {
+        let aabb = sdf.bounding_box();
+        let object = SdfHittable::new(sdf, mat.clone());
+        let middle = 0.5 * (aabb.min + aabb.max);
+        let size = (aabb.max - aabb.min).length();
+
+        let mut hits = 0;
+        for i in 0..40 {
+            for j in 0..40 {
+                // from a spread of directions on a sphere around the box, at points across it
+                let y = 1.0 - 2.0 * (i as f64 + 0.5) / 40.0;
+                let phi = 2.0 * std::f64::consts::PI * (j as f64 + 0.5) / 40.0;
+                let s = (1.0 - y * y).sqrt();
+                let from = middle + 2.0 * size * Vec3::new(s * phi.cos(), y, s * phi.sin());
+                let target = middle + 0.3 * size * Vec3::new(phi.sin(), (3.0 * phi).cos(), y);
+
+                let r = Ray::new(from, target - from);
+                let Some(rec) = object.hit(&r, Interval::new(0.001, f64::INFINITY)) else {continue};
+                hits += 1;
+                let p = rec.p;
+                assert!(contains(&aabb, p, 1e-3), "the {name} was hit at {p:?}, outside its box {aabb:?}");
+            }
+        }
+        assert!(hits > 0, "no ray hit the {name}");
+    }
+}
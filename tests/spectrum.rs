@@ -0,0 +1,87 @@
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{CameraBuilder, Color, ConstantEnvironment, Dielectric, Hittable, HittableList, Interval, Lambertian, Material, Point3, Ray, Sphere, Vec3};
+
+const BK7_B: [f64; 3] = [1.03961212, 0.231792344, 1.01046945];
+const BK7_C: [f64; 3] = [0.00600069867, 0.0200179144, 103.560653];
+
+// the direction a ray at `wavelength` refracts in, entering flat glass 50° off the normal
+fn refracted(glass: Arc<dyn Material>, wavelength: Option<f64>) -> Vec3 {
+    let ground = Sphere::new(Point3::new(0, -1000, 0), 1000.0, glass.clone());
+    let angle = 50f64.to_radians();
+    let mut r = Ray::new(Point3::new(-angle.sin(), angle.cos(), 0.0), Vec3::new(angle.sin(), -angle.cos(), 0.0));
+    r.wavelength = wavelength;
+    let rec = ground.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+
+    let mut rng = Rng::new(4, 1);
+    with_rng(&mut rng, || {
+        // some rays are reflected instead, at random
+        (0..100).filter_map(|_| glass.scatter(&r, &rec)).map(|(_, s)| s.dir.unit_vector()).find(|d| d.y < 0.0)
+    }).expect("some of the light should be refracted")
+}
+
+// the index of refraction a ray at `wavelength` is bent by, from Snell's law
+fn index(glass: Arc<dyn Material>, wavelength: Option<f64>) -> f64 {
+    50f64.to_radians().sin() / refracted(glass, wavelength).x
+}
+
+#[test]
+fn glass_catalogue_indices() {
+    // BK7 is 1.5168 at the sodium D line, which is what RGB rendering uses, and 1.5245 at 450 nm
+    let sellmeier: Arc<dyn Material> = Arc::new(Dielectric::sellmeier(BK7_B, BK7_C));
+    let n = index(sellmeier.clone(), None);
+    assert!((n - 1.5168).abs() < 1e-4, "BK7 has an index of {n}");
+    let n = index(sellmeier.clone(), Some(450.0));
+    assert!((n - 1.5245).abs() < 1e-3, "BK7 has an index of {n} in blue");
+
+    let cauchy: Arc<dyn Material> = Arc::new(Dielectric::cauchy(1.5046, 0.00420));
+    let n = index(cauchy.clone(), None);
+    assert!((n - 1.5168).abs() < 1e-3, "Cauchy's BK7 has an index of {n}");
+
+    // blue light is bent more than red
+    for (name, glass) in [("sellmeier", sellmeier), ("cauchy", cauchy)] {
+        assert!(glass.disperses(), "{name} glass should disperse");
+        let mut previous = f64::INFINITY;
+        for wavelength in [400.0, 450.0, 500.0, 550.0, 600.0, 650.0, 700.0] {
+            let n = index(glass.clone(), Some(wavelength));
+            assert!(n < previous, "{name} glass's index should fall with wavelength, but is {n} at {wavelength} nm");
+            previous = n;
+        }
+    }
+    assert!(!Dielectric::new(1.5).disperses());
+}
+
+// light of any colour comes out that colour, on average, after being upsampled to a spectrum and
+// converted back
+#[test]
+fn spectral_rendering_keeps_colours() {
+    for sky in [Color::new(1, 1, 1), Color::new(0.25, 0.5, 0.75), Color::new(0.9, 0.2, 0.1)] {
+        let mut world = HittableList::default();
+        world.add(Arc::new(Sphere::new(Point3::new(0, 0, -3), 1.0, Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5))))));
+
+        let render = |spectral: bool| {
+            let fb = CameraBuilder::new()
+                .width(24)
+                .aspect_ratio(1.0)
+                .samples_per_pixel(256)
+                .max_depth(8)
+                .spectral(spectral)
+                .environment(Arc::new(ConstantEnvironment::new(sky)))
+                .set_view(Point3::new(0,0,0), Point3::new(0,0,-1), Vec3::new(0,1,0))
+                .build()
+                .render(&world);
+
+            let mut sum = Color::new(0, 0, 0);
+            for y in 0..fb.height() {
+                for x in 0..fb.width() {
+                    sum += fb.pixel(x, y);
+                }
+            }
+            sum / (fb.width() * fb.height()) as f64
+        };
+
+        let (spectral, rgb) = (render(true), render(false));
+        assert!((spectral - rgb).length() < 0.01, "under a {sky:?} sky, the average is {spectral:?} spectrally but {rgb:?} in RGB");
+    }
+}
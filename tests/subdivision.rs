@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+
+use raytracing::{Mesh, Point3, Subdivision};
+
+const SCHEMES: [Subdivision; 2] = [Subdivision::Loop, Subdivision::CatmullClark];
+
+fn tetrahedron() -> Mesh {
+    Mesh {
+        positions: vec![Point3::new(1, 1, 1), Point3::new(1, -1, -1), Point3::new(-1, 1, -1), Point3::new(-1, -1, 1)],
+        triangles: vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+        ..Mesh::default()
+    }
+}
+
+fn cube() -> Mesh {
+    let positions = (0..8)
+        .map(|i| {
+            let corner = |bit: usize| if i & bit == 0 {-1.0} else {1.0};
+            Point3::new(corner(1), corner(2), corner(4))
+        })
+        .collect();
+
+    Mesh {
+        positions,
+        quads: vec![[0, 2, 3, 1], [4, 5, 7, 6], [0, 1, 5, 4], [2, 6, 7, 3], [0, 4, 6, 2], [1, 3, 7, 5]],
+        ..Mesh::default()
+    }
+}
+
+// a bumpy open patch of two by two quads, with texture coordinates across it
+fn patch() -> Mesh {
+    let mut mesh = Mesh::default();
+    for y in 0..3 {
+        for x in 0..3 {
+            let height = [0.0, 0.4, -0.2, 0.3, 0.5, 0.1, -0.3, 0.2, 0.6][3 * y + x];
+            mesh.positions.push(Point3::new(x as f64, y as f64, height));
+            mesh.uvs.push((x as f64 / 2.0, y as f64 / 2.0));
+        }
+    }
+    for y in 0..2 {
+        for x in 0..2 {
+            let i = 3 * y + x;
+            mesh.quads.push([i, i + 1, i + 4, i + 3]);
+        }
+    }
+    mesh
+}
+
+fn key(p: Point3) -> [u64; 3] {
+    [(p.x + 0.0).to_bits(), (p.y + 0.0).to_bits(), (p.z + 0.0).to_bits()]
+}
+
+// whether the mesh has a vertex within a rounding error of `p`
+fn has_point(mesh: &Mesh, p: Point3) -> bool {
+    mesh.positions.iter().any(|&q| (q - p).length() < 1e-9)
+}
+
+#[test]
+fn closed_meshes_stay_watertight() {
+    for scheme in SCHEMES {
+        for (name, mesh) in [("tetrahedron", tetrahedron()), ("cube", cube())] {
+            for levels in 1..4 {
+                let smooth = mesh.subdivide(scheme, levels, 180.0);
+
+                // every edge, matched up by position, is used once each way round by the faces either
+                // side of it
+                let mut sides: HashMap<([u64; 3], [u64; 3]), i32> = HashMap::new();
+                for t in &smooth.triangles {
+                    for i in 0..3 {
+                        let (a, b) = (smooth.positions[t[i]], smooth.positions[t[(i + 1) % 3]]);
+                        *sides.entry((key(a), key(b))).or_default() += 1;
+                    }
+                }
+                for (&(a, b), &count) in &sides {
+                    assert_eq!(count, 1, "{scheme:?} {name} at level {levels} has an edge used {count} times one way");
+                    assert_eq!(sides.get(&(b, a)), Some(&1), "{scheme:?} {name} at level {levels} has a hole");
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn boundaries_follow_the_crease_rules() {
+    let mesh = patch();
+    let p = |x: usize, y: usize| mesh.positions[3 * y + x];
+    // each vertex around the edge of the patch, and its neighbours along the edge
+    let boundary = [
+        (p(1, 0), p(0, 0), p(2, 0)),
+        (p(2, 1), p(2, 0), p(2, 2)),
+        (p(1, 2), p(0, 2), p(2, 2)),
+        (p(0, 1), p(0, 0), p(0, 2)),
+        (p(0, 0), p(1, 0), p(0, 1)),
+        (p(2, 2), p(1, 2), p(2, 1)),
+    ];
+
+    for scheme in SCHEMES {
+        let smooth = mesh.subdivide(scheme, 1, 180.0);
+        for &(v, a, b) in &boundary {
+            let moved = 0.75 * v + 0.125 * (a + b);
+            assert!(has_point(&smooth, moved), "{scheme:?} didn't move {v:?} to {moved:?}");
+
+            // new points along the boundary go halfway between its ends
+            let middle = 0.5 * (v + a);
+            assert!(has_point(&smooth, middle), "{scheme:?} has no point at {middle:?}");
+        }
+    }
+}
+
+#[test]
+fn texture_coordinates_stay_at_the_original_corners() {
+    let mesh = patch();
+    for scheme in SCHEMES {
+        for levels in 1..3 {
+            let smooth = mesh.subdivide(scheme, levels, 180.0);
+            assert_eq!(smooth.uvs.len(), smooth.positions.len());
+            for &uv in &mesh.uvs {
+                assert!(smooth.uvs.contains(&uv), "{scheme:?} lost the texture coordinates {uv:?}");
+            }
+        }
+
+        // and they go with the vertices they started on, here the corners of the patch
+        let smooth = mesh.subdivide(scheme, 1, 180.0);
+        for (corner, a, b) in [(0, 1, 3), (2, 1, 5), (8, 7, 5), (6, 7, 3)] {
+            let uv = mesh.uvs[corner];
+            let i = smooth.uvs.iter().position(|&t| t == uv).unwrap();
+            let moved = 0.75 * mesh.positions[corner] + 0.125 * (mesh.positions[a] + mesh.positions[b]);
+            assert!((smooth.positions[i] - moved).length() < 1e-9, "{scheme:?} put {uv:?} at {:?}", smooth.positions[i]);
+        }
+    }
+}
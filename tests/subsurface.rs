@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{Color, Hittable, Interval, Point3, Ray, Sphere, Subsurface, Vec3};
+
+// the average light coming back out of a unit sphere of `mat`, lit from one side, by following walks
+// in and around it until they leave
+fn escaping(mat: Subsurface) -> Color {
+    let sphere = Sphere::new(Point3::new(0, 0, 0), 1.0, Arc::new(mat));
+    let walks = 100_000;
+    let mut rng = Rng::new(6, 3);
+
+    with_rng(&mut rng, || {
+        let mut total = Color::new(0, 0, 0);
+        for i in 0..walks {
+            // parallel rays spread across the sphere
+            let offset = Vec3::new(((i % 250) as f64 + 0.5) / 250.0 - 0.5, ((i / 250) as f64 + 0.5) / 400.0 - 0.5, 0.0);
+            let mut r = Ray::new(Point3::new(0, 0, 3) + 1.6 * offset, Vec3::new(0, 0, -1));
+            let mut throughput = Color::new(1, 1, 1);
+            for _ in 0..10_000 {
+                let Some(rec) = sphere.hit(&r, Interval::new(0.001, f64::INFINITY)) else {
+                    total += throughput / walks as f64;
+                    break;
+                };
+                let Some((atten, scatter)) = rec.mat.scatter(&r, &rec) else {break};
+                throughput = throughput * atten;
+                r = scatter;
+            }
+        }
+        total
+    })
+}
+
+// with nothing absorbed, all the light that goes in comes back out, however it's scattered. When every
+// channel scatters alike, each walk carries all of its light out
+#[test]
+fn scattering_alone_loses_no_light() {
+    let out = escaping(Subsurface::coefficients(Color::new(0, 0, 0), Color::new(2, 2, 2), 1.3));
+    assert!((out - Color::new(1, 1, 1)).length() < 1e-9, "scattering lets out {out:?} of the light");
+
+    // otherwise walks are weighted by how likely each channel was to take them, which only adds up
+    // on average
+    let out = escaping(Subsurface::coefficients(Color::new(0, 0, 0), Color::new(1.5, 2.0, 2.5), 1.5));
+    assert!((out - Color::new(1, 1, 1)).length() < 0.03, "scattering by colour lets out {out:?} of the light");
+}
+
+#[test]
+fn absorption_takes_away_light() {
+    let out = escaping(Subsurface::coefficients(Color::new(0.5, 0.0, 0.05), Color::new(2, 2, 2), 1.3));
+    assert!((out.y - 1.0).abs() < 0.02, "green isn't absorbed, but only {} of it gets out", out.y);
+    assert!(out.x < out.z && out.z < out.y, "the most absorbed channels should come out darkest, not {out:?}");
+}
+
+// a material made to look a colour comes out with its channels in the same order, none brighter
+// than white, and white when nothing is absorbed
+#[test]
+fn materials_look_their_colour() {
+    let out = escaping(Subsurface::new(Color::new(0.8, 0.5, 0.2), Color::new(0.3, 0.3, 0.3), 1.3));
+    assert!(out.x > out.y && out.y > out.z && out.x <= 1.0, "an orange material comes out {out:?}");
+
+    let out = escaping(Subsurface::new(Color::new(1, 1, 1), Color::new(0.4, 0.35, 0.3), 1.3));
+    assert!((out - Color::new(1, 1, 1)).length() < 0.03, "a white material comes out {out:?}");
+}
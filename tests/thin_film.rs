@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{Color, Dielectric, Hittable, Interval, Material, Metal, Point3, Ray, RoughDielectric, Sphere, ThinFilm, Vec3};
+
+// where a ray from the centre of a unit sphere of `mat` leaves it, and what the material makes of it
+fn scatter_from_inside(mat: Arc<dyn Material>) -> Option<(Color, Ray)> {
+    let sphere = Sphere::new(Point3::new(0, 0, 0), 1.0, Arc::clone(&mat));
+    let r = Ray::new(Point3::new(0, 0, 0), Vec3::new(0, 0, 1));
+    let rec = sphere.hit(&r, Interval::new(0.001, f64::INFINITY)).expect("ray should leave the sphere");
+    mat.scatter(&r, &rec)
+}
+
+#[test]
+fn light_let_through_is_absorbed_by_the_base() {
+    let absorption = Color::new(0.5, 1.0, 2.0);
+    let glass = Arc::new(Dielectric::colored(1.0, Color::new(1, 1, 1), absorption));
+    // with no difference in index anywhere, nothing is reflected
+    let film = Arc::new(ThinFilm::new(glass, Arc::new(300.0), 1.0));
+
+    for _ in 0..100 {
+        let (atten, scattered) = scatter_from_inside(film.clone()).expect("light should get through");
+        assert!(scattered.dir.z > 0.0);
+        let expected = Color::new((-0.5f64).exp(), (-1.0f64).exp(), (-2.0f64).exp());
+        assert!((atten - expected).length() < 1e-9, "attenuated by {atten:?} rather than {expected:?}");
+    }
+}
+
+// clear glass under a film neither makes nor loses light: what isn't reflected is let through, so
+// the reflected and transmitted light add up to all of it in every channel
+#[test]
+fn clear_bases_keep_all_the_light() {
+    let film: Arc<dyn Material> = Arc::new(ThinFilm::new(Arc::new(Dielectric::new(1.5)), Arc::new(350.0), 1.33));
+    let sphere = Sphere::new(Point3::new(0, 0, 0), 1.0, film.clone());
+    let r = Ray::new(Point3::new(0.3, 0.2, 3.0), Vec3::new(0, 0, -1));
+    let rec = sphere.hit(&r, Interval::new(0.001, f64::INFINITY)).unwrap();
+
+    let samples = 100_000;
+    let mut rng = Rng::new(3, 7);
+    let (mut reflected, mut transmitted) = (Color::new(0, 0, 0), Color::new(0, 0, 0));
+    with_rng(&mut rng, || {
+        for _ in 0..samples {
+            let (atten, scattered) = film.scatter(&r, &rec).expect("a clear base always scatters");
+            if scattered.dir.dot(&rec.normal) > 0.0 {
+                reflected += atten / samples as f64;
+            } else {
+                transmitted += atten / samples as f64;
+            }
+        }
+    });
+
+    let total = reflected + transmitted;
+    assert!((total - Color::new(1, 1, 1)).length() < 0.01, "{total:?} of the light comes back out");
+    // and the film's colours are in the reflection
+    assert!(reflected.x != reflected.y || reflected.y != reflected.z);
+}
+
+#[test]
+fn light_let_through_is_spread_by_a_rough_base() {
+    let film = Arc::new(ThinFilm::new(Arc::new(RoughDielectric::new(1.5, 0.5)), Arc::new(300.0), 1.33));
+
+    let transmitted: Vec<Vec3> = (0..200)
+        .filter_map(|_| scatter_from_inside(film.clone()))
+        .map(|(_, scattered)| scattered.dir.unit_vector())
+        .filter(|dir| dir.z > 0.0)
+        .collect();
+    assert!(!transmitted.is_empty());
+    assert!(transmitted.iter().any(|dir| dir.z < 0.99), "light went straight through a rough base");
+}
+
+#[test]
+fn only_dispersive_bases_need_tracing_at_one_wavelength() {
+    let thickness = Arc::new(400.0);
+    let metal = ThinFilm::new(Arc::new(Metal::new(Color::new(0.9, 0.6, 0.3), 0.0)), thickness.clone(), 1.4);
+    let glass = ThinFilm::new(Arc::new(Dielectric::new(1.5)), thickness.clone(), 1.4);
+    let prism = ThinFilm::new(Arc::new(Dielectric::cauchy(1.5046, 0.0042)), thickness, 1.4);
+
+    assert!(!metal.disperses());
+    assert!(!glass.disperses());
+    assert!(prism.disperses());
+}
@@ -0,0 +1,151 @@
+use std::fs;
+use std::io;
+use std::sync::Arc;
+
+use raytracing::util::{with_rng, Rng};
+use raytracing::{Color, HenyeyGreenstein, Hittable, Interval, Point3, Ray, Vec3, Volume, VoxelGrid};
+
+// a density rising across the grid in x, and a little in y
+fn ramp(x: f64, y: f64, _z: f64) -> f64 {
+    0.2 + 1.5 * x + 0.5 * y
+}
+
+#[test]
+fn densities_are_interpolated_between_voxel_centres() {
+    let grid = VoxelGrid::from_fn(8, 6, 4, ramp);
+    // between the outermost voxel centres, a linear density is found exactly
+    for i in 0..=20 {
+        for j in 0..=20 {
+            let (x, y) = (1.0 / 16.0 + 14.0 / 16.0 * i as f64 / 20.0, 1.0 / 12.0 + 10.0 / 12.0 * j as f64 / 20.0);
+            let p = Vec3::new(x, y, 0.3);
+            assert!((grid.density(p) - ramp(x, y, 0.3)).abs() < 1e-12, "the density at {p:?} is {}", grid.density(p));
+        }
+    }
+    assert_eq!(grid.density(Vec3::new(1.2, 0.5, 0.5)), 0.0);
+    assert_eq!(grid.density(Vec3::new(0.5, -0.1, 0.5)), 0.0);
+}
+
+// a NumPy file of 32 bit floats with the given shape, as `numpy.save` writes it
+fn npy(name: &str, shape: &str, values: &[f32]) -> std::path::PathBuf {
+    let mut header = format!("{{'descr': '<f4', 'fortran_order': False, 'shape': {shape}, }}");
+    while (header.len() + 11) % 64 != 0 {
+        header.push(' ');
+    }
+    header.push('\n');
+
+    let mut bytes = b"\x93NUMPY\x01\x00".to_vec();
+    bytes.extend((header.len() as u16).to_le_bytes());
+    bytes.extend(header.as_bytes());
+    for v in values {
+        bytes.extend(v.to_le_bytes());
+    }
+
+    let path = std::env::temp_dir().join(format!("raytracing-{}-{name}.npy", std::process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+#[test]
+fn numpy_files_load_with_x_fastest() {
+    let values: Vec<f32> = (0..24).map(|i| i as f32 / 24.0).collect();
+    let path = npy("grid", "(2, 3, 4)", &values);
+    let grid = VoxelGrid::load_npy(&path).unwrap();
+    fs::remove_file(path).unwrap();
+
+    // the centre of voxel x, y, z has the value at [z, y, x]
+    for z in 0..2 {
+        for y in 0..3 {
+            for x in 0..4 {
+                let centre = Vec3::new((x as f64 + 0.5) / 4.0, (y as f64 + 0.5) / 3.0, (z as f64 + 0.5) / 2.0);
+                let expected = values[(z * 3 + y) * 4 + x] as f64;
+                assert!((grid.density(centre) - expected).abs() < 1e-6, "voxel {x}, {y}, {z} is {}", grid.density(centre));
+            }
+        }
+    }
+}
+
+#[test]
+fn empty_numpy_files_are_refused() {
+    for (name, shape) in [("empty", "(0, 0, 0)"), ("flat", "(4, 0, 3)")] {
+        let path = npy(name, shape, &[]);
+        let error = VoxelGrid::load_npy(&path).err().expect("a grid with no voxels shouldn't load");
+        fs::remove_file(path).unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+}
+
+#[test]
+#[should_panic]
+fn grids_need_voxels() {
+    VoxelGrid::new(3, 0, 2, Vec::new());
+}
+
+// a volume filling the box from (-1, -1, -1) to (1, 1, 1), and rays through it along x, not of unit
+// length, so that steps have to be measured in distance rather than along the ray
+fn volume(grid: VoxelGrid, density: f64) -> (Arc<VoxelGrid>, Volume) {
+    let grid = Arc::new(grid);
+    let mat = Arc::new(HenyeyGreenstein::new(Color::new(1, 1, 1), 0.0));
+    (grid.clone(), Volume::new(grid, Point3::new(-1, -1, -1), Point3::new(1, 1, 1), density, mat))
+}
+
+fn ray(y: f64) -> Ray {
+    Ray::new(Point3::new(-3.0, y, 0.2), Vec3::new(2, 0, 0))
+}
+
+// the chance of light getting through along the ray at height `y`, from the density integrated
+// along it (the Beer–Lambert law), and by tracking through the volume: both the light ratio tracking
+// lets through, and how many rays delta tracking lets through without hitting anything
+fn through(grid: &VoxelGrid, volume: &Volume, density: f64, y: f64) -> (f64, f64, f64) {
+    let steps = 10_000;
+    let optical_depth: f64 = (0..steps).map(|i| {
+        let x = (i as f64 + 0.5) / steps as f64;
+        density * grid.density(Vec3::new(x, (y + 1.0) / 2.0, 0.6)) * 2.0 / steps as f64
+    }).sum();
+
+    let r = ray(y);
+    let ray_t = Interval::new(0.0, f64::INFINITY);
+    let samples = 200_000;
+    let mut rng = Rng::new(8, 2);
+    let (tracked, missed) = with_rng(&mut rng, || {
+        let tracked = (0..samples).map(|_| volume.transmittance(&r, ray_t)).sum::<f64>() / samples as f64;
+        let missed = (0..samples).filter(|_| volume.hit(&r, ray_t).is_none()).count() as f64 / samples as f64;
+        (tracked, missed)
+    });
+    ((-optical_depth).exp(), tracked, missed)
+}
+
+#[test]
+fn constant_density_follows_beer_lambert() {
+    let density = 0.6;
+    let (grid, volume) = volume(VoxelGrid::new(1, 1, 1, vec![1.0]), density);
+    let (expected, tracked, missed) = through(&grid, &volume, density, 0.0);
+    assert!((expected - (-2.0 * density).exp()).abs() < 1e-12);
+    assert!((tracked - expected).abs() < 0.005, "{tracked} of the light gets through rather than {expected}");
+    assert!((missed - expected).abs() < 0.005, "{missed} of the rays get through rather than {expected}");
+
+    // particles are hit as often at each depth as the exponential distribution of free paths says
+    let r = ray(0.0);
+    let mut rng = Rng::new(1, 6);
+    let samples = 100_000;
+    let mut first_half = 0.0;
+    with_rng(&mut rng, || {
+        for _ in 0..samples {
+            if volume.hit(&r, Interval::new(0.0, f64::INFINITY)).is_some_and(|rec| rec.p.x < 0.0) {
+                first_half += 1.0 / samples as f64;
+            }
+        }
+    });
+    let expected = 1.0 - (-density).exp();
+    assert!((first_half - expected).abs() < 0.005, "{first_half} of the rays hit the first half rather than {expected}");
+}
+
+#[test]
+fn varying_density_follows_beer_lambert() {
+    let density = 0.5;
+    let (grid, volume) = volume(VoxelGrid::from_fn(16, 8, 4, ramp), density);
+    for y in [-0.7, 0.1, 0.8] {
+        let (expected, tracked, missed) = through(&grid, &volume, density, y);
+        assert!((tracked - expected).abs() < 0.005, "at {y}, {tracked} of the light gets through rather than {expected}");
+        assert!((missed - expected).abs() < 0.005, "at {y}, {missed} of the rays get through rather than {expected}");
+    }
+}